@@ -13,13 +13,17 @@
 
 // You should have received a copy of the GNU General Public License
 // along with Thales.  If not, see <http://www.gnu.org/licenses/>.
-use ethereum_types::H256;
+use ethereum_types::{H160, H256, U256};
+use fc_rpc_core::types::Bytes;
 use futures::{compat::Compat, future::BoxFuture};
 use jsonrpc_core::Result as RpcResult;
 use jsonrpc_derive::rpc;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+pub use fc_rpc_core::types::CallRequest;
 pub use rpc_impl_Debug::gen_server::Debug as DebugServer;
+pub use thales_rpc_core_trace::{RequestBlockId, RequestBlockTag};
 pub mod types {
 	pub use thales_rpc_primitives_debug::single;
 }
@@ -32,11 +36,54 @@ pub struct TraceParams {
 	pub disable_storage: Option<bool>,
 	pub disable_memory: Option<bool>,
 	pub disable_stack: Option<bool>,
-	/// Javascript tracer (we just check if it's Blockscout tracer string)
+	/// Native tracer name (`callTracer` or `prestateTracer`), or javascript tracer (we just check
+	/// if it's Blockscout tracer string)
 	pub tracer: Option<String>,
+	pub tracer_config: Option<TracerConfig>,
 	pub timeout: Option<String>,
 }
 
+/// Options of the native tracers.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracerConfig {
+	/// Make the `prestateTracer` return the changed state before and after the transaction.
+	pub diff_mode: Option<bool>,
+	/// Make the `callTracer` include the logs emitted by each call frame.
+	pub with_log: Option<bool>,
+}
+
+/// Parameters of `debug_traceCall`: the tracing parameters and the state of the accounts to
+/// override before tracing the call.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceCallParams {
+	#[serde(flatten)]
+	pub trace_params: TraceParams,
+	pub state_overrides: Option<BTreeMap<H160, StateOverride>>,
+}
+
+/// State of an account to override before tracing a call.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StateOverride {
+	pub balance: Option<U256>,
+	pub nonce: Option<U256>,
+	pub code: Option<Bytes>,
+	/// Replaces the whole storage of the account.
+	pub state: Option<BTreeMap<H256, H256>>,
+	/// Replaces only the given storage entries of the account.
+	pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+/// Trace of one of the transactions of a traced block.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTransactionTrace {
+	pub tx_hash: H256,
+	pub result: single::TransactionTrace,
+}
+
 #[rpc(server)]
 pub trait Debug {
 	#[rpc(name = "debug_traceTransaction")]
@@ -45,4 +92,26 @@ pub trait Debug {
 		transaction_hash: H256,
 		params: Option<TraceParams>,
 	) -> Compat<BoxFuture<'static, RpcResult<single::TransactionTrace>>>;
+
+	#[rpc(name = "debug_traceCall")]
+	fn trace_call(
+		&self,
+		request: CallRequest,
+		block_number: RequestBlockId,
+		params: Option<TraceCallParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<single::TransactionTrace>>>;
+
+	#[rpc(name = "debug_traceBlockByNumber")]
+	fn trace_block_by_number(
+		&self,
+		block_number: RequestBlockId,
+		params: Option<TraceParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<Vec<BlockTransactionTrace>>>>;
+
+	#[rpc(name = "debug_traceBlockByHash")]
+	fn trace_block_by_hash(
+		&self,
+		block_hash: H256,
+		params: Option<TraceParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<Vec<BlockTransactionTrace>>>>;
 }
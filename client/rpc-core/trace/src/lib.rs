@@ -14,11 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with Thales.  If not, see <http://www.gnu.org/licenses/>.
 
-use ethereum_types::H160;
+use ethereum_types::{H160, H256, U256};
+pub use fc_rpc_core::types::{Bytes, Index};
 use futures::{compat::Compat, future::BoxFuture};
 use jsonrpc_derive::rpc;
 pub use thales_rpc_primitives_debug::block::TransactionTrace;
-use serde::{de::Error, Deserialize, Deserializer};
+use serde::{de::Error, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 
 pub use rpc_impl_Trace::gen_server::Trace as TraceServer;
 
@@ -29,6 +31,32 @@ pub trait Trace {
 		&self,
 		filter: FilterRequest,
 	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<TransactionTrace>>>>;
+
+	#[rpc(name = "trace_block")]
+	fn block(
+		&self,
+		block: RequestBlockId,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<TransactionTrace>>>>;
+
+	#[rpc(name = "trace_transaction")]
+	fn transaction(
+		&self,
+		transaction_hash: H256,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<TransactionTrace>>>>;
+
+	#[rpc(name = "trace_get")]
+	fn get(
+		&self,
+		transaction_hash: H256,
+		indices: Vec<Index>,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Option<TransactionTrace>>>>;
+
+	#[rpc(name = "trace_replayTransaction")]
+	fn replay_transaction(
+		&self,
+		transaction_hash: H256,
+		trace_types: Vec<ReplayTraceType>,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<TraceResults>>>;
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
@@ -53,10 +81,86 @@ pub struct FilterRequest {
 	pub count: Option<u32>,
 }
 
+/// Outputs requested from `trace_replayTransaction`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplayTraceType {
+	Trace,
+	StateDiff,
+	VmTrace,
+}
+
+/// Result of `trace_replayTransaction`. Each output is only present if it was requested.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceResults {
+	/// Output of the transaction, or its revert data if it reverted. Empty for contract creations
+	/// and failed transactions.
+	pub output: Bytes,
+	pub trace: Option<Vec<TransactionTrace>>,
+	pub state_diff: Option<BTreeMap<H160, AccountDiff>>,
+	/// VM traces are not supported, requesting them is an error. It is always null.
+	pub vm_trace: Option<()>,
+}
+
+/// Changes of an account made by a transaction.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct AccountDiff {
+	pub balance: Diff<U256>,
+	pub nonce: Diff<U256>,
+	pub code: Diff<Bytes>,
+	pub storage: BTreeMap<H256, Diff<H256>>,
+}
+
+/// Change of a value, serialized as `"="` if it didn't change, `{"+":..}` for a value of a
+/// created account, `{"-":..}` for a value of a destroyed account or `{"*":{"from":..,"to":..}}`
+/// otherwise.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Diff<T> {
+	Same,
+	Born(T),
+	Died(T),
+	Changed { from: T, to: T },
+}
+
+impl<T: Serialize> Serialize for Diff<T> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		#[derive(Serialize)]
+		struct Changed<'a, T> {
+			from: &'a T,
+			to: &'a T,
+		}
+
+		match self {
+			Diff::Same => serializer.serialize_str("="),
+			Diff::Born(value) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("+", value)?;
+				map.end()
+			}
+			Diff::Died(value) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("-", value)?;
+				map.end()
+			}
+			Diff::Changed { from, to } => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("*", &Changed { from, to })?;
+				map.end()
+			}
+		}
+	}
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum RequestBlockId {
 	Number(#[serde(deserialize_with = "deserialize_u32_0x")] u32),
+	/// Hash of an Ethereum block.
+	Hash(H256),
 	Tag(RequestBlockTag),
 }
 
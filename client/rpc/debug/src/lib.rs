@@ -19,29 +19,67 @@ use futures::{
 	FutureExt, SinkExt, StreamExt,
 };
 use jsonrpc_core::Result as RpcResult;
-pub use thales_rpc_core_debug::{Debug as DebugT, DebugServer, TraceParams};
+pub use thales_rpc_core_debug::{
+	BlockTransactionTrace, CallRequest, Debug as DebugT, DebugServer, RequestBlockId,
+	RequestBlockTag, TraceCallParams, TraceParams,
+};
 
 use tokio::{
 	self,
 	sync::{oneshot, Semaphore},
+	time::timeout,
 };
 
-use ethereum_types::{H128, H256};
+use ethereum::Transaction as EthereumTransaction;
+use ethereum_types::{H128, H256, U256};
 use fc_rpc::{frontier_backend_client, internal_err};
 use fp_rpc::EthereumRuntimeRPCApi;
-use thales_rpc_primitives_debug::{single, DebugRuntimeApi};
+use thales_rpc_primitives_debug::{block, single, v1, CallResult, CreateResult, DebugRuntimeApi};
+use thales_rpc_trace::CacheRequester;
 use sc_client_api::backend::Backend;
-use sp_api::{BlockId, HeaderT, ProvideRuntimeApi};
+use sp_api::{ApiExt, BlockId, HeaderT, ProvideRuntimeApi};
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{
 	Backend as BlockchainBackend, Error as BlockChainError, HeaderBackend, HeaderMetadata,
 };
 use sp_runtime::traits::Block as BlockT;
 use sp_utils::mpsc::TracingUnboundedSender;
-use std::{future::Future, marker::PhantomData, str::FromStr, sync::Arc};
+use std::{
+	collections::BTreeMap, future::Future, marker::PhantomData, str::FromStr, sync::Arc,
+	time::Duration,
+};
 
 pub type Responder = oneshot::Sender<RpcResult<single::TransactionTrace>>;
-pub type DebugRequester = TracingUnboundedSender<((H256, Option<TraceParams>), Responder)>;
+pub type BlockResponder = oneshot::Sender<RpcResult<Vec<BlockTransactionTrace>>>;
+pub type DebugRequester = TracingUnboundedSender<DebugRequest>;
+
+/// Block to trace, given either by number or by Ethereum block hash.
+#[derive(Clone, Copy, Debug)]
+pub enum RequestBlock {
+	Number(RequestBlockId),
+	Hash(H256),
+}
+
+/// Limits of the raw traces, bounding the memory the runtime uses to record their step logs, and
+/// the time it spends replaying them.
+#[derive(Clone, Copy, Debug)]
+pub struct RawTraceLimits {
+	pub max_step_logs: u32,
+	pub max_step_logs_bytes: u32,
+	/// Estimate of the number of steps a raw trace replays per second, turning the timeout of a
+	/// request into a step budget for the runtime.
+	pub steps_per_second: u32,
+}
+
+/// Requests sent by the RPC handler to the service-defined task.
+pub enum DebugRequest {
+	/// `debug_traceTransaction`
+	Transaction((H256, Option<TraceParams>), Responder),
+	/// `debug_traceCall`
+	Call((CallRequest, RequestBlock, Option<TraceCallParams>), Responder),
+	/// `debug_traceBlockByNumber` and `debug_traceBlockByHash`
+	Block((RequestBlock, Option<TraceParams>), BlockResponder),
+}
 
 pub struct Debug {
 	pub requester: DebugRequester,
@@ -51,23 +89,20 @@ impl Debug {
 	pub fn new(requester: DebugRequester) -> Self {
 		Self { requester }
 	}
-}
 
-impl DebugT for Debug {
-	/// Handler for `debug_traceTransaction` request. Communicates with the service-defined task
-	/// using channels.
-	fn trace_transaction(
-		&self,
-		transaction_hash: H256,
-		params: Option<TraceParams>,
-	) -> Compat<BoxFuture<'static, RpcResult<single::TransactionTrace>>> {
+	/// Communicates with the service-defined task using channels.
+	fn request<T, F>(&self, request: F) -> Compat<BoxFuture<'static, RpcResult<T>>>
+	where
+		T: Send + 'static,
+		F: FnOnce(oneshot::Sender<RpcResult<T>>) -> DebugRequest + Send + 'static,
+	{
 		let mut requester = self.requester.clone();
 
 		async move {
 			let (tx, rx) = oneshot::channel();
 			// Send a message from the rpc handler to the service level task.
 			requester
-				.send(((transaction_hash, params), tx))
+				.send(request(tx))
 				.await
 				.map_err(|err| {
 					internal_err(format!(
@@ -86,6 +121,49 @@ impl DebugT for Debug {
 	}
 }
 
+impl DebugT for Debug {
+	/// Handler for `debug_traceTransaction` request.
+	fn trace_transaction(
+		&self,
+		transaction_hash: H256,
+		params: Option<TraceParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<single::TransactionTrace>>> {
+		self.request(move |tx| DebugRequest::Transaction((transaction_hash, params), tx))
+	}
+
+	/// Handler for `debug_traceCall` request.
+	fn trace_call(
+		&self,
+		request: CallRequest,
+		block_number: RequestBlockId,
+		params: Option<TraceCallParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<single::TransactionTrace>>> {
+		self.request(move |tx| {
+			DebugRequest::Call((request, RequestBlock::Number(block_number), params), tx)
+		})
+	}
+
+	/// Handler for `debug_traceBlockByNumber` request.
+	fn trace_block_by_number(
+		&self,
+		block_number: RequestBlockId,
+		params: Option<TraceParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<Vec<BlockTransactionTrace>>>> {
+		self.request(move |tx| {
+			DebugRequest::Block((RequestBlock::Number(block_number), params), tx)
+		})
+	}
+
+	/// Handler for `debug_traceBlockByHash` request.
+	fn trace_block_by_hash(
+		&self,
+		block_hash: H256,
+		params: Option<TraceParams>,
+	) -> Compat<BoxFuture<'static, RpcResult<Vec<BlockTransactionTrace>>>> {
+		self.request(move |tx| DebugRequest::Block((RequestBlock::Hash(block_hash), params), tx))
+	}
+}
+
 pub struct DebugHandler<B: BlockT, C, BE>(PhantomData<(B, C, BE)>);
 
 impl<B, C, BE> DebugHandler<B, C, BE>
@@ -95,61 +173,124 @@ where
 	C: HeaderMetadata<B, Error = BlockChainError> + HeaderBackend<B>,
 	C: Send + Sync + 'static,
 	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	B::Header: HeaderT<Number = u32>,
 	C::Api: BlockBuilder<B>,
 	C::Api: DebugRuntimeApi<B>,
 	C::Api: EthereumRuntimeRPCApi<B>,
 {
 	/// Task spawned at service level that listens for messages on the rpc channel and spawns
-	/// blocking tasks using a permit pool.
+	/// blocking tasks using a permit pool. Blocks are traced with the `trace_filter` cache when
+	/// it is available and holds the requested trace type.
+	///
+	/// Requests fail when their `timeout` expires. A replay that already started can't be
+	/// interrupted, it keeps running in the background until done and holds its permit until
+	/// then. Raw traces, whose replay is much slower than the execution of the block, are given
+	/// a step budget matching the timeout so their replay stops around the same time. Other
+	/// traces replay the transactions at the speed of their execution.
 	pub fn task(
 		client: Arc<C>,
 		backend: Arc<BE>,
 		frontier_backend: Arc<fc_db::Backend<B>>,
 		permit_pool: Arc<Semaphore>,
+		cache_requester: Option<CacheRequester>,
+		raw_trace_limits: RawTraceLimits,
 	) -> (impl Future<Output = ()>, DebugRequester) {
 		let (tx, mut rx): (DebugRequester, _) =
 			sp_utils::mpsc::tracing_unbounded("debug-requester");
 
 		let fut = async move {
 			loop {
-				if let Some(((transaction_hash, params), response_tx)) = rx.next().await {
-					let client = client.clone();
-					let backend = backend.clone();
-					let frontier_backend = frontier_backend.clone();
-					let permit_pool = permit_pool.clone();
-					// Note on spawned tasks https://tokio.rs/tokio/tutorial/spawning#tasks.
-					//
-					// Substrate uses the default value for `core_threads` (number of cores of the
-					// machine running the node) and `max_threads` (512 total).
-					//
-					// Task below is spawned in the substrate's built tokio::Runtime, so they share
-					// the same thread pool as the rest of the service-spawned tasks. Additionally,
-					// blocking tasks use a more restrictive permit pool shared by trace modules.
-					// https://docs.rs/tokio/0.2.23/tokio/sync/struct.Semaphore.html
-					tokio::task::spawn(async move {
-						let _ = response_tx.send(
-							async {
-								let _permit = permit_pool.acquire().await;
-								tokio::task::spawn_blocking(move || {
-									Self::handle_request(
-										client.clone(),
-										backend.clone(),
-										frontier_backend.clone(),
-										transaction_hash,
-										params,
-									)
-								})
-								.await
-								.map_err(|e| {
-									internal_err(format!(
-										"Internal error on spawned task : {:?}",
-										e
-									))
-								})?
-							}
-							.await,
-						);
-					});
+				match rx.next().await {
+					Some(DebugRequest::Transaction((transaction_hash, params), response_tx)) => {
+						let client = client.clone();
+						let backend = backend.clone();
+						let frontier_backend = frontier_backend.clone();
+						let permit_pool = permit_pool.clone();
+						// Note on spawned tasks https://tokio.rs/tokio/tutorial/spawning#tasks.
+						//
+						// Substrate uses the default value for `core_threads` (number of cores of
+						// the machine running the node) and `max_threads` (512 total).
+						//
+						// Task below is spawned in the substrate's built tokio::Runtime, so they
+						// share the same thread pool as the rest of the service-spawned tasks.
+						// Additionally, blocking tasks use a more restrictive permit pool shared by
+						// trace modules.
+						// https://docs.rs/tokio/0.2.23/tokio/sync/struct.Semaphore.html
+						tokio::task::spawn(async move {
+							let _ = response_tx.send(
+								async {
+									let request_timeout = request_timeout(params.as_ref())?;
+									let trace = Self::spawn_blocking_with_permit(
+										permit_pool,
+										move || {
+											Self::handle_request(
+												client,
+												backend,
+												frontier_backend,
+												transaction_hash,
+												params,
+												raw_trace_limits,
+											)
+										},
+									);
+									with_timeout(request_timeout, trace).await
+								}
+								.await,
+							);
+						});
+					}
+					Some(DebugRequest::Call((request, block, params), response_tx)) => {
+						let client = client.clone();
+						let frontier_backend = frontier_backend.clone();
+						let permit_pool = permit_pool.clone();
+						tokio::task::spawn(async move {
+							let _ = response_tx.send(
+								async {
+									let request_timeout = request_timeout(
+										params.as_ref().map(|params| &params.trace_params),
+									)?;
+									let trace = Self::spawn_blocking_with_permit(
+										permit_pool,
+										move || {
+											Self::handle_call_request(
+												client,
+												frontier_backend,
+												request,
+												block,
+												params,
+												raw_trace_limits,
+											)
+										},
+									);
+									with_timeout(request_timeout, trace).await
+								}
+								.await,
+							);
+						});
+					}
+					Some(DebugRequest::Block((block, params), response_tx)) => {
+						let client = client.clone();
+						let backend = backend.clone();
+						let frontier_backend = frontier_backend.clone();
+						let permit_pool = permit_pool.clone();
+						let cache_requester = cache_requester.clone();
+						tokio::task::spawn(async move {
+							let _ = response_tx.send(
+								Self::trace_block(
+									client,
+									backend,
+									frontier_backend,
+									permit_pool,
+									cache_requester,
+									block,
+									params,
+									raw_trace_limits,
+								)
+								.await,
+							);
+						});
+					}
+					None => {}
 				}
 			}
 		};
@@ -168,6 +309,7 @@ where
 		frontier_backend: Arc<fc_db::Backend<B>>,
 		transaction_hash: H256,
 		params: Option<TraceParams>,
+		raw_trace_limits: RawTraceLimits,
 	) -> RpcResult<single::TransactionTrace> {
 		let (hash, index) = match frontier_backend_client::load_transactions::<B, C>(
 			client.as_ref(),
@@ -197,6 +339,8 @@ where
 		// Get parent blockid.
 		let parent_block_id = BlockId::Hash(*header.parent_hash());
 
+		let api_version = debug_api_version::<B, _>(&*api, &parent_block_id)?;
+
 		// Get the extrinsics.
 		let ext = blockchain.body(reference_id).unwrap().unwrap();
 
@@ -207,38 +351,21 @@ where
 		};
 
 		// Set trace type
-		let trace_type = match params {
-			Some(TraceParams {
-				tracer: Some(tracer),
-				..
-			}) => {
-				let hash: H128 = sp_io::hashing::twox_128(&tracer.as_bytes()).into();
-				let blockscout_hash = H128::from_str("0x94d9f08796f91eb13a2e82a6066882f7").unwrap();
-				if hash == blockscout_hash {
-					single::TraceType::CallList
-				} else {
-					return Err(internal_err(format!(
-						"javascript based tracing is not available (hash :{:?})",
-						hash
-					)));
-				}
-			}
-			Some(params) => single::TraceType::Raw {
-				disable_storage: params.disable_storage.unwrap_or(false),
-				disable_memory: params.disable_memory.unwrap_or(false),
-				disable_stack: params.disable_stack.unwrap_or(false),
-			},
-			_ => single::TraceType::Raw {
-				disable_storage: false,
-				disable_memory: false,
-				disable_stack: false,
-			},
-		};
+		let trace_type = trace_type(params, raw_trace_limits)?;
 
 		// Get the actual ethereum transaction.
 		if let Some(block) = reference_block {
 			let transactions = block.transactions;
 			if let Some(transaction) = transactions.get(index) {
+				if api_version < 2 {
+					return trace_transaction_v1::<B, _>(
+						&*client.runtime_api(),
+						&parent_block_id,
+						ext,
+						transaction,
+						trace_type,
+					);
+				}
 				return client
 					.runtime_api()
 					.trace_transaction(&parent_block_id, ext, &transaction, trace_type)
@@ -248,4 +375,559 @@ where
 		}
 		return Err(internal_err("Runtime block call failed".to_string()));
 	}
+
+	/// Traces all the Ethereum transactions of a block, within the timeout of the request.
+	///
+	/// Call lists are taken from the `trace_filter` cache when available, which replays each
+	/// block only once for both modules. Other trace types replay the block in a blocking task
+	/// using the shared permit pool.
+	async fn trace_block(
+		client: Arc<C>,
+		backend: Arc<BE>,
+		frontier_backend: Arc<fc_db::Backend<B>>,
+		permit_pool: Arc<Semaphore>,
+		cache_requester: Option<CacheRequester>,
+		block: RequestBlock,
+		params: Option<TraceParams>,
+		raw_trace_limits: RawTraceLimits,
+	) -> RpcResult<Vec<BlockTransactionTrace>> {
+		let request_timeout = request_timeout(params.as_ref())?;
+		let trace_type = trace_type(params, raw_trace_limits)?;
+
+		let header = Self::block_header(client.as_ref(), frontier_backend.as_ref(), block)?;
+		// No transactions in the genesis block.
+		if *header.number() == 0 {
+			return Ok(vec![]);
+		}
+
+		let traces = async move {
+			match cache_requester {
+				Some(cache_requester) if trace_type == single::TraceType::CallList => {
+					Self::cached_call_lists(&cache_requester, header.hash()).await
+				}
+				_ => {
+					Self::spawn_blocking_with_permit(permit_pool, move || {
+						Self::handle_block_request(client, backend, header, trace_type)
+					})
+					.await
+				}
+			}
+		};
+
+		with_timeout(request_timeout, traces).await
+	}
+
+	/// Runs `f` in a blocking task once a permit of the pool is available. The permit is held by
+	/// the blocking task, so it is only released when `f` returns, even if the request was
+	/// dropped by its timeout in the meantime.
+	async fn spawn_blocking_with_permit<T, F>(permit_pool: Arc<Semaphore>, f: F) -> RpcResult<T>
+	where
+		T: Send + 'static,
+		F: FnOnce() -> RpcResult<T> + Send + 'static,
+	{
+		let permit = permit_pool.acquire_owned().await;
+		tokio::task::spawn_blocking(move || {
+			let _permit = permit;
+			f()
+		})
+		.await
+		.map_err(|e| internal_err(format!("Internal error on spawned task : {:?}", e)))?
+	}
+
+	/// Traces a call that is not part of the chain on top of the state of a block, after
+	/// overriding the state of some accounts.
+	fn handle_call_request(
+		client: Arc<C>,
+		frontier_backend: Arc<fc_db::Backend<B>>,
+		request: CallRequest,
+		block: RequestBlock,
+		params: Option<TraceCallParams>,
+		raw_trace_limits: RawTraceLimits,
+	) -> RpcResult<single::TransactionTrace> {
+		let header = Self::block_header(client.as_ref(), frontier_backend.as_ref(), block)?;
+		let block_id = BlockId::Hash(header.hash());
+		let api = client.runtime_api();
+		if debug_api_version::<B, _>(&*api, &block_id)? < 2 {
+			return Err(internal_err(
+				"The runtime of this block doesn't support tracing calls".to_string(),
+			));
+		}
+
+		let (trace_params, state_overrides) = match params {
+			Some(TraceCallParams {
+				trace_params,
+				state_overrides,
+			}) => (Some(trace_params), state_overrides.unwrap_or_default()),
+			None => (None, BTreeMap::new()),
+		};
+		let trace_type = trace_type(trace_params, raw_trace_limits)?;
+
+		let state_overrides = state_overrides
+			.into_iter()
+			.map(|(address, state_override)| {
+				let state_override = single::StateOverride {
+					balance: state_override.balance,
+					nonce: state_override.nonce,
+					code: state_override.code.map(|code| code.0),
+					state: state_override.state,
+					state_diff: state_override.state_diff.unwrap_or_default(),
+				};
+				(address, state_override)
+			})
+			.collect();
+
+		let CallRequest {
+			from,
+			to,
+			gas,
+			value,
+			data,
+			..
+		} = request;
+
+		// Like `eth_call`, a call without gas limit can use all the gas of a block.
+		let gas_limit = match gas {
+			Some(gas) => gas,
+			None => api
+				.current_block(&block_id)
+				.map_err(|e| internal_err(format!("Runtime block call failed: {:?}", e)))?
+				.map(|block| block.header.gas_limit)
+				.ok_or_else(|| internal_err("Ethereum block not found".to_string()))?,
+		};
+
+		api.trace_call(
+			&block_id,
+			from.unwrap_or_default(),
+			to,
+			data.map(|data| data.0).unwrap_or_default(),
+			value.unwrap_or_default(),
+			gas_limit,
+			state_overrides,
+			trace_type,
+		)
+		.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?
+		.map_err(|e| internal_err(format!("DispatchError: {:?}", e)))
+	}
+
+	/// Header of the Substrate block to trace.
+	fn block_header(
+		client: &C,
+		frontier_backend: &fc_db::Backend<B>,
+		block: RequestBlock,
+	) -> RpcResult<B::Header> {
+		let block_id = match block {
+			RequestBlock::Number(RequestBlockId::Number(number)) => BlockId::Number(number),
+			RequestBlock::Number(RequestBlockId::Tag(RequestBlockTag::Latest)) => {
+				BlockId::Number(client.info().best_number)
+			}
+			RequestBlock::Number(RequestBlockId::Tag(RequestBlockTag::Earliest)) => {
+				BlockId::Number(0)
+			}
+			RequestBlock::Number(RequestBlockId::Tag(RequestBlockTag::Pending)) => {
+				return Err(internal_err("'pending' is not supported".to_string()))
+			}
+			RequestBlock::Hash(hash) | RequestBlock::Number(RequestBlockId::Hash(hash)) => {
+				match frontier_backend_client::load_hash::<B, C>(client, frontier_backend, hash) {
+					Ok(Some(block_id)) => block_id,
+					Ok(None) => return Err(internal_err("Block hash not found".to_string())),
+					Err(e) => return Err(e),
+				}
+			}
+		};
+
+		client
+			.header(block_id)
+			.map_err(|e| {
+				internal_err(format!(
+					"Error when fetching block {} header : {:?}",
+					block_id, e
+				))
+			})?
+			.ok_or_else(|| internal_err(format!("Block {} don't exist", block_id)))
+	}
+
+	/// Call lists of the transactions of a block, built from the traces of the `trace_filter`
+	/// cache.
+	async fn cached_call_lists(
+		cache_requester: &CacheRequester,
+		substrate_hash: H256,
+	) -> RpcResult<Vec<BlockTransactionTrace>> {
+		let batch_id = cache_requester.start_batch(vec![substrate_hash]).await?;
+		let traces = cache_requester.get_traces(substrate_hash).await;
+		cache_requester.stop_batch(batch_id).await;
+
+		// Traces are ordered by transaction, and each transaction has at least one trace.
+		let mut transactions: Vec<BlockTransactionTrace> = vec![];
+		for trace in traces? {
+			if transactions.len() <= trace.transaction_position as usize {
+				transactions.push(BlockTransactionTrace {
+					tx_hash: trace.transaction_hash,
+					result: single::TransactionTrace::CallList(vec![]),
+				});
+			}
+			if let Some(BlockTransactionTrace {
+				result: single::TransactionTrace::CallList(calls),
+				..
+			}) = transactions.last_mut()
+			{
+				calls.push(call_list_entry(trace)?);
+			}
+		}
+
+		Ok(transactions)
+	}
+
+	/// Replays all the Ethereum transactions of a block in the Runtime, over the state of its
+	/// parent block.
+	fn handle_block_request(
+		client: Arc<C>,
+		backend: Arc<BE>,
+		header: B::Header,
+		trace_type: single::TraceType,
+	) -> RpcResult<Vec<BlockTransactionTrace>> {
+		let substrate_block_id = BlockId::Hash(header.hash());
+		let parent_block_id = BlockId::Hash(*header.parent_hash());
+		let api = client.runtime_api();
+		let api_version = debug_api_version::<B, _>(&*api, &parent_block_id)?;
+
+		let statuses = api
+			.current_transaction_statuses(&substrate_block_id)
+			.map_err(|e| internal_err(format!("Runtime block call failed: {:?}", e)))?
+			.ok_or_else(|| internal_err("Ethereum block not found".to_string()))?;
+
+		let extrinsics = backend
+			.blockchain()
+			.body(substrate_block_id)
+			.map_err(|e| internal_err(format!("Blockchain error: {:?}", e)))?
+			.ok_or_else(|| internal_err("Block body not found".to_string()))?;
+
+		// The step budget of a raw trace is shared by the transactions of the block.
+		let trace_type = match trace_type {
+			single::TraceType::Raw {
+				disable_storage,
+				disable_memory,
+				disable_stack,
+				max_step_logs,
+				max_step_logs_bytes,
+				max_steps,
+			} => single::TraceType::Raw {
+				disable_storage,
+				disable_memory,
+				disable_stack,
+				max_step_logs,
+				max_step_logs_bytes,
+				max_steps: max_steps / (statuses.len().max(1) as u32),
+			},
+			trace_type => trace_type,
+		};
+
+		let traces = if api_version < 2 {
+			// Version 1 only traces a single transaction, over a fresh state for each of them.
+			let transactions = api
+				.current_block(&substrate_block_id)
+				.map_err(|e| internal_err(format!("Runtime block call failed: {:?}", e)))?
+				.ok_or_else(|| internal_err("Ethereum block not found".to_string()))?
+				.transactions;
+			transactions
+				.iter()
+				.map(|transaction| {
+					trace_transaction_v1::<B, _>(
+						&*client.runtime_api(),
+						&parent_block_id,
+						extrinsics.clone(),
+						transaction,
+						trace_type,
+					)
+				})
+				.collect::<RpcResult<Vec<_>>>()?
+		} else {
+			api.trace_block_transactions(&parent_block_id, extrinsics, trace_type)
+				.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?
+				.map_err(|e| internal_err(format!("DispatchError: {:?}", e)))?
+		};
+
+		if traces.len() != statuses.len() {
+			return Err(internal_err(
+				"Amount of traces doesn't match the amount of transactions".to_string(),
+			));
+		}
+
+		Ok(statuses
+			.into_iter()
+			.zip(traces)
+			.map(|(status, result)| BlockTransactionTrace {
+				tx_hash: status.transaction_hash,
+				result,
+			})
+			.collect())
+	}
+}
+
+/// Timeout requested by the tracing parameters.
+fn request_timeout(params: Option<&TraceParams>) -> RpcResult<Option<Duration>> {
+	match params.and_then(|params| params.timeout.as_ref()) {
+		Some(request_timeout) => Ok(Some(parse_timeout(request_timeout)?)),
+		None => Ok(None),
+	}
+}
+
+/// Version of the `DebugRuntimeApi` implemented by the runtime at `block_id`. Fails if the runtime
+/// doesn't implement it.
+fn debug_api_version<B, Api>(api: &Api, block_id: &BlockId<B>) -> RpcResult<u32>
+where
+	B: BlockT,
+	Api: ApiExt<B>,
+{
+	for version in (1..=2).rev() {
+		let supported = api
+			.has_api_with::<dyn DebugRuntimeApi<B>, _>(block_id, |v| v >= version)
+			.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?;
+		if supported {
+			return Ok(version);
+		}
+	}
+
+	Err(internal_err("The runtime of this block doesn't support tracing".to_string()))
+}
+
+/// Traces a transaction with version 1 of the `DebugRuntimeApi`, implemented by the runtimes
+/// that were live before version 2. Only the raw and call list traces are supported.
+fn trace_transaction_v1<B, Api>(
+	api: &Api,
+	parent_block_id: &BlockId<B>,
+	extrinsics: Vec<B::Extrinsic>,
+	transaction: &EthereumTransaction,
+	trace_type: single::TraceType,
+) -> RpcResult<single::TransactionTrace>
+where
+	B: BlockT,
+	Api: DebugRuntimeApi<B>,
+{
+	let trace_type = v1::TraceType::from_current(trace_type).ok_or_else(|| {
+		internal_err("The runtime of this block doesn't support this tracer".to_string())
+	})?;
+
+	#[allow(deprecated)]
+	let trace = api
+		.trace_transaction_before_version_2(parent_block_id, extrinsics, transaction, trace_type)
+		.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?
+		.map_err(|e| internal_err(format!("DispatchError: {:?}", e)))?;
+
+	Ok(trace.into())
+}
+
+/// Waits for a tracing future, failing once the timeout of the request expires.
+async fn with_timeout<T>(
+	request_timeout: Option<Duration>,
+	future: impl Future<Output = RpcResult<T>>,
+) -> RpcResult<T> {
+	match request_timeout {
+		Some(request_timeout) => timeout(request_timeout, future)
+			.await
+			.map_err(|_| internal_err("Tracing timed out".to_string()))?,
+		None => future.await,
+	}
+}
+
+/// Trace type requested by the tracing parameters.
+fn trace_type(
+	params: Option<TraceParams>,
+	raw_trace_limits: RawTraceLimits,
+) -> RpcResult<single::TraceType> {
+	// Raw traces without a timeout are not bounded in time.
+	let max_steps = match request_timeout(params.as_ref())? {
+		Some(request_timeout) => {
+			let max_steps = request_timeout.as_millis()
+				* u128::from(raw_trace_limits.steps_per_second)
+				/ 1_000;
+			max_steps.min(u128::from(u32::MAX)) as u32
+		}
+		None => u32::MAX,
+	};
+
+	Ok(match params {
+		Some(TraceParams {
+			tracer: Some(tracer),
+			tracer_config,
+			..
+		}) => {
+			let hash: H128 = sp_io::hashing::twox_128(&tracer.as_bytes()).into();
+			let blockscout_hash = H128::from_str("0x94d9f08796f91eb13a2e82a6066882f7").unwrap();
+			if tracer == "callTracer" {
+				single::TraceType::CallTracer {
+					with_log: tracer_config
+						.and_then(|config| config.with_log)
+						.unwrap_or(false),
+				}
+			} else if tracer == "prestateTracer" {
+				single::TraceType::PrestateTracer {
+					diff_mode: tracer_config
+						.and_then(|config| config.diff_mode)
+						.unwrap_or(false),
+				}
+			} else if hash == blockscout_hash {
+				single::TraceType::CallList
+			} else {
+				return Err(internal_err(format!(
+					"javascript based tracing is not available (hash :{:?})",
+					hash
+				)));
+			}
+		}
+		Some(params) => single::TraceType::Raw {
+			disable_storage: params.disable_storage.unwrap_or(false),
+			disable_memory: params.disable_memory.unwrap_or(false),
+			disable_stack: params.disable_stack.unwrap_or(false),
+			max_step_logs: raw_trace_limits.max_step_logs,
+			max_step_logs_bytes: raw_trace_limits.max_step_logs_bytes,
+			max_steps,
+		},
+		_ => single::TraceType::Raw {
+			disable_storage: false,
+			disable_memory: false,
+			disable_stack: false,
+			max_step_logs: raw_trace_limits.max_step_logs,
+			max_step_logs_bytes: raw_trace_limits.max_step_logs_bytes,
+			max_steps,
+		},
+	})
+}
+
+/// Parse a duration in the format of the `timeout` tracing option of geth, such as "300ms" or
+/// "1m30s".
+fn parse_timeout(timeout: &str) -> RpcResult<Duration> {
+	let invalid = || internal_err(format!("invalid timeout: {}", timeout));
+	if timeout.is_empty() {
+		return Err(invalid());
+	}
+
+	let mut duration = Duration::from_secs(0);
+	let mut rest = timeout;
+	while !rest.is_empty() {
+		let (amount, tail) = rest.split_at(
+			rest.find(|c: char| !c.is_ascii_digit() && c != '.')
+				.unwrap_or(rest.len()),
+		);
+		let (unit, tail) = tail.split_at(
+			tail.find(|c: char| c.is_ascii_digit() || c == '.')
+				.unwrap_or(tail.len()),
+		);
+
+		let amount: f64 = amount.parse().map_err(|_| invalid())?;
+		let unit_secs: f64 = match unit {
+			"ns" => 1e-9,
+			"us" | "µs" => 1e-6,
+			"ms" => 1e-3,
+			"s" => 1.0,
+			"m" => 60.0,
+			"h" => 3600.0,
+			_ => return Err(invalid()),
+		};
+		// `Duration::from_secs_f64` panics on values it can't represent.
+		let secs = amount * unit_secs;
+		if !secs.is_finite() || secs >= u64::MAX as f64 {
+			return Err(invalid());
+		}
+		duration = duration
+			.checked_add(Duration::from_secs_f64(secs))
+			.ok_or_else(invalid)?;
+		rest = tail;
+	}
+
+	Ok(duration)
+}
+
+/// Convert a trace of the `trace_filter` cache back into an entry of a Blockscout call list.
+/// Those traces don't keep the gas used by failed calls, which Blockscout ignores anyway, so it
+/// is reported as zero.
+fn call_list_entry(trace: block::TransactionTrace) -> RpcResult<single::Call> {
+	let (from, value, gas, gas_used, inner) = match (trace.action, trace.output) {
+		(
+			block::TransactionTraceAction::Call {
+				call_type,
+				from,
+				gas,
+				input,
+				to,
+				value,
+			},
+			output,
+		) => {
+			let (gas_used, res) = match output {
+				block::TransactionTraceOutput::Result(block::TransactionTraceResult::Call {
+					gas_used,
+					output,
+				}) => (gas_used, CallResult::Output(output)),
+				block::TransactionTraceOutput::Revert(revert) => {
+					(U256::zero(), CallResult::Revert(revert))
+				}
+				block::TransactionTraceOutput::Error(e) => (U256::zero(), CallResult::Error(e)),
+				_ => return Err(internal_err("Unexpected output of a call trace".to_string())),
+			};
+			let inner = single::CallInner::Call {
+				call_type,
+				to,
+				input,
+				res,
+			};
+			(from, value, gas, gas_used, inner)
+		}
+		(
+			block::TransactionTraceAction::Create {
+				from,
+				gas,
+				init,
+				value,
+				..
+			},
+			output,
+		) => {
+			let (gas_used, res) = match output {
+				block::TransactionTraceOutput::Result(block::TransactionTraceResult::Create {
+					address,
+					code,
+					gas_used,
+				}) => (
+					gas_used,
+					CreateResult::Success {
+						created_contract_address_hash: address,
+						created_contract_code: code,
+					},
+				),
+				block::TransactionTraceOutput::Revert(revert) => {
+					(U256::zero(), CreateResult::Revert { revert })
+				}
+				block::TransactionTraceOutput::Error(e) => {
+					(U256::zero(), CreateResult::Error { error: e })
+				}
+				_ => return Err(internal_err("Unexpected output of a create trace".to_string())),
+			};
+			(from, value, gas, gas_used, single::CallInner::Create { init, res })
+		}
+		(
+			block::TransactionTraceAction::Suicide {
+				address,
+				balance,
+				refund_address,
+			},
+			_,
+		) => {
+			let inner = single::CallInner::SelfDestruct {
+				balance,
+				refund_address,
+			};
+			(address, U256::zero(), U256::zero(), U256::zero(), inner)
+		}
+	};
+
+	Ok(single::Call {
+		from,
+		trace_address: trace.trace_address,
+		subtraces: trace.subtraces,
+		value,
+		gas,
+		gas_used,
+		inner,
+		logs: trace.logs,
+	})
 }
@@ -0,0 +1,363 @@
+// Copyright 2019-2020 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-disk index of the traces served by `trace_filter`, and the task filling it.
+//!
+//! The traces of a block are stored once traced, keyed by the Substrate block hash, so they
+//! survive the expiration of the `CacheTask` cache and node restarts. The addresses appearing in
+//! the traces of a block are indexed too, allowing `trace_filter` to skip blocks that can't
+//! match its address filters without decoding their traces.
+
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use kvdb::{DBTransaction, KeyValueDB};
+use std::{future::Future, marker::PhantomData, path::Path, sync::Arc};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use sc_client_api::{backend::Backend, BlockchainEvents};
+use sp_api::{HeaderT, ProvideRuntimeApi};
+use sp_block_builder::BlockBuilder;
+use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_runtime::traits::Block as BlockT;
+
+use ethereum_types::{H160, H256};
+use fp_rpc::EthereumRuntimeRPCApi;
+
+use crate::{trace_addresses, CacheTask, TransactionTrace};
+use thales_rpc_primitives_debug::DebugRuntimeApi;
+use thales_rpc_primitives_txpool::TxPoolRuntimeApi;
+
+mod columns {
+	pub const NUM_COLUMNS: u32 = 3;
+
+	/// Substrate block hash => SCALE encoded traces of the block.
+	pub const TRACES: u32 = 0;
+	/// Address ++ Substrate block hash => (), for the "from" addresses of the traces of the block.
+	pub const FROM_ADDRESS: u32 = 1;
+	/// Address ++ Substrate block hash => (), for the "to" addresses of the traces of the block.
+	pub const TO_ADDRESS: u32 = 2;
+}
+
+/// Key of the version of the index in the `TRACES` column, which can't be a block hash.
+const VERSION_KEY: &[u8] = b"version";
+/// Version of the encoding of the index. An index written with another version is cleared when
+/// opened, its traces could not be decoded anymore.
+const VERSION: u32 = 2;
+
+/// On-disk index of block traces.
+pub struct TraceIndex {
+	db: Arc<dyn KeyValueDB>,
+}
+
+impl TraceIndex {
+	/// Open the index stored at `path`, creating it if it doesn't exist.
+	pub fn open(path: &Path) -> std::result::Result<Self, String> {
+		let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(columns::NUM_COLUMNS);
+		let path = path
+			.to_str()
+			.ok_or_else(|| "Invalid trace index path".to_string())?;
+		let db = kvdb_rocksdb::Database::open(&db_config, path)
+			.map_err(|e| format!("Failed to open trace index : {:?}", e))?;
+
+		let index = Self { db: Arc::new(db) };
+		index.check_version()?;
+
+		Ok(index)
+	}
+
+	/// Clear the index if it was written with another version of its encoding.
+	fn check_version(&self) -> std::result::Result<(), String> {
+		let version = self
+			.db
+			.get(columns::TRACES, VERSION_KEY)
+			.map_err(|e| format!("Failed to read the trace index version : {:?}", e))?;
+		if version.as_deref() == Some(&VERSION.encode()[..]) {
+			return Ok(());
+		}
+
+		tracing::info!("Clearing the trace index, written with another version.");
+
+		let mut transaction = DBTransaction::new();
+		for column in 0..columns::NUM_COLUMNS {
+			transaction.delete_prefix(column, &[]);
+		}
+		transaction.put_vec(columns::TRACES, VERSION_KEY, VERSION.encode());
+
+		self.db
+			.write(transaction)
+			.map_err(|e| format!("Failed to clear the trace index : {:?}", e))
+	}
+
+	/// Whether the traces of this block are stored in the index.
+	pub fn contains(&self, block: H256) -> bool {
+		matches!(self.db.get(columns::TRACES, block.as_bytes()), Ok(Some(_)))
+	}
+
+	/// Traces of this block, if they are stored in the index.
+	pub fn traces(&self, block: H256) -> Option<Vec<TransactionTrace>> {
+		let encoded = match self.db.get(columns::TRACES, block.as_bytes()) {
+			Ok(encoded) => encoded?,
+			Err(e) => {
+				tracing::warn!("Failed to read block {} from the trace index : {:?}", block, e);
+				return None;
+			}
+		};
+
+		match Vec::<TransactionTrace>::decode(&mut &encoded[..]) {
+			Ok(traces) => Some(traces),
+			Err(e) => {
+				tracing::warn!("Failed to decode indexed traces of block {} : {:?}", block, e);
+				None
+			}
+		}
+	}
+
+	/// Whether the traces of this block may match the address filters of `trace_filter`. Blocks
+	/// which are not indexed may always match.
+	pub fn may_match(&self, block: H256, from_address: &[H160], to_address: &[H160]) -> bool {
+		if (from_address.is_empty() && to_address.is_empty()) || !self.contains(block) {
+			return true;
+		}
+
+		let indexed = |column, addresses: &[H160]| {
+			addresses.iter().any(|address| {
+				matches!(self.db.get(column, &address_key(address, block)), Ok(Some(_)))
+			})
+		};
+
+		indexed(columns::FROM_ADDRESS, from_address) || indexed(columns::TO_ADDRESS, to_address)
+	}
+
+	/// Store the traces of this block and index their addresses.
+	pub fn insert(
+		&self,
+		block: H256,
+		traces: &[TransactionTrace],
+	) -> std::result::Result<(), String> {
+		let mut transaction = DBTransaction::new();
+
+		for trace in traces {
+			let (from, to) = trace_addresses(trace);
+			transaction.put(columns::FROM_ADDRESS, &address_key(&from, block), &[]);
+			if let Some(to) = to {
+				transaction.put(columns::TO_ADDRESS, &address_key(&to, block), &[]);
+			}
+		}
+
+		// The traces are written with the addresses, so an indexed block always has its
+		// addresses indexed.
+		transaction.put_vec(columns::TRACES, block.as_bytes(), traces.encode());
+
+		self.db
+			.write(transaction)
+			.map_err(|e| format!("Failed to write block {} in the trace index : {:?}", block, e))
+	}
+}
+
+fn address_key(address: &H160, block: H256) -> Vec<u8> {
+	let mut key = address.as_bytes().to_vec();
+	key.extend_from_slice(block.as_bytes());
+	key
+}
+
+/// Type wrapper for the indexer task, generic over the Client, Block and Backend types.
+pub struct IndexerTask<B, C, BE>(PhantomData<(B, C, BE)>);
+
+impl<B, C, BE> IndexerTask<B, C, BE>
+where
+	BE: Backend<B> + 'static,
+	C: ProvideRuntimeApi<B> + BlockchainEvents<B>,
+	C: HeaderMetadata<B, Error = BlockChainError> + HeaderBackend<B>,
+	C: Send + Sync + 'static,
+	B: BlockT<Hash = H256> + Send + Sync + 'static,
+	B::Header: HeaderT<Number = u32>,
+	C::Api: BlockBuilder<B>,
+	C::Api: DebugRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	C::Api: TxPoolRuntimeApi<B>,
+{
+	/// Create a new indexer task, tracing each imported block once and storing its traces in
+	/// the index. It shares the blocking permits of the RPC tracing tasks.
+	///
+	/// Blocks imported during the initial sync don't produce import notifications, and are
+	/// indexed when `trace_filter` traces them.
+	///
+	/// Returns a Future that needs to be added to a tokio executor.
+	pub fn create(
+		client: Arc<C>,
+		backend: Arc<BE>,
+		index: Arc<TraceIndex>,
+		blocking_permits: Arc<Semaphore>,
+	) -> impl Future<Output = ()> {
+		async move {
+			let mut notifications = client.import_notification_stream();
+
+			while let Some(notification) = notifications.next().await {
+				let block_hash = notification.hash;
+				if index.contains(block_hash) {
+					continue;
+				}
+
+				let _permit = blocking_permits.acquire().await;
+
+				tracing::trace!("Indexing traces of block {}.", block_hash);
+
+				let client = Arc::clone(&client);
+				let backend = Arc::clone(&backend);
+				let index = Arc::clone(&index);
+
+				let result = tokio::task::spawn_blocking(move || {
+					let traces = CacheTask::<B, C, BE>::cache_block(client, backend, block_hash)
+						.map_err(|e| format!("{:?}", e))?;
+					index.insert(block_hash, &traces)
+				})
+				.await;
+
+				match result {
+					Ok(Ok(())) => (),
+					Ok(Err(e)) => {
+						tracing::warn!("Failed to index traces of block {} : {}", block_hash, e)
+					}
+					Err(e) => tracing::warn!(
+						"Indexing traces of block {} panicked : {:?}",
+						block_hash,
+						e
+					),
+				}
+			}
+		}
+		.instrument(tracing::debug_span!("trace_filter_indexer"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::FilterSelection;
+	use thales_rpc_primitives_debug::{block, CallType};
+
+	/// The trace of a call from `from` to `to`, at the top of the first transaction of a block.
+	fn call_trace(block_hash: H256, from: H160, to: H160) -> TransactionTrace {
+		TransactionTrace {
+			action: block::TransactionTraceAction::Call {
+				call_type: CallType::Call,
+				from,
+				gas: 21_000.into(),
+				input: vec![],
+				to,
+				value: 0.into(),
+			},
+			block_hash,
+			block_number: 1,
+			logs: vec![],
+			output: block::TransactionTraceOutput::Result(block::TransactionTraceResult::Call {
+				gas_used: 21_000.into(),
+				output: vec![],
+			}),
+			subtraces: 0,
+			trace_address: vec![],
+			transaction_hash: H256::repeat_byte(0x11),
+			transaction_position: 0,
+		}
+	}
+
+	#[test]
+	fn trace_filter_is_served_from_a_reopened_index() {
+		let dir = tempfile::tempdir().unwrap();
+		let (alice, bob, charlie) = (
+			H160::repeat_byte(0xaa),
+			H160::repeat_byte(0xbb),
+			H160::repeat_byte(0xcc),
+		);
+		let (block_1, block_2, block_3) = (
+			H256::repeat_byte(1),
+			H256::repeat_byte(2),
+			H256::repeat_byte(3),
+		);
+
+		{
+			let index = TraceIndex::open(dir.path()).unwrap();
+			index
+				.insert(block_1, &[call_trace(block_1, alice, bob)])
+				.unwrap();
+			index
+				.insert(block_2, &[call_trace(block_2, bob, charlie)])
+				.unwrap();
+		}
+
+		let index = TraceIndex::open(dir.path()).unwrap();
+		assert!(index.contains(block_1));
+		assert!(index.contains(block_2));
+		assert!(!index.contains(block_3));
+
+		// The indexed block without any trace from Alice is skipped, the block which is not
+		// indexed may always match.
+		let blocks: Vec<H256> = vec![block_1, block_2, block_3]
+			.into_iter()
+			.filter(|block| index.may_match(*block, &[alice], &[]))
+			.collect();
+		assert_eq!(blocks, vec![block_1, block_3]);
+		assert!(index.may_match(block_2, &[], &[charlie]));
+		assert!(!index.may_match(block_1, &[], &[charlie]));
+
+		let mut selection = FilterSelection {
+			from_address: &[alice],
+			to_address: &[],
+			after: 0,
+			count: 10,
+			max_count: Some(10),
+			skipped: 0,
+			traces: vec![],
+		};
+		for block in &blocks[..1] {
+			let traces = index.traces(*block).expect("block is indexed");
+			assert!(selection.add_block(traces).unwrap());
+		}
+		assert_eq!(selection.traces, vec![call_trace(block_1, alice, bob)]);
+	}
+
+	#[test]
+	fn index_written_with_another_version_is_cleared() {
+		let dir = tempfile::tempdir().unwrap();
+		let (alice, bob) = (H160::repeat_byte(0xaa), H160::repeat_byte(0xbb));
+		let block = H256::repeat_byte(1);
+
+		{
+			let index = TraceIndex::open(dir.path()).unwrap();
+			index
+				.insert(block, &[call_trace(block, alice, bob)])
+				.unwrap();
+
+			let mut transaction = DBTransaction::new();
+			transaction.put_vec(columns::TRACES, VERSION_KEY, (VERSION + 1).encode());
+			index.db.write(transaction).unwrap();
+		}
+
+		let index = TraceIndex::open(dir.path()).unwrap();
+		assert!(!index.contains(block));
+		assert!(index.traces(block).is_none());
+		assert!(index.may_match(block, &[H160::repeat_byte(0xcc)], &[]));
+
+		// The index is usable again once cleared.
+		index
+			.insert(block, &[call_trace(block, alice, bob)])
+			.unwrap();
+		drop(index);
+		let index = TraceIndex::open(dir.path()).unwrap();
+		assert_eq!(index.traces(block), Some(vec![call_trace(block, alice, bob)]));
+	}
+}
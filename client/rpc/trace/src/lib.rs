@@ -14,15 +14,22 @@
 // You should have received a copy of the GNU General Public License
 // along with Thales.  If not, see <http://www.gnu.org/licenses/>.
 
-//! `trace_filter` RPC handler and its associated service task.
+//! `trace_filter`, `trace_block`, `trace_transaction`, `trace_get` and `trace_replayTransaction`
+//! RPC handlers and their associated service task.
 //! The RPC handler rely on `CacheTask` which provides a future that must be run inside a tokio
 //! executor.
 //!
 //! The implementation is composed of multiple tasks :
-//! - Many calls the the RPC handler `Trace::filter`, communicating with the main task.
+//! - Many calls the the RPC handlers of `Trace`, communicating with the main task.
 //! - A main `CacheTask` managing the cache and the communication between tasks.
 //! - For each traced block an async task responsible to wait for a permit, spawn a blocking
 //!   task and waiting for the result, then send it to the main `CacheTask`.
+//! - Optionally an `IndexerTask` tracing each imported block and storing its traces in an
+//!   on-disk `TraceIndex`. Blocks stored in the index are not traced again by the `CacheTask`,
+//!   and the blocks it traces are added to the index.
+//!
+//! The pending block, made of the ready transactions of the pool on top of the best block, is
+//! traced on request and never cached nor indexed.
 
 use futures::{
 	compat::Compat,
@@ -31,7 +38,13 @@ use futures::{
 	stream::FuturesUnordered,
 	FutureExt, SinkExt, StreamExt,
 };
-use std::{collections::BTreeMap, future::Future, marker::PhantomData, sync::Arc, time::Duration};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	future::Future,
+	marker::PhantomData,
+	sync::Arc,
+	time::Duration,
+};
 use tokio::{
 	sync::{mpsc, oneshot, Semaphore},
 	time::delay_for,
@@ -40,38 +53,51 @@ use tracing::{instrument, Instrument};
 
 use jsonrpc_core::Result;
 use sc_client_api::backend::Backend;
-use sp_api::{BlockId, HeaderT, ProvideRuntimeApi};
+use sha3::{Digest, Keccak256};
+use sp_api::{ApiExt, BlockId, HeaderT, ProvideRuntimeApi};
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{
 	Backend as BlockchainBackend, Error as BlockChainError, HeaderBackend, HeaderMetadata,
 };
 use sp_runtime::traits::Block as BlockT;
+use sp_transaction_pool::{InPoolTransaction, TransactionPool};
 use sp_utils::mpsc::TracingUnboundedSender;
 
-use ethereum_types::H256;
-use fc_rpc::internal_err;
+use ethereum_types::{H160, H256, U256};
+use fc_rpc::{frontier_backend_client, internal_err};
 use fp_rpc::EthereumRuntimeRPCApi;
 
 pub use thales_rpc_core_trace::{
-	FilterRequest, RequestBlockId, RequestBlockTag, Trace as TraceT, TraceServer, TransactionTrace,
+	AccountDiff, Bytes, Diff, FilterRequest, Index, ReplayTraceType, RequestBlockId,
+	RequestBlockTag, Trace as TraceT, TraceResults, TraceServer, TransactionTrace,
 };
-use thales_rpc_primitives_debug::{block, DebugRuntimeApi};
+use thales_rpc_primitives_debug::{block, single, DebugRuntimeApi};
+use thales_rpc_primitives_txpool::TxPoolRuntimeApi;
+
+mod index;
+pub use index::{IndexerTask, TraceIndex};
 
 /// RPC handler. Will communicate with a `CacheTask` through a `CacheRequester`.
-pub struct Trace<B, C> {
+pub struct Trace<B: BlockT, C> {
 	_phantom: PhantomData<B>,
 	client: Arc<C>,
+	frontier_backend: Arc<fc_db::Backend<B>>,
 	requester: CacheRequester,
+	index: Option<Arc<TraceIndex>>,
 	max_count: u32,
+	max_block_range: u32,
 }
 
-impl<B, C> Clone for Trace<B, C> {
+impl<B: BlockT, C> Clone for Trace<B, C> {
 	fn clone(&self) -> Self {
 		Self {
 			_phantom: PhantomData::default(),
 			client: Arc::clone(&self.client),
+			frontier_backend: Arc::clone(&self.frontier_backend),
 			requester: self.requester.clone(),
+			index: self.index.clone(),
 			max_count: self.max_count,
+			max_block_range: self.max_block_range,
 		}
 	}
 }
@@ -84,33 +110,232 @@ where
 	C: Send + Sync + 'static,
 {
 	/// Create a new RPC handler.
-	pub fn new(client: Arc<C>, requester: CacheRequester, max_count: u32) -> Self {
+	pub fn new(
+		client: Arc<C>,
+		frontier_backend: Arc<fc_db::Backend<B>>,
+		requester: CacheRequester,
+		index: Option<Arc<TraceIndex>>,
+		max_count: u32,
+		max_block_range: u32,
+	) -> Self {
 		Self {
 			client,
+			frontier_backend,
 			requester,
+			index,
 			max_count,
+			max_block_range,
 			_phantom: PhantomData::default(),
 		}
 	}
 
-	/// Convert an optional block ID (number or tag) to a block height.
+	/// Convert an optional block ID (number, hash or tag) to a block height. The pending block is
+	/// the one following the best block.
 	fn block_id(&self, id: Option<RequestBlockId>) -> Result<u32> {
 		match id {
 			Some(RequestBlockId::Number(n)) => Ok(n),
+			Some(RequestBlockId::Hash(hash)) => {
+				let block_id = frontier_backend_client::load_hash::<B, C>(
+					self.client.as_ref(),
+					self.frontier_backend.as_ref(),
+					hash,
+				)?
+				.ok_or_else(|| internal_err(format!("Block with hash {} don't exist", hash)))?;
+
+				let block_header = self
+					.client
+					.header(block_id)
+					.map_err(|e| {
+						internal_err(format!("Error when fetching block {} header : {:?}", hash, e))
+					})?
+					.ok_or_else(|| internal_err(format!("Block with hash {} don't exist", hash)))?;
+
+				Ok(*block_header.number())
+			}
 			None | Some(RequestBlockId::Tag(RequestBlockTag::Latest)) => {
 				Ok(self.client.info().best_number)
 			}
-			Some(RequestBlockId::Tag(RequestBlockTag::Earliest)) => Ok(0),
 			Some(RequestBlockId::Tag(RequestBlockTag::Pending)) => {
-				Err(internal_err("'pending' is not supported"))
+				Ok(self.client.info().best_number + 1)
 			}
+			Some(RequestBlockId::Tag(RequestBlockTag::Earliest)) => Ok(0),
 		}
 	}
 
+	/// Get the hash of the Substrate block at the given height, if the node has its header.
+	fn block_hash(&self, block_height: u32) -> Result<Option<H256>> {
+		let block_id = BlockId::<B>::Number(block_height);
+		let block_header = self.client.header(block_id).map_err(|e| {
+			internal_err(format!(
+				"Error when fetching block {} header : {:?}",
+				block_height, e
+			))
+		})?;
+
+		Ok(block_header.map(|header| header.hash()))
+	}
+
+	/// Get the hash of the Substrate block containing an Ethereum transaction, and the position
+	/// of the transaction in the block.
+	fn transaction_position(&self, transaction_hash: H256) -> Result<(H256, u32)> {
+		let (eth_block_hash, position) = frontier_backend_client::load_transactions::<B, C>(
+			self.client.as_ref(),
+			self.frontier_backend.as_ref(),
+			transaction_hash,
+		)?
+		.ok_or_else(|| internal_err("Transaction hash not found"))?;
+
+		let block_id = frontier_backend_client::load_hash::<B, C>(
+			self.client.as_ref(),
+			self.frontier_backend.as_ref(),
+			eth_block_hash,
+		)?
+		.ok_or_else(|| internal_err("Block hash not found"))?;
+
+		let block_header = self
+			.client
+			.header(block_id)
+			.map_err(|e| internal_err(format!("Error when fetching block header : {:?}", e)))?
+			.ok_or_else(|| internal_err("Block hash not found"))?;
+
+		Ok((block_header.hash(), position))
+	}
+
+	/// Get the traces of a single block, in a batch of its own.
+	async fn block_traces(&self, block_hash: H256) -> Result<Vec<TransactionTrace>> {
+		let batch_id = self.requester.start_batch(vec![block_hash]).await?;
+		let res = self.requester.get_traces(block_hash).await;
+		self.requester.stop_batch(batch_id).await;
+
+		res
+	}
+
+	/// Get the traces of a transaction, with the hash of the Substrate block containing it and
+	/// its position in the block.
+	async fn transaction_traces(
+		&self,
+		transaction_hash: H256,
+	) -> Result<(H256, u32, Vec<TransactionTrace>)> {
+		let (block_hash, position) = self.transaction_position(transaction_hash)?;
+
+		let traces = self
+			.block_traces(block_hash)
+			.await?
+			.into_iter()
+			.filter(|trace| trace.transaction_position == position)
+			.collect();
+
+		Ok((block_hash, position, traces))
+	}
+
+	/// `trace_block` endpoint (wrapped in the trait implementation with futures compatibilty)
+	async fn block(self, id: RequestBlockId) -> Result<Vec<TransactionTrace>> {
+		if id == RequestBlockId::Tag(RequestBlockTag::Pending) {
+			return self.requester.pending_traces().await;
+		}
+
+		let block_height = self.block_id(Some(id))?;
+		if block_height == 0 {
+			return Ok(vec![]); // no traces for genesis block.
+		}
+
+		let block_hash = self.block_hash(block_height)?.ok_or_else(|| {
+			internal_err(format!("Block with height {} don't exist", block_height))
+		})?;
+		self.block_traces(block_hash).await
+	}
+
+	/// `trace_transaction` endpoint (wrapped in the trait implementation with futures
+	/// compatibilty)
+	async fn transaction(self, transaction_hash: H256) -> Result<Vec<TransactionTrace>> {
+		let (_, _, traces) = self.transaction_traces(transaction_hash).await?;
+		Ok(traces)
+	}
+
+	/// `trace_get` endpoint (wrapped in the trait implementation with futures compatibilty)
+	async fn get(
+		self,
+		transaction_hash: H256,
+		indices: Vec<Index>,
+	) -> Result<Option<TransactionTrace>> {
+		let trace_address: Vec<u32> = indices.iter().map(|index| index.value() as u32).collect();
+
+		let (_, _, traces) = self.transaction_traces(transaction_hash).await?;
+		Ok(traces
+			.into_iter()
+			.find(|trace| trace.trace_address == trace_address))
+	}
+
+	/// `trace_replayTransaction` endpoint (wrapped in the trait implementation with futures
+	/// compatibilty)
+	async fn replay_transaction(
+		self,
+		transaction_hash: H256,
+		trace_types: Vec<ReplayTraceType>,
+	) -> Result<TraceResults> {
+		if trace_types.contains(&ReplayTraceType::VmTrace) {
+			return Err(internal_err("vmTrace is not supported".to_string()));
+		}
+
+		let (block_hash, position, traces) = self.transaction_traces(transaction_hash).await?;
+
+		let output = traces
+			.iter()
+			.find(|trace| trace.trace_address.is_empty())
+			.map(|trace| match &trace.output {
+				block::TransactionTraceOutput::Result(block::TransactionTraceResult::Call {
+					output,
+					..
+				}) => output.clone(),
+				block::TransactionTraceOutput::Revert(revert) => revert.data.clone(),
+				_ => vec![],
+			})
+			.unwrap_or_default();
+
+		let state_diff = if trace_types.contains(&ReplayTraceType::StateDiff) {
+			Some(self.requester.state_diff(block_hash, position).await?)
+		} else {
+			None
+		};
+
+		Ok(TraceResults {
+			output: Bytes(output),
+			trace: if trace_types.contains(&ReplayTraceType::Trace) {
+				Some(traces)
+			} else {
+				None
+			},
+			state_diff,
+			vm_trace: None,
+		})
+	}
+
 	/// `trace_filter` endpoint (wrapped in the trait implementation with futures compatibilty)
 	async fn filter(self, req: FilterRequest) -> Result<Vec<TransactionTrace>> {
 		let from_block = self.block_id(req.from_block)?;
 		let to_block = self.block_id(req.to_block)?;
+		if from_block > to_block {
+			return Err(internal_err(format!(
+				"fromBlock ({}) can't be greater than toBlock ({})",
+				from_block, to_block
+			)));
+		}
+
+		// Blocks after the best one don't exist yet, and have no traces. The pending block is
+		// traced after the blocks of the chain.
+		let pending = req.to_block == Some(RequestBlockId::Tag(RequestBlockTag::Pending));
+		let to_block = to_block.min(self.client.info().best_number);
+		if from_block > to_block && !pending {
+			return Ok(vec![]);
+		}
+
+		let block_range = (to_block + 1).saturating_sub(from_block) + pending as u32;
+		if block_range > self.max_block_range {
+			return Err(internal_err(format!(
+				"block range ({}) can't be greater than maximum ({})",
+				block_range, self.max_block_range
+			)));
+		}
 		let block_heights = from_block..=to_block;
 
 		let count = req.count.unwrap_or(self.max_count);
@@ -121,6 +346,9 @@ where
 			)));
 		}
 
+		let from_address = req.from_address.as_deref().unwrap_or_default();
+		let to_address = req.to_address.as_deref().unwrap_or_default();
+
 		// Build a list of all the Substrate block hashes that need to be traced.
 		let mut block_hashes = vec![];
 		for block_height in block_heights {
@@ -128,21 +356,22 @@ where
 				continue; // no traces for genesis block.
 			}
 
-			let block_id = BlockId::<B>::Number(block_height);
-			let block_header = self
-				.client
-				.header(block_id)
-				.map_err(|e| {
-					internal_err(format!(
-						"Error when fetching block {} header : {:?}",
-						block_height, e
-					))
-				})?
-				.ok_or_else(|| {
-					internal_err(format!("Block with height {} don't exist", block_height))
-				})?;
+			// Skip the blocks whose header is missing, such as the ones before a warp sync. They
+			// can't be traced.
+			let block_hash = match self.block_hash(block_height)? {
+				Some(block_hash) => block_hash,
+				None => {
+					tracing::debug!("Skipping missing block {} in trace_filter.", block_height);
+					continue;
+				}
+			};
 
-			let block_hash = block_header.hash();
+			// Skip the indexed blocks without any trace matching the addresses.
+			if let Some(index) = &self.index {
+				if !index.may_match(block_hash, from_address, to_address) {
+					continue;
+				}
+			}
 
 			block_hashes.push(block_hash);
 		}
@@ -152,7 +381,9 @@ where
 		// Fetch all the traces. It is done in another function to simplify error handling and allow
 		// to call the following `stop_batch` regardless of the result. This is important for the
 		// cache cleanup to work properly.
-		let res = self.fetch_traces(req, &block_hashes, count as usize).await;
+		let res = self
+			.fetch_traces(req, &block_hashes, pending, count as usize)
+			.await;
 		// Stop the batch, allowing the cache task to remove useless non-started block traces and
 		// start the expiration delay.
 		self.requester.stop_batch(batch_id).await;
@@ -164,13 +395,24 @@ where
 		&self,
 		req: FilterRequest,
 		block_hashes: &[H256],
+		pending: bool,
 		count: usize,
 	) -> Result<Vec<TransactionTrace>> {
 		let from_address = req.from_address.unwrap_or_default();
 		let to_address = req.to_address.unwrap_or_default();
-
-		let mut traces_amount: i64 = -(req.after.unwrap_or(0) as i64);
-		let mut traces = vec![];
+		let mut selection = FilterSelection {
+			from_address: &from_address,
+			to_address: &to_address,
+			after: req.after.unwrap_or(0) as usize,
+			count,
+			max_count: if req.count.is_none() {
+				Some(self.max_count)
+			} else {
+				None
+			},
+			skipped: 0,
+			traces: vec![],
+		};
 
 		for &block_hash in block_hashes {
 			// Request the traces of this block to the cache service.
@@ -178,57 +420,69 @@ where
 			// has finished tracing.
 			let block_traces = self.requester.get_traces(block_hash).await?;
 
-			// Filter addresses.
-			let mut block_traces: Vec<_> = block_traces
-				.iter()
-				.filter(|trace| match trace.action {
-					block::TransactionTraceAction::Call { from, to, .. } => {
-						(from_address.is_empty() || from_address.contains(&from))
-							&& (to_address.is_empty() || to_address.contains(&to))
-					}
-					block::TransactionTraceAction::Create { from, .. } => {
-						(from_address.is_empty() || from_address.contains(&from))
-							&& to_address.is_empty()
-					}
-					block::TransactionTraceAction::Suicide { address, .. } => {
-						(from_address.is_empty() || from_address.contains(&address))
-							&& to_address.is_empty()
-					}
-				})
-				.cloned()
-				.collect();
-
-			// Don't insert anything if we're still before "after"
-			traces_amount += block_traces.len() as i64;
-			if traces_amount > 0 {
-				let traces_amount = traces_amount as usize;
-				// If the current Vec of traces is across the "after" marker,
-				// we skip some elements of it.
-				if traces_amount < block_traces.len() {
-					let skip = block_traces.len() - traces_amount;
-					block_traces = block_traces.into_iter().skip(skip).collect();
-				}
+			if !selection.add_block(block_traces)? {
+				return Ok(selection.traces);
+			}
+		}
 
-				traces.append(&mut block_traces);
-
-				// If we go over "count" (the limit), we trim and exit the loop,
-				// unless we used the default maximum, in which case we return an error.
-				if traces_amount >= count {
-					if req.count.is_none() {
-						return Err(internal_err(format!(
-							"the amount of traces goes over the maximum ({}), please use 'after' \
-							and 'count' in your request",
-							self.max_count
-						)));
-					}
+		// The pending block is not part of the batch, its traces change with the transaction
+		// pool and are not cached.
+		if pending {
+			let block_traces = self.requester.pending_traces().await?;
+			selection.add_block(block_traces)?;
+		}
+
+		Ok(selection.traces)
+	}
+}
+
+/// Traces returned by `trace_filter`, selected among the traces of the requested blocks.
+struct FilterSelection<'a> {
+	from_address: &'a [H160],
+	to_address: &'a [H160],
+	/// Number of matching traces to skip before selecting any.
+	after: usize,
+	/// Maximum number of selected traces.
+	count: usize,
+	/// The default maximum, when `count` is not given by the request. Going over it is an error.
+	max_count: Option<u32>,
+	skipped: usize,
+	traces: Vec<TransactionTrace>,
+}
+
+impl<'a> FilterSelection<'a> {
+	/// Select the matching traces of the next block. Returns whether the traces of the next
+	/// blocks are still needed.
+	fn add_block(&mut self, block_traces: Vec<TransactionTrace>) -> Result<bool> {
+		for trace in block_traces {
+			if !matches_addresses(&trace, self.from_address, self.to_address) {
+				continue;
+			}
+
+			// Don't insert anything if we're still before "after".
+			if self.skipped < self.after {
+				self.skipped += 1;
+				continue;
+			}
 
-					traces = traces.into_iter().take(count).collect();
-					break;
+			// If we go over "count" (the limit) we stop, unless we used the default maximum,
+			// in which case we return an error.
+			if self.traces.len() == self.count {
+				if let Some(max_count) = self.max_count {
+					return Err(internal_err(format!(
+						"the amount of traces goes over the maximum ({}), please use 'after' \
+						and 'count' in your request",
+						max_count
+					)));
 				}
+
+				return Ok(false);
 			}
+
+			self.traces.push(trace);
 		}
 
-		Ok(traces)
+		Ok(true)
 	}
 }
 
@@ -246,6 +500,72 @@ where
 		// Wraps the async function into futures compatibility layer.
 		self.clone().filter(filter).boxed().compat()
 	}
+
+	fn block(
+		&self,
+		block: RequestBlockId,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<TransactionTrace>>>> {
+		self.clone().block(block).boxed().compat()
+	}
+
+	fn transaction(
+		&self,
+		transaction_hash: H256,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<TransactionTrace>>>> {
+		self.clone().transaction(transaction_hash).boxed().compat()
+	}
+
+	fn get(
+		&self,
+		transaction_hash: H256,
+		indices: Vec<Index>,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<Option<TransactionTrace>>>> {
+		self.clone().get(transaction_hash, indices).boxed().compat()
+	}
+
+	fn replay_transaction(
+		&self,
+		transaction_hash: H256,
+		trace_types: Vec<ReplayTraceType>,
+	) -> Compat<BoxFuture<'static, jsonrpc_core::Result<TraceResults>>> {
+		self.clone()
+			.replay_transaction(transaction_hash, trace_types)
+			.boxed()
+			.compat()
+	}
+}
+
+/// Addresses a trace is matched against by `trace_filter`. Like OpenEthereum, the "to" address of
+/// a contract creation is the created contract, and the one of a self-destruct is the refund
+/// address.
+fn trace_addresses(trace: &TransactionTrace) -> (H160, Option<H160>) {
+	match trace.action {
+		block::TransactionTraceAction::Call { from, to, .. } => (from, Some(to)),
+		block::TransactionTraceAction::Create { from, .. } => match trace.output {
+			block::TransactionTraceOutput::Result(block::TransactionTraceResult::Create {
+				address,
+				..
+			}) => (from, Some(address)),
+			_ => (from, None),
+		},
+		block::TransactionTraceAction::Suicide {
+			address,
+			refund_address,
+			..
+		} => (address, Some(refund_address)),
+	}
+}
+
+/// Whether a trace matches the address filters of `trace_filter`. A trace matches if its "from"
+/// address is one of `from_address` or its "to" address is one of `to_address`. Without any
+/// address, all traces match.
+fn matches_addresses(trace: &TransactionTrace, from_address: &[H160], to_address: &[H160]) -> bool {
+	if from_address.is_empty() && to_address.is_empty() {
+		return true;
+	}
+
+	let (from, to) = trace_addresses(trace);
+	from_address.contains(&from) || to.map_or(false, |to| to_address.contains(&to))
 }
 
 /// An opaque batch ID.
@@ -273,6 +593,22 @@ enum CacheRequest {
 	/// Notify the cache that it can stop the batch with that ID. Any block contained only in
 	/// this batch and still not started will be discarded.
 	StopBatch { batch_id: CacheBatchId },
+	/// Replay a transaction to get the state changes it made.
+	/// The result is not cached.
+	StateDiff {
+		/// Returns the state changes or an error.
+		sender: oneshot::Sender<Result<BTreeMap<H160, AccountDiff>>>,
+		/// Hash of the block containing the transaction.
+		block: H256,
+		/// Position of the transaction in the block.
+		transaction_position: u32,
+	},
+	/// Trace the pending block, made of the ready transactions of the pool on top of the best
+	/// block. The result is not cached.
+	PendingTraces {
+		/// Returns the array of traces or an error.
+		sender: oneshot::Sender<Result<Vec<TransactionTrace>>>,
+	},
 }
 
 /// Allows to interact with the cache task.
@@ -341,6 +677,71 @@ impl CacheRequester {
 			.map_err(|e| internal_err(format!("Failed to replay block. Error : {:?}", e)))
 	}
 
+	/// Replay a transaction to get the state changes it made, once a blocking permit is
+	/// available.
+	#[instrument(skip(self))]
+	pub async fn state_diff(
+		&self,
+		block: H256,
+		transaction_position: u32,
+	) -> Result<BTreeMap<H160, AccountDiff>> {
+		let (response_tx, response_rx) = oneshot::channel();
+		let mut sender = self.0.clone();
+
+		sender
+			.send(CacheRequest::StateDiff {
+				sender: response_tx,
+				block,
+				transaction_position,
+			})
+			.await
+			.map_err(|e| {
+				internal_err(format!(
+					"Failed to send request to the trace cache task. Error : {:?}",
+					e
+				))
+			})?;
+
+		response_rx
+			.await
+			.map_err(|e| {
+				internal_err(format!(
+					"Trace cache task closed the response channel. Error : {:?}",
+					e
+				))
+			})?
+			.map_err(|e| internal_err(format!("Failed to replay transaction. Error : {:?}", e)))
+	}
+
+	/// Trace the pending block, once a blocking permit is available.
+	#[instrument(skip(self))]
+	pub async fn pending_traces(&self) -> Result<Vec<TransactionTrace>> {
+		let (response_tx, response_rx) = oneshot::channel();
+		let mut sender = self.0.clone();
+
+		sender
+			.send(CacheRequest::PendingTraces {
+				sender: response_tx,
+			})
+			.await
+			.map_err(|e| {
+				internal_err(format!(
+					"Failed to send request to the trace cache task. Error : {:?}",
+					e
+				))
+			})?;
+
+		response_rx
+			.await
+			.map_err(|e| {
+				internal_err(format!(
+					"Trace cache task closed the response channel. Error : {:?}",
+					e
+				))
+			})?
+			.map_err(|e| internal_err(format!("Failed to trace pending block. Error : {:?}", e)))
+	}
+
 	/// Notify the cache that it can stop the batch with that ID. Any block contained only in
 	/// this batch and still in the waiting pool will be discarded.
 	#[instrument(skip(self))]
@@ -415,6 +816,7 @@ enum BlockingTaskMessage {
 pub struct CacheTask<B, C, BE> {
 	client: Arc<C>,
 	backend: Arc<BE>,
+	index: Option<Arc<TraceIndex>>,
 	blocking_permits: Arc<Semaphore>,
 	cached_blocks: BTreeMap<H256, CacheBlock>,
 	batches: BTreeMap<u64, Vec<H256>>,
@@ -433,17 +835,27 @@ where
 	C::Api: BlockBuilder<B>,
 	C::Api: DebugRuntimeApi<B>,
 	C::Api: EthereumRuntimeRPCApi<B>,
+	C::Api: TxPoolRuntimeApi<B>,
 {
 	/// Create a new cache task.
 	///
+	/// Blocks stored in the `index` are read from it instead of being traced, and the traces of
+	/// the other blocks are added to it. The ready transactions of the `pool` make the pending
+	/// block.
+	///
 	/// Returns a Future that needs to be added to a tokio executor, and an handle allowing to
 	/// send requests to the task.
-	pub fn create(
+	pub fn create<P>(
 		client: Arc<C>,
 		backend: Arc<BE>,
+		pool: Arc<P>,
+		index: Option<Arc<TraceIndex>>,
 		cache_duration: Duration,
 		blocking_permits: Arc<Semaphore>,
-	) -> (impl Future<Output = ()>, CacheRequester) {
+	) -> (impl Future<Output = ()>, CacheRequester)
+	where
+		P: TransactionPool<Block = B> + 'static,
+	{
 		// Communication with the outside world :
 		let (requester_tx, mut requester_rx) =
 			sp_utils::mpsc::tracing_unbounded("trace-filter-cache");
@@ -463,6 +875,7 @@ where
 			let mut inner = Self {
 				client,
 				backend,
+				index,
 				blocking_permits,
 				cached_blocks: BTreeMap::new(),
 				batches: BTreeMap::new(),
@@ -491,6 +904,15 @@ where
 
 								inner.request_stop_batch(batch_id);
 							},
+							Some(CacheRequest::StateDiff {sender, block, transaction_position})
+								=> inner.request_state_diff(sender, block, transaction_position),
+							Some(CacheRequest::PendingTraces {sender}) => {
+								let extrinsics = pool
+									.ready()
+									.map(|transaction| transaction.data().clone())
+									.collect();
+								inner.request_pending_traces(sender, extrinsics);
+							},
 						}
 					},
 					message = blocking_rx.next() => {
@@ -538,6 +960,18 @@ where
 					block_cache.active_batch_count
 				);
 			}
+			// The block is in the index, it doesn't need to be traced.
+			else if let Some(traces) = self.index.as_ref().and_then(|index| index.traces(block)) {
+				tracing::trace!("Index hit for block {}, adding it to the cache.", block);
+
+				self.cached_blocks.insert(
+					block,
+					CacheBlock {
+						active_batch_count: 1,
+						state: CacheBlockState::Cached { traces: Ok(traces) },
+					},
+				);
+			}
 			// Otherwise we need to queue this block for tracing.
 			else {
 				tracing::trace!("Cache miss for block {}, pooling it for tracing.", block);
@@ -546,6 +980,7 @@ where
 				let (unqueue_sender, unqueue_receiver) = oneshot::channel();
 				let client = Arc::clone(&self.client);
 				let backend = Arc::clone(&self.backend);
+				let index = self.index.clone();
 				let mut blocking_tx = blocking_tx.clone();
 
 				// Spawn all block caching asynchronously.
@@ -574,7 +1009,17 @@ where
 						// Perform block tracing in a tokio blocking task.
 						let result = async {
 							tokio::task::spawn_blocking(move || {
-								Self::cache_block(client, backend, block)
+								let traces = Self::cache_block(client, backend, block)?;
+
+								// Store the traces in the index, it can still be served
+								// from the cache if it fails.
+								if let Some(index) = index {
+									if let Err(e) = index.insert(block, &traces) {
+										tracing::warn!("{}", e);
+									}
+								}
+
+								Ok(traces)
 							})
 							.await
 							.map_err(|e| {
@@ -702,6 +1147,72 @@ where
 		}
 	}
 
+	/// Handle a request to get the state changes of a transaction.
+	/// The transaction is replayed in a blocking task once a permit is available, and the result
+	/// is sent directly without being cached.
+	#[instrument(skip(self, sender))]
+	fn request_state_diff(
+		&self,
+		sender: oneshot::Sender<Result<BTreeMap<H160, AccountDiff>>>,
+		block: H256,
+		transaction_position: u32,
+	) {
+		let blocking_permits = Arc::clone(&self.blocking_permits);
+		let client = Arc::clone(&self.client);
+		let backend = Arc::clone(&self.backend);
+
+		tokio::spawn(
+			async move {
+				let _permit = blocking_permits.acquire().await;
+
+				let result = tokio::task::spawn_blocking(move || {
+					Self::state_diff(client, backend, block, transaction_position)
+				})
+				.await
+				.map_err(|e| {
+					internal_err(format!(
+						"Replaying transaction of Substrate block {} panicked : {:?}",
+						block, e
+					))
+				})
+				.and_then(|result| result);
+
+				let _ = sender.send(result);
+			}
+			.instrument(tracing::trace_span!("Transaction replay", block = %block)),
+		);
+	}
+
+	/// Handle a request to trace the pending block.
+	/// The block is traced in a blocking task once a permit is available, and the result is sent
+	/// directly without being cached.
+	#[instrument(skip(self, sender, extrinsics))]
+	fn request_pending_traces(
+		&self,
+		sender: oneshot::Sender<Result<Vec<TransactionTrace>>>,
+		extrinsics: Vec<B::Extrinsic>,
+	) {
+		let blocking_permits = Arc::clone(&self.blocking_permits);
+		let client = Arc::clone(&self.client);
+
+		tokio::spawn(
+			async move {
+				let _permit = blocking_permits.acquire().await;
+
+				let result =
+					tokio::task::spawn_blocking(move || Self::pending_traces(client, extrinsics))
+						.await
+						.map_err(|e| {
+							internal_err(format!("Tracing the pending block panicked : {:?}", e))
+						})
+						.and_then(|result| result);
+
+				let _ = sender.send(result);
+			}
+			.instrument(tracing::trace_span!("Pending block tracing")),
+		);
+	}
+
 	/// A tracing blocking task notifies it got a permit and is starting the tracing.
 	/// This started status is stored to avoid removing this block entry.
 	#[instrument(skip(self))]
@@ -824,25 +1335,24 @@ where
 		let extrinsics = backend
 			.blockchain()
 			.body(substrate_block_id)
-			.unwrap()
-			.unwrap();
-
-		// Trace the block.
-		let mut traces: Vec<_> = api
-			.trace_block(&substrate_parent_id, extrinsics)
 			.map_err(|e| {
 				internal_err(format!(
-					"Blockchain error when replaying block {} : {:?}",
-					height, e
+					"Error when fetching substrate block {} body : {:?}",
+					substrate_hash, e
 				))
 			})?
-			.map_err(|e| {
-				internal_err(format!(
-					"Internal runtime error when replaying block {} : {:?}",
-					height, e
-				))
+			.ok_or_else(|| {
+				internal_err(format!("Subtrate block {} body don't exist", substrate_block_id))
 			})?;
 
+		// Trace the block.
+		let mut traces = trace_block::<B, _>(
+			&*api,
+			&substrate_parent_id,
+			extrinsics,
+			&format!("replaying block {}", height),
+		)?;
+
 		// Fill missing data.
 		for trace in traces.iter_mut() {
 			trace.block_hash = eth_block_hash;
@@ -851,15 +1361,245 @@ where
 				.get(trace.transaction_position as usize)
 				.expect("amount of eth transactions should match")
 				.transaction_hash;
+		}
 
-			// Reformat error messages.
-			if let block::TransactionTraceOutput::Error(ref mut error) = trace.output {
-				if error.as_slice() == b"execution reverted" {
-					*error = b"Reverted".to_vec();
-				}
-			}
+		Ok(traces)
+	}
+
+	/// (In blocking task) Use the Runtime API to trace the pending block, made of `extrinsics`
+	/// on top of the best block. The pending block has no hash yet, its traces have a zero
+	/// block hash.
+	#[instrument(skip(client, extrinsics))]
+	fn pending_traces(
+		client: Arc<C>,
+		extrinsics: Vec<B::Extrinsic>,
+	) -> Result<Vec<TransactionTrace>> {
+		let info = client.info();
+		let best_block_id = BlockId::Hash(info.best_hash);
+		let api = client.runtime_api();
+
+		let eth_transactions = api
+			.extrinsic_filter(&best_block_id, extrinsics.clone())
+			.map_err(|e| {
+				internal_err(format!(
+					"Failed to get the Ethereum transactions of the pending block : {:?}",
+					e
+				))
+			})?;
+
+		let mut traces =
+			trace_block::<B, _>(&*api, &best_block_id, extrinsics, "tracing the pending block")?;
+
+		for trace in traces.iter_mut() {
+			let transaction = eth_transactions
+				.get(trace.transaction_position as usize)
+				.expect("amount of eth transactions should match");
+
+			trace.block_hash = H256::zero();
+			trace.block_number = info.best_number + 1;
+			trace.transaction_hash =
+				H256::from_slice(Keccak256::digest(&rlp::encode(transaction)).as_slice());
 		}
 
 		Ok(traces)
 	}
+
+	/// (In blocking task) Use the Runtime API to replay a transaction and get the state changes
+	/// it made.
+	#[instrument(skip(client, backend))]
+	fn state_diff(
+		client: Arc<C>,
+		backend: Arc<BE>,
+		substrate_hash: H256,
+		transaction_position: u32,
+	) -> Result<BTreeMap<H160, AccountDiff>> {
+		let substrate_block_id = BlockId::Hash(substrate_hash);
+
+		// Get Subtrate block data.
+		let api = client.runtime_api();
+		let block_header = client
+			.header(substrate_block_id)
+			.map_err(|e| {
+				internal_err(format!(
+					"Error when fetching substrate block {} header : {:?}",
+					substrate_hash, e
+				))
+			})?
+			.ok_or_else(|| {
+				internal_err(format!("Subtrate block {} don't exist", substrate_block_id))
+			})?;
+
+		let substrate_parent_id = BlockId::<B>::Hash(*block_header.parent_hash());
+
+		// Get the Ethereum transaction.
+		let transaction = api
+			.current_block(&substrate_block_id)
+			.map_err(|e| {
+				internal_err(format!(
+					"Failed to get Ethereum block data for Substrate block {} : {:?}",
+					substrate_hash, e
+				))
+			})?
+			.and_then(|eth_block| {
+				eth_block
+					.transactions
+					.into_iter()
+					.nth(transaction_position as usize)
+			})
+			.ok_or_else(|| {
+				internal_err(format!(
+					"Failed to get Ethereum transaction {} of Substrate block {}",
+					transaction_position, substrate_hash
+				))
+			})?;
+
+		// Get extrinsics (containing Ethereum ones)
+		let extrinsics = backend
+			.blockchain()
+			.body(substrate_block_id)
+			.map_err(|e| {
+				internal_err(format!(
+					"Error when fetching substrate block {} body : {:?}",
+					substrate_hash, e
+				))
+			})?
+			.ok_or_else(|| {
+				internal_err(format!("Subtrate block {} body don't exist", substrate_block_id))
+			})?;
+
+		// Replay the transaction, recording the state before and after it.
+		if debug_api_version::<B, _>(&*api, &substrate_parent_id)? < 2 {
+			return Err(internal_err("The runtime of this block doesn't support state diffs"));
+		}
+		let trace = api
+			.trace_transaction(
+				&substrate_parent_id,
+				extrinsics,
+				&transaction,
+				single::TraceType::PrestateTracer { diff_mode: true },
+			)
+			.map_err(|e| {
+				internal_err(format!(
+					"Blockchain error when replaying transaction {} of block {} : {:?}",
+					transaction_position, substrate_hash, e
+				))
+			})?
+			.map_err(|e| {
+				internal_err(format!(
+					"Internal runtime error when replaying transaction {} of block {} : {:?}",
+					transaction_position, substrate_hash, e
+				))
+			})?;
+
+		match trace {
+			single::TransactionTrace::Prestate(single::PrestateTrace::Diff { pre, post }) => {
+				Ok(state_diff(pre, post))
+			}
+			_ => Err(internal_err("Runtime returned an unexpected trace")),
+		}
+	}
+}
+
+/// Version of the `DebugRuntimeApi` implemented by the runtime at `block_id`. Fails if the runtime
+/// doesn't implement it.
+fn debug_api_version<B, Api>(api: &Api, block_id: &BlockId<B>) -> Result<u32>
+where
+	B: BlockT,
+	Api: ApiExt<B>,
+{
+	for version in (1..=2).rev() {
+		let supported = api
+			.has_api_with::<dyn DebugRuntimeApi<B>, _>(block_id, |v| v >= version)
+			.map_err(|e| internal_err(format!("Runtime api access error: {:?}", e)))?;
+		if supported {
+			return Ok(version);
+		}
+	}
+
+	Err(internal_err("The runtime of this block doesn't support tracing"))
+}
+
+/// Traces a block on top of the state of `block_id`, with the version of the `DebugRuntimeApi`
+/// implemented by its runtime. The traces of version 1 have no logs. `action` describes the
+/// tracing in the errors.
+fn trace_block<B, Api>(
+	api: &Api,
+	block_id: &BlockId<B>,
+	extrinsics: Vec<B::Extrinsic>,
+	action: &str,
+) -> Result<Vec<block::TransactionTrace>>
+where
+	B: BlockT,
+	Api: DebugRuntimeApi<B>,
+{
+	let traces = if debug_api_version::<B, _>(api, block_id)? >= 2 {
+		api.trace_block(block_id, extrinsics)
+	} else {
+		#[allow(deprecated)]
+		let traces = api.trace_block_before_version_2(block_id, extrinsics);
+		traces.map(|traces| traces.map(|traces| traces.into_iter().map(Into::into).collect()))
+	};
+
+	traces
+		.map_err(|e| internal_err(format!("Blockchain error when {} : {:?}", action, e)))?
+		.map_err(|e| internal_err(format!("Internal runtime error when {} : {:?}", action, e)))
+}
+
+/// Convert the states of the accounts before and after a transaction, restricted to what it
+/// changed, to the OpenEthereum state diff format.
+///
+/// Accounts created by the transaction are only in `post`, with their whole state, and their
+/// values are reported as born. Accounts destroyed by the transaction are only in `pre`, and
+/// their values are reported as dead.
+fn state_diff(
+	mut pre: BTreeMap<H160, single::AccountState>,
+	mut post: BTreeMap<H160, single::AccountState>,
+) -> BTreeMap<H160, AccountDiff> {
+	fn diff<T>(from: Option<T>, to: Option<T>) -> Diff<T> {
+		match (from, to) {
+			(Some(from), Some(to)) => Diff::Changed { from, to },
+			(None, Some(to)) => Diff::Born(to),
+			(Some(from), None) => Diff::Died(from),
+			(None, None) => Diff::Same,
+		}
+	}
+
+	let addresses: BTreeSet<H160> = pre.keys().chain(post.keys()).cloned().collect();
+
+	addresses
+		.into_iter()
+		.map(|address| {
+			let (before, after) = (pre.remove(&address), post.remove(&address));
+
+			let storage = match (&before, &after) {
+				(Some(before), None) => before
+					.storage
+					.iter()
+					.map(|(index, from)| (*index, Diff::Died(*from)))
+					.collect(),
+				(before, Some(after)) => after
+					.storage
+					.iter()
+					.map(|(index, to)| match before {
+						Some(before) => {
+							let from = before.storage.get(index).cloned().unwrap_or_default();
+							(*index, Diff::Changed { from, to: *to })
+						}
+						None => (*index, Diff::Born(*to)),
+					})
+					.collect(),
+				(None, None) => BTreeMap::new(),
+			};
+
+			let (before, after) = (before.unwrap_or_default(), after.unwrap_or_default());
+			let account_diff = AccountDiff {
+				balance: diff(before.balance, after.balance),
+				nonce: diff(before.nonce.map(U256::from), after.nonce.map(U256::from)),
+				code: diff(before.code.map(Bytes), after.code.map(Bytes)),
+				storage,
+			};
+
+			(address, account_diff)
+		})
+		.collect()
 }
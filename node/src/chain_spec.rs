@@ -26,8 +26,8 @@ use log::debug;
 use thales_runtime::{
 	AccountId, Balance, BalancesConfig, CouncilCollectiveConfig, DemocracyConfig, EVMConfig,
 	EthereumChainIdConfig, EthereumConfig, GenesisConfig, InflationInfo, ParachainInfoConfig,
-	ParachainStakingConfig, Range, SchedulerConfig, SudoConfig, SystemConfig,
-	TechComitteeCollectiveConfig, GLMR, WASM_BINARY,
+	ParachainStakingConfig, Range, Runtime, SchedulerConfig, SudoConfig, SystemConfig,
+	TechComitteeCollectiveConfig, ThalesPrecompiles, GLMR, WASM_BINARY,
 };
 use sc_chain_spec::{ChainSpecExtension, ChainSpecGroup};
 use sc_service::ChainType;
@@ -235,13 +235,9 @@ pub fn testnet_genesis(
 ) -> GenesisConfig {
 	// This is supposed the be the simplest bytecode to revert without returning any data.
 	// We will pre-deploy it under all of our precompiles to ensure they can be called from
-	// within contracts. TODO We should have a test to ensure this is the right bytecode.
+	// within contracts. The runtime integration tests make sure contracts can call them.
 	// (PUSH1 0x00 PUSH1 0x00 REVERT)
 	let revert_bytecode = vec![0x60, 0x00, 0x60, 0x00, 0xFD];
-	// TODO consider whether this should be imported from thales precompiles
-	let precompile_addresses = vec![1, 2, 3, 4, 5, 6, 7, 8, 1024, 1025, 2048]
-		.into_iter()
-		.map(H160::from_low_u64_be);
 	GenesisConfig {
 		frame_system: SystemConfig {
 			code: WASM_BINARY
@@ -262,14 +258,11 @@ pub fn testnet_genesis(
 		},
 		pallet_ethereum_chain_id: EthereumChainIdConfig { chain_id },
 		pallet_evm: EVMConfig {
-			// We need _some_ code inserted at the precompile address so that
-			// the evm will actually call the address.
-			// TODO Cleanly fetch the addresses from
-			// the runtime/thales precompiles and systematically fill them with code
-			// that will revert if it is called by accident (it shouldn't be because
-			// it is shadowed by the precompile).
-			// This one is for the parachain staking precompile wrappers
-			accounts: precompile_addresses
+			// We need _some_ code inserted at the precompile addresses so that contracts
+			// checking the code size of the address before calling it (as Solidity does for
+			// high-level calls) will actually call the address. The code reverts if it is
+			// called by accident (it shouldn't be because it is shadowed by the precompile).
+			accounts: ThalesPrecompiles::<Runtime>::used_addresses()
 				.map(|a| {
 					(
 						a,
@@ -60,6 +60,10 @@ pub enum Subcommand {
 	/// The custom benchmark subcommmand benchmarking runtime pallets.
 	#[structopt(name = "benchmark", about = "Benchmark runtime pallets.")]
 	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
+
+	/// Print the block production statistics of a collator.
+	#[structopt(name = "stats")]
+	Stats(StatsCmd),
 }
 
 #[derive(Debug, StructOpt)]
@@ -114,6 +118,37 @@ pub struct ExportGenesisWasmCommand {
 	pub chain: Option<String>,
 }
 
+/// Command for printing the block production statistics of a collator for the last rounds,
+/// as stored in the local database.
+#[derive(Debug, StructOpt)]
+pub struct StatsCmd {
+	/// Account of the collator.
+	#[structopt(long, parse(try_from_str = parse_h160))]
+	pub collator: H160,
+
+	/// Number of rounds to print, ending with the current one.
+	#[structopt(long, default_value = "10")]
+	pub rounds: u32,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: sc_cli::SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: sc_cli::ImportParams,
+}
+
+impl sc_cli::CliConfiguration for StatsCmd {
+	fn shared_params(&self) -> &sc_cli::SharedParams {
+		&self.shared_params
+	}
+
+	fn import_params(&self) -> Option<&sc_cli::ImportParams> {
+		Some(&self.import_params)
+	}
+}
+
 #[derive(Debug, StructOpt)]
 pub struct RunCmd {
 	#[structopt(flatten)]
@@ -150,16 +185,44 @@ pub struct RunCmd {
 	#[structopt(long, default_value = "10")]
 	pub ethapi_max_permits: u32,
 
+	/// Maximum number of step logs a raw trace of the "debug" module records for a transaction.
+	/// Traces going over this limit are truncated, and flagged as such in the response.
+	#[structopt(long, default_value = "1000000")]
+	pub ethapi_debug_max_step_logs: u32,
+
+	/// Maximum amount of memory (in bytes) the step logs of a raw trace of the "debug" module can
+	/// use for a transaction. Traces going over this limit are truncated, and flagged as such in
+	/// the response.
+	#[structopt(long, default_value = "100000000")]
+	pub ethapi_debug_max_step_logs_bytes: u32,
+
+	/// Estimate of the number of steps a raw trace of the "debug" module replays per second. The
+	/// timeout of a request is turned into a budget of steps, so its replay stops around the time
+	/// the request times out. Traces going over their budget are truncated, and flagged as such
+	/// in the response.
+	#[structopt(long, default_value = "200000")]
+	pub ethapi_debug_steps_per_second: u32,
+
 	/// Maximum number of trace entries a single request of `trace_filter` is allowed to return.
 	/// A request asking for more or an unbounded one going over this limit will both return an
 	/// error.
 	#[structopt(long, default_value = "500")]
 	pub ethapi_trace_max_count: u32,
 
+	/// Maximum number of blocks a single request of `trace_filter` is allowed to trace.
+	#[structopt(long, default_value = "1000")]
+	pub ethapi_trace_max_block_range: u32,
+
 	/// Duration (in seconds) after which the cache of `trace_filter` for a given block will be
 	/// discarded.
 	#[structopt(long, default_value = "300")]
 	pub ethapi_trace_cache_duration: u64,
+
+	/// Trace each imported block once and store the traces in an on-disk index, from which
+	/// `trace_filter` serves the blocks it already traced, even after a restart. Requires the
+	/// "trace" module.
+	#[structopt(long)]
+	pub ethapi_trace_index: bool,
 }
 
 fn parse_h160(input: &str) -> Result<H160, String> {
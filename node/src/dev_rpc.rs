@@ -0,0 +1,77 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! RPC methods driving the relay chain mocked by the dev service.
+
+use crate::inherents::MockRelayState;
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+use sp_core::Bytes;
+
+pub use rpc_impl_DevApi::gen_server::DevApi as DevApiServer;
+
+#[rpc(server)]
+pub trait DevApi {
+	/// Queue a downward message, delivered to the parachain in the next block.
+	#[rpc(name = "dev_injectDownwardMessage")]
+	fn inject_downward_message(&self, msg: Bytes) -> Result<()>;
+
+	/// Queue a horizontal message from the given parachain, delivered in the next block.
+	#[rpc(name = "dev_injectHrmpMessage")]
+	fn inject_hrmp_message(&self, sender: u32, msg: Bytes) -> Result<()>;
+
+	/// Set an entry of the relay chain state proof provided with every following block, or
+	/// remove it when no value is given.
+	#[rpc(name = "dev_setRelayStateEntry")]
+	fn set_relay_state_entry(&self, key: Bytes, value: Option<Bytes>) -> Result<()>;
+}
+
+pub struct Dev {
+	mock_relay: MockRelayState,
+}
+
+impl Dev {
+	pub fn new(mock_relay: MockRelayState) -> Self {
+		Self { mock_relay }
+	}
+}
+
+impl DevApi for Dev {
+	fn inject_downward_message(&self, msg: Bytes) -> Result<()> {
+		self.mock_relay.inject_downward_message(msg.to_vec());
+		Ok(())
+	}
+
+	fn inject_hrmp_message(&self, sender: u32, msg: Bytes) -> Result<()> {
+		self.mock_relay
+			.inject_horizontal_message(sender.into(), msg.to_vec());
+		Ok(())
+	}
+
+	fn set_relay_state_entry(&self, key: Bytes, value: Option<Bytes>) -> Result<()> {
+		self.mock_relay.set_relay_state(|sproof| {
+			sproof
+				.additional_key_values
+				.retain(|(k, _)| k[..] != key[..]);
+			if let Some(value) = value {
+				sproof
+					.additional_key_values
+					.push((key.to_vec(), value.to_vec()));
+			}
+		});
+		Ok(())
+	}
+}
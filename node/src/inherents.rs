@@ -23,15 +23,38 @@
 //! data inherent. This mock provider provides stub data that does not represent anything "real"
 //! about the external world, but can pass the runtime's checks. This is useful in testing
 //! for example, running the --dev service without a relay chain backbone.
+//!
+//! The mocked relay chain advances its relay parent number at every block, delivers downward and
+//! horizontal messages injected through the `MockRelayState` handle (see the `dev` RPC), and
+//! lets tests customize the relay state proof. The relay parent number and the message queue heads
+//! are read back from the best block before each inherent is built, so a restarted node carries on
+//! from its database and queued messages are only dropped once a block delivering them is imported.
 
-use cumulus_primitives_core::PersistedValidationData;
+use cumulus_primitives_core::{
+	relay_chain::{self, v1::HrmpChannelId},
+	AbridgedHrmpChannel, InboundDownwardMessage, InboundHrmpMessage, ParaId,
+	PersistedValidationData,
+};
 use cumulus_primitives_parachain_inherent::{ParachainInherentData, INHERENT_IDENTIFIER};
-use parity_scale_codec::Encode;
-use sp_core::H160;
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::{Backend, StorageProvider};
+use sp_core::{
+	storage::{StorageData, StorageKey},
+	twox_128, H160,
+};
 use sp_inherents::{InherentData, InherentDataProviders, InherentIdentifier, ProvideInherentData};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{BlakeTwo256, Hash as HashT},
+};
 use sp_timestamp::InherentError;
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, Mutex},
+};
 
 use cumulus_test_relay_sproof_builder::RelayStateSproofBuilder;
+use thales_runtime::opaque::Block;
 
 /// Build the inherent data providers for the node.
 ///
@@ -40,7 +63,7 @@ use cumulus_test_relay_sproof_builder::RelayStateSproofBuilder;
 /// - The validation data provider can be mocked.
 pub fn build_inherent_data_providers(
 	author: Option<H160>,
-	mock: bool,
+	mock_relay: Option<MockRelayState>,
 ) -> Result<InherentDataProviders, sc_service::Error> {
 	let providers = InherentDataProviders::new();
 
@@ -59,9 +82,9 @@ pub fn build_inherent_data_providers(
 	}
 
 	// Parachain inherent provider, only for dev-service nodes.
-	if mock {
+	if let Some(mock_relay) = mock_relay {
 		providers
-			.register_provider(MockValidationDataInherentDataProvider)
+			.register_provider(MockValidationDataInherentDataProvider(mock_relay))
 			.map_err(Into::into)
 			.map_err(sp_consensus::error::Error::InherentData)?;
 	}
@@ -73,11 +96,238 @@ pub fn build_inherent_data_providers(
 	Ok(providers)
 }
 
+/// Extend a message queue chain head with a message, the same way the relay chain does.
+fn extend_mqc_head(head: relay_chain::Hash, sent_at: u32, msg: &[u8]) -> relay_chain::Hash {
+	BlakeTwo256::hash_of(&(head, sent_at, BlakeTwo256::hash_of(&msg)))
+}
+
+fn parachain_system_key(item: &[u8]) -> StorageKey {
+	StorageKey([twox_128(b"ParachainSystem"), twox_128(item)].concat())
+}
+
+/// Relay chain related state the parachain stored in a block.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ChainRelayState {
+	pub relay_parent_number: u32,
+	pub dmq_mqc_head: relay_chain::Hash,
+	pub hrmp_mqc_heads: BTreeMap<ParaId, relay_chain::Hash>,
+}
+
+impl ChainRelayState {
+	/// Read the state stored by `cumulus_pallet_parachain_system` at the given block. Missing
+	/// entries, for example at genesis, are left to their default values.
+	pub fn read<C, B>(client: &C, at: &BlockId<Block>) -> sp_blockchain::Result<Self>
+	where
+		C: StorageProvider<Block, B>,
+		B: Backend<Block>,
+	{
+		let read = |item: &[u8]| client.storage(at, &parachain_system_key(item));
+		fn decode<T: Decode>(data: Option<StorageData>) -> sp_blockchain::Result<Option<T>> {
+			data.map(|data| T::decode(&mut &data.0[..]))
+				.transpose()
+				.map_err(|e| sp_blockchain::Error::Storage(format!("{:?}", e)))
+		}
+
+		Ok(Self {
+			relay_parent_number: decode::<PersistedValidationData>(read(b"ValidationData")?)?
+				.map(|data| data.relay_parent_number)
+				.unwrap_or_default(),
+			dmq_mqc_head: decode(read(b"LastDmqMqcHead")?)?.unwrap_or_default(),
+			hrmp_mqc_heads: decode(read(b"LastHrmpMqcHeads")?)?.unwrap_or_default(),
+		})
+	}
+}
+
+/// Messages delivered by the last provided inherent, with the state the parachain stores once the
+/// block including it is imported.
+struct InFlight {
+	state: ChainRelayState,
+	downward_messages: usize,
+	horizontal_messages: BTreeMap<ParaId, usize>,
+}
+
+struct MockRelayInner {
+	para_id: ParaId,
+	chain_state: Box<dyn Fn() -> ChainRelayState + Send>,
+	relay_state: RelayStateSproofBuilder,
+	imported: ChainRelayState,
+	in_flight: Option<InFlight>,
+	downward_messages: Vec<Vec<u8>>,
+	horizontal_messages: BTreeMap<ParaId, Vec<Vec<u8>>>,
+}
+
+impl MockRelayInner {
+	/// Catch up with the state of the best block, dropping the messages it delivered.
+	fn sync(&mut self) {
+		self.imported = (self.chain_state)();
+		let delivered = match self.in_flight.take() {
+			Some(in_flight)
+				if self.imported.relay_parent_number >= in_flight.state.relay_parent_number =>
+			{
+				in_flight
+			}
+			_ => return,
+		};
+
+		self.downward_messages.drain(..delivered.downward_messages);
+		for (sender, count) in delivered.horizontal_messages {
+			if let Some(messages) = self.horizontal_messages.get_mut(&sender) {
+				messages.drain(..count);
+				if messages.is_empty() {
+					self.horizontal_messages.remove(&sender);
+				}
+			}
+		}
+	}
+}
+
+/// Handle on the state of the relay chain mocked by the dev service.
+#[derive(Clone)]
+pub struct MockRelayState(Arc<Mutex<MockRelayInner>>);
+
+impl MockRelayState {
+	/// Create a mocked relay chain delivering messages to `para_id`. `chain_state` returns the
+	/// relay chain related state of the parachain's best block.
+	pub fn new(
+		para_id: ParaId,
+		chain_state: impl Fn() -> ChainRelayState + Send + 'static,
+	) -> Self {
+		Self(Arc::new(Mutex::new(MockRelayInner {
+			para_id,
+			chain_state: Box::new(chain_state),
+			relay_state: RelayStateSproofBuilder::default(),
+			imported: Default::default(),
+			in_flight: None,
+			downward_messages: Vec::new(),
+			horizontal_messages: BTreeMap::new(),
+		})))
+	}
+
+	/// Queue a downward message, delivered in the next block.
+	pub fn inject_downward_message(&self, msg: Vec<u8>) {
+		self.lock().downward_messages.push(msg);
+	}
+
+	/// Queue a horizontal message from the given sender, delivered in the next block.
+	/// This opens the mocked HRMP channel from the sender if necessary.
+	pub fn inject_horizontal_message(&self, sender: ParaId, msg: Vec<u8>) {
+		self.lock()
+			.horizontal_messages
+			.entry(sender)
+			.or_default()
+			.push(msg);
+	}
+
+	/// Customize the relay state proof provided with every following block. Entries related to
+	/// the messages (para id, message queue heads, HRMP channels) are overwritten by the mock.
+	pub fn set_relay_state(&self, f: impl FnOnce(&mut RelayStateSproofBuilder)) {
+		f(&mut self.lock().relay_state);
+	}
+
+	/// Relay parent number of the best block.
+	pub fn relay_parent_number(&self) -> u32 {
+		let mut inner = self.lock();
+		inner.sync();
+		inner.imported.relay_parent_number
+	}
+
+	/// Advance the mocked relay chain by one block on top of the best block and build the
+	/// matching parachain inherent, delivering the queued messages. The messages stay queued
+	/// until a block including them is imported.
+	pub fn next_inherent_data(&self) -> ParachainInherentData {
+		let mut inner = self.lock();
+		inner.sync();
+		let mut state = inner.imported.clone();
+		state.relay_parent_number += 1;
+		let sent_at = state.relay_parent_number;
+
+		let downward_messages: Vec<_> = inner
+			.downward_messages
+			.iter()
+			.map(|msg| InboundDownwardMessage {
+				sent_at,
+				msg: msg.clone(),
+			})
+			.collect();
+		for message in &downward_messages {
+			state.dmq_mqc_head = extend_mqc_head(state.dmq_mqc_head, sent_at, &message.msg);
+		}
+
+		let mut horizontal_messages = BTreeMap::new();
+		for (sender, messages) in inner.horizontal_messages.iter() {
+			let head = state.hrmp_mqc_heads.entry(*sender).or_default();
+			let messages: Vec<_> = messages
+				.iter()
+				.map(|data| InboundHrmpMessage {
+					sent_at,
+					data: data.clone(),
+				})
+				.collect();
+			for message in &messages {
+				*head = extend_mqc_head(*head, sent_at, &message.data);
+			}
+			horizontal_messages.insert(*sender, messages);
+		}
+
+		let mut sproof = inner.relay_state.clone();
+		sproof.para_id = inner.para_id;
+		sproof.dmq_mqc_head = Some(state.dmq_mqc_head);
+		if !state.hrmp_mqc_heads.is_empty() {
+			sproof.hrmp_ingress_channel_index =
+				Some(state.hrmp_mqc_heads.keys().cloned().collect());
+			for (sender, head) in state.hrmp_mqc_heads.iter() {
+				sproof.hrmp_channels.insert(
+					HrmpChannelId {
+						sender: *sender,
+						recipient: inner.para_id,
+					},
+					AbridgedHrmpChannel {
+						max_capacity: u32::MAX,
+						max_total_size: u32::MAX,
+						max_message_size: u32::MAX,
+						msg_count: 0,
+						total_size: 0,
+						mqc_head: Some(*head),
+					},
+				);
+			}
+		}
+
+		// Use the "sproof" (spoof proof) builder to build valid mock state root and proof.
+		let (relay_storage_root, proof) = sproof.into_state_root_and_proof();
+
+		inner.in_flight = Some(InFlight {
+			state,
+			downward_messages: downward_messages.len(),
+			horizontal_messages: horizontal_messages
+				.iter()
+				.map(|(sender, messages)| (*sender, messages.len()))
+				.collect(),
+		});
+
+		ParachainInherentData {
+			validation_data: PersistedValidationData {
+				parent_head: Default::default(),
+				relay_parent_storage_root: relay_storage_root,
+				relay_parent_number: sent_at,
+				max_pov_size: Default::default(),
+			},
+			downward_messages,
+			horizontal_messages,
+			relay_chain_state: proof,
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<MockRelayInner> {
+		self.0.lock().expect("mock relay state lock poisoned")
+	}
+}
+
 /// Inherent data provider that supplies mocked validation data.
 ///
 /// This is useful when running a node that is not actually backed by any relay chain.
 /// For example when running a local node, or running integration tests.
-struct MockValidationDataInherentDataProvider;
+struct MockValidationDataInherentDataProvider(MockRelayState);
 
 impl ProvideInherentData for MockValidationDataInherentDataProvider {
 	fn inherent_identifier(&self) -> &'static InherentIdentifier {
@@ -88,26 +338,129 @@ impl ProvideInherentData for MockValidationDataInherentDataProvider {
 		&self,
 		inherent_data: &mut InherentData,
 	) -> Result<(), sp_inherents::Error> {
-		// Use the "sproof" (spoof proof) builder to build valid mock state root and proof.
-		let (relay_storage_root, proof) =
-			RelayStateSproofBuilder::default().into_state_root_and_proof();
-
-		let data = ParachainInherentData {
-			validation_data: PersistedValidationData {
-				parent_head: Default::default(),
-				relay_parent_storage_root: relay_storage_root,
-				relay_parent_number: Default::default(),
-				max_pov_size: Default::default(),
-			},
-			downward_messages: Default::default(),
-			horizontal_messages: Default::default(),
-			relay_chain_state: proof,
-		};
-
-		inherent_data.put_data(INHERENT_IDENTIFIER, &data)
+		inherent_data.put_data(INHERENT_IDENTIFIER, &self.0.next_inherent_data())
 	}
 
 	fn error_to_string(&self, error: &[u8]) -> Option<String> {
 		InherentError::try_from(&INHERENT_IDENTIFIER, error).map(|e| format!("{:?}", e))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A mocked relay chain with a handle on the state of the mocked best block.
+	fn mock_relay() -> (MockRelayState, Arc<Mutex<ChainRelayState>>) {
+		let chain = Arc::new(Mutex::new(ChainRelayState::default()));
+		let best = chain.clone();
+		let relay = MockRelayState::new(1000.into(), move || best.lock().unwrap().clone());
+		(relay, chain)
+	}
+
+	/// Import the block built with the last provided inherent.
+	fn import(relay: &MockRelayState, chain: &Mutex<ChainRelayState>) {
+		let state = relay.lock().in_flight.as_ref().unwrap().state.clone();
+		*chain.lock().unwrap() = state;
+	}
+
+	#[test]
+	fn relay_parent_number_increases_every_imported_block() {
+		let (relay, chain) = mock_relay();
+		assert_eq!(
+			relay
+				.next_inherent_data()
+				.validation_data
+				.relay_parent_number,
+			1
+		);
+		import(&relay, &chain);
+		assert_eq!(
+			relay
+				.next_inherent_data()
+				.validation_data
+				.relay_parent_number,
+			2
+		);
+		import(&relay, &chain);
+		assert_eq!(relay.relay_parent_number(), 2);
+	}
+
+	#[test]
+	fn relay_parent_number_is_seeded_from_the_best_block() {
+		let (relay, chain) = mock_relay();
+		chain.lock().unwrap().relay_parent_number = 41;
+		assert_eq!(
+			relay
+				.next_inherent_data()
+				.validation_data
+				.relay_parent_number,
+			42
+		);
+	}
+
+	#[test]
+	fn injected_messages_are_delivered_once() {
+		let (relay, chain) = mock_relay();
+		relay.inject_downward_message(vec![1, 2, 3]);
+		relay.inject_horizontal_message(2000.into(), vec![4, 5]);
+
+		let data = relay.next_inherent_data();
+		assert_eq!(
+			data.downward_messages,
+			vec![InboundDownwardMessage {
+				sent_at: 1,
+				msg: vec![1, 2, 3]
+			}]
+		);
+		assert_eq!(
+			data.horizontal_messages.get(&2000.into()),
+			Some(&vec![InboundHrmpMessage {
+				sent_at: 1,
+				data: vec![4, 5]
+			}])
+		);
+		import(&relay, &chain);
+
+		let data = relay.next_inherent_data();
+		assert!(data.downward_messages.is_empty());
+		assert!(data.horizontal_messages.is_empty());
+	}
+
+	#[test]
+	fn messages_of_a_block_not_imported_are_delivered_again() {
+		let (relay, _chain) = mock_relay();
+		relay.inject_downward_message(vec![1]);
+		relay.next_inherent_data();
+
+		relay.inject_downward_message(vec![2]);
+		let data = relay.next_inherent_data();
+		assert_eq!(
+			data.downward_messages,
+			vec![
+				InboundDownwardMessage {
+					sent_at: 1,
+					msg: vec![1]
+				},
+				InboundDownwardMessage {
+					sent_at: 1,
+					msg: vec![2]
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn message_queue_heads_are_chained() {
+		let (relay, chain) = mock_relay();
+		relay.inject_downward_message(vec![1]);
+		relay.next_inherent_data();
+		import(&relay, &chain);
+		relay.inject_downward_message(vec![2]);
+		relay.next_inherent_data();
+		import(&relay, &chain);
+
+		let expected = extend_mqc_head(extend_mqc_head(Default::default(), 1, &[1]), 2, &[2]);
+		assert_eq!(chain.lock().unwrap().dmq_mqc_head, expected);
+	}
+}
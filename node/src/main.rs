@@ -26,8 +26,10 @@ mod test_spec;
 mod service;
 mod cli;
 mod command;
+mod dev_rpc;
 mod inherents;
 mod rpc;
+mod stats;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
@@ -20,12 +20,15 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::cli::EthApi as EthApiCmd;
-use ethereum::EthereumStorageSchema;
+use crate::dev_rpc::{Dev, DevApiServer};
+use crate::inherents::MockRelayState;
+use ethereum::{Block as EthereumBlock, EthereumStorageSchema, Receipt as EthereumReceipt};
 use fc_rpc::{SchemaV1Override, StorageOverride};
 use fc_rpc_core::types::{FilterPool, PendingTransactions};
+use fp_rpc::TransactionStatus;
 use jsonrpc_pubsub::manager::SubscriptionManager;
 use thales_rpc_debug::DebugRequester;
-use thales_rpc_trace::CacheRequester as TraceFilterCacheRequester;
+use thales_rpc_trace::{CacheRequester as TraceFilterCacheRequester, TraceIndex};
 use thales_runtime::{opaque::Block, AccountId, Balance, Hash, Index};
 use sc_client_api::{
 	backend::{AuxStore, Backend, StateBackend, StorageProvider},
@@ -41,9 +44,59 @@ use sp_block_builder::BlockBuilder;
 use sp_blockchain::{
 	Backend as BlockchainBackend, Error as BlockChainError, HeaderBackend, HeaderMetadata,
 };
-use sp_runtime::traits::BlakeTwo256;
+use sp_core::{H160, H256, U256};
+use sp_runtime::{generic::BlockId, traits::BlakeTwo256};
 use sp_transaction_pool::TransactionPool;
 
+/// Storage override filling the `miner` (beneficiary) of Ethereum blocks from the
+/// `author_inherent` digest of the matching Substrate header, when the runtime didn't record it.
+pub struct AuthorDigestOverride<C> {
+	client: Arc<C>,
+	inner: Box<dyn StorageOverride<Block> + Send + Sync>,
+}
+
+impl<C> AuthorDigestOverride<C> {
+	pub fn new(client: Arc<C>, inner: Box<dyn StorageOverride<Block> + Send + Sync>) -> Self {
+		Self { client, inner }
+	}
+}
+
+impl<C> StorageOverride<Block> for AuthorDigestOverride<C>
+where
+	C: HeaderBackend<Block> + Send + Sync,
+{
+	fn account_code_at(&self, block: &BlockId<Block>, address: H160) -> Option<Vec<u8>> {
+		self.inner.account_code_at(block, address)
+	}
+
+	fn storage_at(&self, block: &BlockId<Block>, address: H160, index: U256) -> Option<H256> {
+		self.inner.storage_at(block, address, index)
+	}
+
+	fn current_block(&self, block: &BlockId<Block>) -> Option<EthereumBlock> {
+		let mut eth_block = self.inner.current_block(block)?;
+		if eth_block.header.beneficiary.is_zero() {
+			if let Ok(Some(header)) = self.client.header(block.clone()) {
+				if let Some(author) = author_inherent::author_from_header::<AccountId, _>(&header) {
+					eth_block.header.beneficiary = author;
+				}
+			}
+		}
+		Some(eth_block)
+	}
+
+	fn current_receipts(&self, block: &BlockId<Block>) -> Option<Vec<EthereumReceipt>> {
+		self.inner.current_receipts(block)
+	}
+
+	fn current_transaction_statuses(
+		&self,
+		block: &BlockId<Block>,
+	) -> Option<Vec<TransactionStatus>> {
+		self.inner.current_transaction_statuses(block)
+	}
+}
+
 /// Full client dependencies.
 pub struct FullDeps<C, P, A: ChainApi, BE> {
 	/// The client instance to use.
@@ -70,12 +123,18 @@ pub struct FullDeps<C, P, A: ChainApi, BE> {
 	pub backend: Arc<BE>,
 	/// Manual seal command sink
 	pub command_sink: Option<futures::channel::mpsc::Sender<EngineCommand<Hash>>>,
+	/// Relay chain mocked by the dev service.
+	pub mock_relay: Option<MockRelayState>,
 	/// Debug server requester.
 	pub debug_requester: Option<DebugRequester>,
 	/// Trace filter cache server requester.
 	pub trace_filter_requester: Option<TraceFilterCacheRequester>,
+	/// Trace filter on-disk index.
+	pub trace_index: Option<Arc<TraceIndex>>,
 	/// Trace filter max count.
 	pub trace_filter_max_count: u32,
+	/// Trace filter max block range.
+	pub trace_filter_max_block_range: u32,
 }
 
 /// Instantiate all Full RPC extensions.
@@ -122,11 +181,14 @@ where
 		filter_pool,
 		ethapi_cmd,
 		command_sink,
+		mock_relay,
 		frontier_backend,
 		backend: _,
 		debug_requester,
 		trace_filter_requester,
+		trace_index,
 		trace_filter_max_count,
+		trace_filter_max_block_range,
 	} = deps;
 
 	io.extend_with(SystemApi::to_delegate(FullSystem::new(
@@ -144,8 +206,10 @@ where
 	let mut overrides = BTreeMap::new();
 	overrides.insert(
 		EthereumStorageSchema::V1,
-		Box::new(SchemaV1Override::new(client.clone()))
-			as Box<dyn StorageOverride<_> + Send + Sync>,
+		Box::new(AuthorDigestOverride::new(
+			client.clone(),
+			Box::new(SchemaV1Override::new(client.clone())),
+		)) as Box<dyn StorageOverride<_> + Send + Sync>,
 	);
 
 	io.extend_with(EthApiServer::to_delegate(EthApi::new(
@@ -198,11 +262,18 @@ where
 		);
 	};
 
+	if let Some(mock_relay) = mock_relay {
+		io.extend_with(DevApiServer::to_delegate(Dev::new(mock_relay)));
+	}
+
 	if let Some(trace_filter_requester) = trace_filter_requester {
 		io.extend_with(TraceServer::to_delegate(Trace::new(
 			client,
+			frontier_backend,
 			trace_filter_requester,
+			trace_index,
 			trace_filter_max_count,
+			trace_filter_max_block_range,
 		)));
 	}
 
@@ -25,7 +25,8 @@
 use crate::cli::EthApi as EthApiCmd;
 use crate::{
 	cli::{RunCmd, Sealing},
-	inherents::build_inherent_data_providers,
+	inherents::{build_inherent_data_providers, ChainRelayState, MockRelayState},
+	stats::{collator_stats_metrics_task, CollatorStatsMetrics},
 };
 use async_io::Timer;
 use cumulus_client_consensus_relay_chain::{
@@ -40,7 +41,8 @@ use fc_mapping_sync::MappingSyncWorker;
 use fc_rpc::EthTask;
 use fc_rpc_core::types::{FilterPool, PendingTransactions};
 use futures::{Stream, StreamExt};
-use thales_rpc_debug::DebugHandler;
+use thales_rpc_debug::{DebugHandler, RawTraceLimits};
+use thales_rpc_trace::{IndexerTask, TraceIndex};
 use thales_runtime::{opaque::Block, RuntimeApi};
 use polkadot_primitives::v0::CollatorPair;
 use sc_cli::SubstrateCli;
@@ -52,9 +54,12 @@ use sc_service::{
 	error::Error as ServiceError, BasePath, Configuration, PartialComponents, Role, TFullBackend,
 	TFullClient, TaskManager,
 };
+use sp_blockchain::HeaderBackend;
 use sp_core::{H160, H256};
+use sp_runtime::generic::BlockId;
 use std::{
 	collections::{BTreeMap, HashMap},
+	path::PathBuf,
 	sync::{Arc, Mutex},
 	time::Duration,
 };
@@ -73,18 +78,21 @@ type FullClient = TFullClient<Block, RuntimeApi, Executor>;
 type FullBackend = TFullBackend<Block>;
 type MaybeSelectChain = Option<sc_consensus::LongestChain<FullBackend, Block>>;
 
-// TODO This is copied from frontier. It should be imported instead after
-// https://github.com/paritytech/frontier/issues/333 is solved
-pub fn open_frontier_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
-	let config_dir = config
+fn config_dir(config: &Configuration) -> PathBuf {
+	config
 		.base_path
 		.as_ref()
 		.map(|base_path| base_path.config_dir(config.chain_spec.id()))
 		.unwrap_or_else(|| {
 			BasePath::from_project("", "", &crate::cli::Cli::executable_name())
 				.config_dir(config.chain_spec.id())
-		});
-	let database_dir = config_dir.join("frontier").join("db");
+		})
+}
+
+// TODO This is copied from frontier. It should be imported instead after
+// https://github.com/paritytech/frontier/issues/333 is solved
+pub fn open_frontier_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
+	let database_dir = config_dir(config).join("frontier").join("db");
 
 	Ok(Arc::new(fc_db::Backend::<Block>::new(
 		&fc_db::DatabaseSettings {
@@ -96,6 +104,13 @@ pub fn open_frontier_backend(config: &Configuration) -> Result<Arc<fc_db::Backen
 	)?))
 }
 
+/// Open the on-disk index of the traces served by `trace_filter`.
+pub fn open_trace_index(config: &Configuration) -> Result<Arc<TraceIndex>, String> {
+	let database_dir = config_dir(config).join("trace").join("db");
+
+	Ok(Arc::new(TraceIndex::open(&database_dir)?))
+}
+
 /// Builds the PartialComponents for a parachain or development service
 ///
 /// Use this function if you don't actually need the full service, but just the partial in order to
@@ -119,12 +134,11 @@ pub fn new_partial(
 			Option<Telemetry>,
 			Option<TelemetryWorkerHandle>,
 			Arc<fc_db::Backend<Block>>,
+			Option<MockRelayState>,
 		),
 	>,
 	ServiceError,
 > {
-	let inherent_data_providers = build_inherent_data_providers(author, dev_service)?;
-
 	let telemetry = config
 		.telemetry_endpoints
 		.clone()
@@ -144,6 +158,25 @@ pub fn new_partial(
 
 	let client = Arc::new(client);
 
+	// The dev service mocks the relay chain, which delivers messages to our own para id and
+	// carries on from the relay state stored in our best block.
+	let mock_relay = if dev_service {
+		let para_id = crate::chain_spec::Extensions::try_get(&*config.chain_spec)
+			.map(|e| e.para_id)
+			.unwrap_or_default();
+		let client = client.clone();
+		Some(MockRelayState::new(para_id.into(), move || {
+			let best = BlockId::Hash(client.info().best_hash);
+			ChainRelayState::read(&*client, &best).unwrap_or_else(|e| {
+				log::error!("Failed to read the relay state of the best block: {:?}", e);
+				Default::default()
+			})
+		}))
+	} else {
+		None
+	};
+	let inherent_data_providers = build_inherent_data_providers(author, mock_relay.clone())?;
+
 	let telemetry_worker_handle = telemetry.as_ref().map(|(worker, _)| worker.handle());
 
 	let telemetry = telemetry.map(|(worker, telemetry)| {
@@ -212,6 +245,7 @@ pub fn new_partial(
 			telemetry,
 			telemetry_worker_handle,
 			frontier_backend,
+			mock_relay,
 		),
 	})
 }
@@ -250,6 +284,7 @@ where
 		mut telemetry,
 		telemetry_worker_handle,
 		frontier_backend,
+		_,
 	) = params.other;
 
 	let polkadot_full_node = cumulus_client_service::build_polkadot_full_node(
@@ -291,10 +326,27 @@ where
 
 	let permit_pool = Arc::new(Semaphore::new(cmd.ethapi_max_permits as usize));
 
+	let trace_index = if cmd.ethapi.contains(&EthApiCmd::Trace) && cmd.ethapi_trace_index {
+		Some(open_trace_index(&parachain_config)?)
+	} else {
+		None
+	};
+
+	let trace_index_task = trace_index.clone().map(|trace_index| {
+		IndexerTask::create(
+			Arc::clone(&client),
+			Arc::clone(&backend),
+			trace_index,
+			Arc::clone(&permit_pool),
+		)
+	});
+
 	let (trace_filter_task, trace_filter_requester) = if cmd.ethapi.contains(&EthApiCmd::Trace) {
 		let (trace_filter_task, trace_filter_requester) = thales_rpc_trace::CacheTask::create(
 			Arc::clone(&client),
 			Arc::clone(&backend),
+			transaction_pool.clone(),
+			trace_index.clone(),
 			Duration::from_secs(cmd.ethapi_trace_cache_duration),
 			Arc::clone(&permit_pool),
 		);
@@ -309,6 +361,12 @@ where
 			Arc::clone(&backend),
 			Arc::clone(&frontier_backend),
 			Arc::clone(&permit_pool),
+			trace_filter_requester.clone(),
+			RawTraceLimits {
+				max_step_logs: cmd.ethapi_debug_max_step_logs,
+				max_step_logs_bytes: cmd.ethapi_debug_max_step_logs_bytes,
+				steps_per_second: cmd.ethapi_debug_steps_per_second,
+			},
 		);
 		(Some(debug_task), Some(debug_requester))
 	} else {
@@ -337,11 +395,14 @@ where
 				filter_pool: filter_pool.clone(),
 				ethapi_cmd: ethapi_cmd.clone(),
 				command_sink: None,
+				mock_relay: None,
 				frontier_backend: frontier_backend.clone(),
 				backend: backend.clone(),
 				debug_requester: debug_requester.clone(),
 				trace_filter_requester: trace_filter_requester.clone(),
+				trace_index: trace_index.clone(),
 				trace_filter_max_count: cmd.ethapi_trace_max_count,
+				trace_filter_max_block_range: cmd.ethapi_trace_max_block_range,
 			};
 
 			crate::rpc::create_full(deps, subscription_task_executor.clone())
@@ -383,6 +444,13 @@ where
 			.spawn("trace-filter-cache", trace_filter_task);
 	}
 
+	// Spawn trace_filter index task if enabled.
+	if let Some(trace_index_task) = trace_index_task {
+		task_manager
+			.spawn_essential_handle()
+			.spawn("trace-filter-indexer", trace_index_task);
+	}
+
 	// Spawn debug task if enabled.
 	if let Some(debug_task) = debug_task {
 		task_manager
@@ -390,6 +458,17 @@ where
 			.spawn("ethapi-debug", debug_task);
 	}
 
+	// Spawn the task following collator block production statistics if Prometheus is enabled.
+	if let Some(registry) = prometheus_registry.as_ref() {
+		let metrics = CollatorStatsMetrics::register(registry).map_err(|e| {
+			ServiceError::Other(format!("Failed to register collator metrics: {:?}", e))
+		})?;
+		task_manager.spawn_handle().spawn(
+			"collator-stats-metrics",
+			collator_stats_metrics_task(client.clone(), metrics),
+		);
+	}
+
 	// Spawn Frontier EthFilterApi maintenance task.
 	if let Some(filter_pool) = filter_pool {
 		// Each filter is allowed to stay in the pool for 100 blocks.
@@ -518,6 +597,7 @@ pub fn new_dev(
 				telemetry,
 				_telemetry_worker_handle,
 				frontier_backend,
+				mock_relay,
 			),
 	} = new_partial(&config, author_id, true)?;
 
@@ -612,10 +692,27 @@ pub fn new_dev(
 
 	let permit_pool = Arc::new(Semaphore::new(cmd.ethapi_max_permits as usize));
 
+	let trace_index = if cmd.ethapi.contains(&EthApiCmd::Trace) && cmd.ethapi_trace_index {
+		Some(open_trace_index(&config)?)
+	} else {
+		None
+	};
+
+	let trace_index_task = trace_index.clone().map(|trace_index| {
+		IndexerTask::create(
+			Arc::clone(&client),
+			Arc::clone(&backend),
+			trace_index,
+			Arc::clone(&permit_pool),
+		)
+	});
+
 	let (trace_filter_task, trace_filter_requester) = if cmd.ethapi.contains(&EthApiCmd::Trace) {
 		let (trace_filter_task, trace_filter_requester) = thales_rpc_trace::CacheTask::create(
 			Arc::clone(&client),
 			Arc::clone(&backend),
+			transaction_pool.clone(),
+			trace_index.clone(),
 			Duration::from_secs(cmd.ethapi_trace_cache_duration),
 			Arc::clone(&permit_pool),
 		);
@@ -630,6 +727,12 @@ pub fn new_dev(
 			Arc::clone(&backend),
 			Arc::clone(&frontier_backend),
 			Arc::clone(&permit_pool),
+			trace_filter_requester.clone(),
+			RawTraceLimits {
+				max_step_logs: cmd.ethapi_debug_max_step_logs,
+				max_step_logs_bytes: cmd.ethapi_debug_max_step_logs_bytes,
+				steps_per_second: cmd.ethapi_debug_steps_per_second,
+			},
 		);
 		(Some(debug_task), Some(debug_requester))
 	} else {
@@ -658,11 +761,14 @@ pub fn new_dev(
 				filter_pool: filter_pool.clone(),
 				ethapi_cmd: ethapi_cmd.clone(),
 				command_sink: command_sink.clone(),
+				mock_relay: mock_relay.clone(),
 				frontier_backend: frontier_backend.clone(),
 				backend: backend.clone(),
 				debug_requester: debug_requester.clone(),
 				trace_filter_requester: trace_filter_requester.clone(),
+				trace_index: trace_index.clone(),
 				trace_filter_max_count: cmd.ethapi_trace_max_count,
+				trace_filter_max_block_range: cmd.ethapi_trace_max_block_range,
 			};
 			crate::rpc::create_full(deps, subscription_task_executor.clone())
 		})
@@ -703,6 +809,13 @@ pub fn new_dev(
 			.spawn("trace-filter-cache", trace_filter_task);
 	}
 
+	// Spawn trace_filter index task if enabled.
+	if let Some(trace_index_task) = trace_index_task {
+		task_manager
+			.spawn_essential_handle()
+			.spawn("trace-filter-indexer", trace_index_task);
+	}
+
 	// Spawn debug task if enabled.
 	if let Some(debug_task) = debug_task {
 		task_manager
@@ -710,6 +823,17 @@ pub fn new_dev(
 			.spawn("ethapi-debug", debug_task);
 	}
 
+	// Spawn the task following collator block production statistics if Prometheus is enabled.
+	if let Some(registry) = prometheus_registry.as_ref() {
+		let metrics = CollatorStatsMetrics::register(registry).map_err(|e| {
+			ServiceError::Other(format!("Failed to register collator metrics: {:?}", e))
+		})?;
+		task_manager.spawn_handle().spawn(
+			"collator-stats-metrics",
+			collator_stats_metrics_task(client.clone(), metrics),
+		);
+	}
+
 	// Spawn Frontier EthFilterApi maintenance task.
 	if let Some(filter_pool) = filter_pool {
 		// Each filter is allowed to stay in the pool for 100 blocks.
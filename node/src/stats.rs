@@ -0,0 +1,175 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Collator block production statistics, as recorded by the runtime.
+//!
+//! This module provides the Prometheus metrics that follow the statistics of the current round
+//! at the best block, and the `stats` subcommand printing the last rounds of a given collator.
+
+use crate::cli::StatsCmd;
+use futures::StreamExt;
+use prometheus_endpoint::{register, Gauge, GaugeVec, Opts, PrometheusError, Registry, F64, U64};
+use sc_client_api::BlockchainEvents;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::generic::BlockId;
+use std::sync::Arc;
+use thales_primitives_collator_stats::CollatorStatsApi;
+use thales_runtime::{opaque::Block, AccountId};
+
+/// Prometheus gauges of the current round statistics, labelled by collator.
+#[derive(Clone)]
+pub struct CollatorStatsMetrics {
+	round: Gauge<U64>,
+	blocks_authored: GaugeVec<U64>,
+	times_eligible: GaugeVec<U64>,
+	authorship_ratio: GaugeVec<F64>,
+}
+
+impl CollatorStatsMetrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			round: register(
+				Gauge::new("thales_staking_round", "Current staking round")?,
+				registry,
+			)?,
+			blocks_authored: register(
+				GaugeVec::new(
+					Opts::new(
+						"thales_collator_blocks_authored",
+						"Blocks authored by the collator in the current round",
+					),
+					&["collator"],
+				)?,
+				registry,
+			)?,
+			times_eligible: register(
+				GaugeVec::new(
+					Opts::new(
+						"thales_collator_times_eligible",
+						"Blocks for which the collator was eligible in the current round",
+					),
+					&["collator"],
+				)?,
+				registry,
+			)?,
+			authorship_ratio: register(
+				GaugeVec::new(
+					Opts::new(
+						"thales_collator_authorship_ratio",
+						"Share of eligible blocks the collator authored in the current round",
+					),
+					&["collator"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Task refreshing the metrics every time a new best block is imported.
+pub async fn collator_stats_metrics_task<C>(client: Arc<C>, metrics: CollatorStatsMetrics)
+where
+	C: ProvideRuntimeApi<Block> + BlockchainEvents<Block>,
+	C::Api: CollatorStatsApi<Block, AccountId>,
+{
+	let mut last_round = None;
+	let mut notifications = client.import_notification_stream();
+
+	while let Some(notification) = notifications.next().await {
+		if !notification.is_new_best {
+			continue;
+		}
+
+		let api = client.runtime_api();
+		let at = BlockId::Hash(notification.hash);
+		let (round, stats) = match api
+			.current_round(&at)
+			.and_then(|round| api.round_stats(&at, round).map(|stats| (round, stats)))
+		{
+			Ok(result) => result,
+			Err(e) => {
+				log::debug!(target: "collator-stats", "Failed to fetch stats: {:?}", e);
+				continue;
+			}
+		};
+
+		// Collators of the previous round may not be part of this one.
+		if last_round != Some(round) {
+			metrics.blocks_authored.reset();
+			metrics.times_eligible.reset();
+			metrics.authorship_ratio.reset();
+			last_round = Some(round);
+		}
+
+		metrics.round.set(round as u64);
+		for (collator, stats) in stats {
+			let label = format!("{:?}", collator);
+			metrics
+				.blocks_authored
+				.with_label_values(&[&label])
+				.set(stats.blocks_authored as u64);
+			metrics
+				.times_eligible
+				.with_label_values(&[&label])
+				.set(stats.times_eligible as u64);
+			metrics
+				.authorship_ratio
+				.with_label_values(&[&label])
+				.set(stats.authorship_ratio().deconstruct() as f64 / 1_000_000_000f64);
+		}
+	}
+}
+
+impl StatsCmd {
+	/// Print the statistics of the collator for the last rounds, read from the local database at
+	/// the best block.
+	pub fn run<C>(&self, client: Arc<C>) -> sc_cli::Result<()>
+	where
+		C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+		C::Api: CollatorStatsApi<Block, AccountId>,
+	{
+		let api = client.runtime_api();
+		let at = BlockId::Hash(client.info().best_hash);
+		let current = api
+			.current_round(&at)
+			.map_err(|e| format!("Failed to fetch the current round: {:?}", e))?;
+		let from = current.saturating_sub(self.rounds.saturating_sub(1));
+		let stats = api
+			.collator_stats(&at, self.collator, from, current)
+			.map_err(|e| format!("Failed to fetch the collator statistics: {:?}", e))?;
+
+		println!(
+			"Collator {:?}, rounds {} to {} (best block #{})",
+			self.collator,
+			from,
+			current,
+			client.info().best_number
+		);
+		println!("{:>8} {:>10} {:>10} {:>8}", "round", "authored", "eligible", "ratio");
+		for (round, stats) in stats {
+			println!(
+				"{:>8} {:>10} {:>10} {:>7.2}%",
+				round,
+				stats.blocks_authored,
+				stats.times_eligible,
+				stats.authorship_ratio().deconstruct() as f64 / 10_000_000f64
+			);
+		}
+
+		Ok(())
+	}
+}
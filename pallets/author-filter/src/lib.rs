@@ -42,6 +42,7 @@ pub mod pallet {
 	use frame_system::pallet_prelude::*;
 	use sp_core::H256;
 	use sp_runtime::Percent;
+	use thales_primitives_collator_stats::CollatorRoundStats;
 
 	/// The Author Filter pallet
 	#[pallet::pallet]
@@ -56,6 +57,8 @@ pub mod pallet {
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 		/// Deterministic on-chain pseudo-randomness used to do the filtering
 		type RandomnessSource: Randomness<H256, Self::BlockNumber>;
+		/// Number of past rounds for which block production statistics are kept
+		type StatsHistoryDepth: Get<u32>;
 	}
 
 	// This code will be called by the author-inherent pallet to check whether the reported author
@@ -68,6 +71,42 @@ pub mod pallet {
 	// inherent.
 	impl<T: Config> author_inherent::CanAuthor<T::AccountId> for Pallet<T> {
 		fn can_author(account: &T::AccountId) -> bool {
+			let eligible = Self::eligible_authors();
+
+			log::trace!(
+				target:"author-filter",
+				"The id I'm checking is: {:?}",
+				account
+			);
+			log::trace!(
+				target:"author-filter",
+				"Was that author eligible: {}",
+				eligible.contains(account)
+			);
+
+			eligible.contains(account)
+		}
+	}
+
+	// Record block production statistics once the author of the block is known. The eligible set
+	// is computed again here, so this handler has the same inherent ordering requirement as the
+	// `CanAuthor` implementation above.
+	impl<T: Config> author_inherent::EventHandler<T::AccountId> for Pallet<T> {
+		fn note_author(author: T::AccountId) {
+			let round = <parachain_staking::Pallet<T>>::round().current;
+			Self::prune_stats(round);
+
+			for account in Self::eligible_authors() {
+				<CollatorStats<T>>::mutate(round, account, |stats| stats.times_eligible += 1);
+			}
+			<CollatorStats<T>>::mutate(round, author, |stats| stats.blocks_authored += 1);
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Compute the pseudorandom subset of the selected candidates that is eligible to author
+		/// at the current height.
+		pub fn eligible_authors() -> Vec<T::AccountId> {
 			let mut staked = <parachain_staking::Pallet<T>>::selected_candidates();
 
 			let num_eligible = EligibleRatio::<T>::get().mul_ceil(staked.len());
@@ -99,11 +138,9 @@ pub mod pallet {
 				// Cast to u32 first so we get the same result on wasm and 64-bit platforms.
 				let index = (randomness.to_low_u64_be() as u32) as usize;
 
-				// Move the selected author from the original vector into the eligible vector
-				// TODO we could short-circuit this check by returning early when the claimed
-				// author is selected. For now I'll leave it like this because:
-				// 1. it is easier to understand what our core filtering logic is
-				// 2. we currently show the entire filtered set in the debug event
+				// Move the selected author from the original vector into the eligible vector.
+				// We don't short-circuit once a particular author is selected because the whole
+				// eligible set is needed for the block production statistics.
 				eligible.push(staked.remove(index % staked.len()));
 
 				// Print some logs for debugging purposes.
@@ -123,23 +160,41 @@ pub mod pallet {
 					"Eligible Authors are: {:?}",
 					eligible
 				);
-				log::trace!(
-					target:"author-filter",
-					"The id I'm checking is: {:?}",
-					account
-				);
-				log::trace!(
-					target:"author-filter",
-					"Was that author eligible: {}",
-					eligible.contains(account)
-				);
 			}
 
-			// Emit an event for debugging purposes
-			// let our_height = frame_system::Module::<T>::block_number();
-			// <Pallet<T>>::deposit_event(Event::Filtered(our_height, relay_height, eligible.clone()));
+			eligible
+		}
 
-			eligible.contains(account)
+		/// Drop the statistics of the round that just left the history window. Only does work on
+		/// the first block of a round.
+		fn prune_stats(round: u32) {
+			if <LastStatsRound<T>>::get() == round {
+				return;
+			}
+			<LastStatsRound<T>>::put(round);
+			let depth = T::StatsHistoryDepth::get();
+			if round >= depth {
+				<CollatorStats<T>>::remove_prefix(round - depth);
+			}
+		}
+
+		/// Statistics of every collator that was eligible or authored during the given round.
+		pub fn round_stats(round: u32) -> Vec<(T::AccountId, CollatorRoundStats)> {
+			<CollatorStats<T>>::iter_prefix(round).collect()
+		}
+
+		/// Statistics of a single collator for each stored round in `from..=to`.
+		pub fn collator_stats(
+			collator: &T::AccountId,
+			from: u32,
+			to: u32,
+		) -> Vec<(u32, CollatorRoundStats)> {
+			let current = <parachain_staking::Pallet<T>>::round().current;
+			let oldest = current.saturating_sub(T::StatsHistoryDepth::get());
+			(from.max(oldest)..=to.min(current))
+				.filter(|round| <CollatorStats<T>>::contains_key(round, collator))
+				.map(|round| (round, <CollatorStats<T>>::get(round, collator)))
+				.collect()
 		}
 	}
 
@@ -164,6 +219,23 @@ pub mod pallet {
 	#[pallet::storage]
 	pub type EligibleRatio<T: Config> = StorageValue<_, Percent, ValueQuery, Half<T>>;
 
+	/// Block production statistics of each collator, per staking round.
+	#[pallet::storage]
+	#[pallet::getter(fn stats)]
+	pub type CollatorStats<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		u32,
+		Twox64Concat,
+		T::AccountId,
+		CollatorRoundStats,
+		ValueQuery,
+	>;
+
+	/// Round in which statistics were last recorded. Used to prune old rounds once per round.
+	#[pallet::storage]
+	pub type LastStatsRound<T: Config> = StorageValue<_, u32, ValueQuery>;
+
 	// Default value for the `EligibleRatio` is one half.
 	#[pallet::type_value]
 	pub fn Half<T: Config>() -> Percent {
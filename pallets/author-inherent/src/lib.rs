@@ -20,26 +20,29 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{
-	decl_error, decl_module, decl_storage, ensure, log,
-	traits::FindAuthor,
-	weights::{DispatchClass, Weight},
-};
-use frame_system::{ensure_none, Config as System};
+use frame_support::pallet;
 use parity_scale_codec::{Decode, Encode};
 #[cfg(feature = "std")]
 use sp_inherents::ProvideInherentData;
-use sp_inherents::{InherentData, InherentIdentifier, IsFatalError, ProvideInherent};
-use sp_runtime::{ConsensusEngineId, DigestItem, RuntimeString};
+use sp_inherents::{InherentData, InherentIdentifier, IsFatalError};
+use sp_runtime::{traits::Header as HeaderT, ConsensusEngineId, DigestItem, RuntimeString};
 use sp_std::vec::Vec;
 
+pub mod weights;
+
+pub use pallet::*;
+
 /// The given account ID is the author of the current block.
 pub trait EventHandler<Author> {
 	fn note_author(author: Author);
 }
 
-impl<T> EventHandler<T> for () {
-	fn note_author(_author: T) {}
+// Allows several pallets to be notified, eg `(ParachainStaking, AuthorFilter)`.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+impl<Author: Clone> EventHandler<Author> for Tuple {
+	fn note_author(author: Author) {
+		for_tuples!( #( Tuple::note_author(author.clone()); )* );
+	}
 }
 
 /// Permissions for what block author can be set in this pallet
@@ -55,55 +58,113 @@ impl<T> CanAuthor<T> for () {
 	}
 }
 
-pub trait Config: System {
-	/// Other pallets that want to be informed about block authorship
-	type EventHandler: EventHandler<Self::AccountId>;
-
-	/// A preliminary means of checking the validity of this author. This check is run before
-	/// block execution begins when data from previous inherent is unavailable. This is meant to
-	/// quickly invalidate blocks from obviously-invalid authors, although it need not rule out all
-	/// invlaid authors. The final check will be made when executing the inherent.
-	type PreliminaryCanAuthor: CanAuthor<Self::AccountId>;
-
-	/// The final word on whether the reported author can author at this height.
-	/// This will be used when executing the inherent. This check is often stricter than the
-	/// Preliminary check, because it can use more data.
-	/// If the pallet that implements this trait depends on an inherent, that inherent **must**
-	/// be included before this one.
-	type FinalCanAuthor: CanAuthor<Self::AccountId>;
+/// Extract the author claimed by this pallet's consensus digest, if any.
+pub fn author_from_header<AccountId: Decode, Header: HeaderT>(
+	header: &Header,
+) -> Option<AccountId> {
+	header.digest().logs().iter().find_map(|log| match log {
+		DigestItem::Consensus(id, data) if id == &ENGINE_ID => {
+			AccountId::decode(&mut &data[..]).ok()
+		}
+		_ => None,
+	})
 }
 
-decl_error! {
-	pub enum Error for Module<T: Config> {
+#[pallet]
+pub mod pallet {
+	use super::*;
+	use crate::weights::WeightInfo;
+	use frame_support::{log, pallet_prelude::*, traits::FindAuthor};
+	use frame_system::pallet_prelude::*;
+	use sp_inherents::ProvideInherent;
+	use sp_runtime::traits::{One, Saturating};
+
+	/// The Author Inherent pallet
+	#[pallet::pallet]
+	pub struct Pallet<T>(PhantomData<T>);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Other pallets that want to be informed about block authorship
+		type EventHandler: EventHandler<Self::AccountId>;
+
+		/// A preliminary means of checking the validity of this author. This check is run before
+		/// block execution begins when data from previous inherent is unavailable. This is meant
+		/// to quickly invalidate blocks from obviously-invalid authors, although it need not rule
+		/// out all invlaid authors. The final check will be made when executing the inherent.
+		type PreliminaryCanAuthor: CanAuthor<Self::AccountId>;
+
+		/// The final word on whether the reported author can author at this height.
+		/// This will be used when executing the inherent. This check is often stricter than the
+		/// Preliminary check, because it can use more data.
+		/// If the pallet that implements this trait depends on an inherent, that inherent **must**
+		/// be included before this one.
+		type FinalCanAuthor: CanAuthor<Self::AccountId>;
+
+		/// Number of past blocks for which the author is kept in `AuthorHistory`.
+		type AuthorHistoryDepth: Get<Self::BlockNumber>;
+
+		/// Weight information for extrinsics in this pallet. The `set_author` weight must account
+		/// for the `FinalCanAuthor` filter and the `EventHandler` it triggers.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
 		/// Author already set in block.
 		AuthorAlreadySet,
 		/// The author in the inherent is not an eligible author.
 		CannotBeAuthor,
 	}
-}
 
-decl_storage! {
-	trait Store for Module<T: Config> as Author {
-		/// Author of current block.
-		Author: Option<T::AccountId>;
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(crate) fn deposit_event)]
+	#[pallet::metadata(T::AccountId = "AccountId", T::BlockNumber = "BlockNumber")]
+	pub enum Event<T: Config> {
+		/// The author of the current block has been set
+		AuthorSet(T::AccountId),
 	}
-}
 
-decl_module! {
-	pub struct Module<T: Config> for enum Call where origin: T::Origin {
-		type Error = Error<T>;
+	/// Author of current block.
+	#[pallet::storage]
+	#[pallet::getter(fn author)]
+	pub type Author<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
-		fn on_initialize() -> Weight {
+	/// Authors of the most recent `AuthorHistoryDepth` blocks, keyed by block number.
+	#[pallet::storage]
+	#[pallet::getter(fn author_history)]
+	pub type AuthorHistory<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, T::AccountId, OptionQuery>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
 			<Author<T>>::kill();
-			0
+			T::DbWeight::get().writes(1)
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			// The inherent is required by `is_inherent_required`, so a block without an author
+			// is rejected during import. We don't panic here because that would turn a missing
+			// inherent into an unrecoverable block production failure.
+			if <Author<T>>::get().is_none() {
+				log::error!(
+					target:"author-inherent",
+					"No valid author set in block {:?}",
+					n
+				);
+			}
 		}
+	}
 
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
 		/// Inherent to set the author of a block
-		#[weight = (
-			0,
-			DispatchClass::Mandatory
-		)]
-		fn set_author(origin, author: T::AccountId) {
+		#[pallet::weight((T::WeightInfo::set_author(), DispatchClass::Mandatory))]
+		pub fn set_author(origin: OriginFor<T>, author: T::AccountId) -> DispatchResultWithPostInfo {
 			log::trace!(target:"author-inherent", "In the author inherent dispatchable");
 
 			ensure_none(origin)?;
@@ -115,7 +176,8 @@ decl_module! {
 				target:"author-inherent",
 				"Passed ensures. About to write claimed author to storage."
 			);
-			Author::<T>::put(&author);
+			<Author<T>>::put(&author);
+			Self::record_author(&author);
 
 			// Add a digest item so Apps can detect the block author
 			// For now we use the Consensus digest item.
@@ -126,31 +188,113 @@ decl_module! {
 			));
 
 			// Notify any other pallets that are listening (eg rewards) about the author
-			T::EventHandler::note_author(author);
+			T::EventHandler::note_author(author.clone());
+
+			Self::deposit_event(Event::AuthorSet(author));
+
+			Ok(().into())
 		}
+	}
 
-		fn on_finalize(_n: T::BlockNumber) {
-			log::trace!(
-				target:"author-inherent",
-				"In author inherent's on finalize. About to assert author was set"
-			);
-			assert!(Author::<T>::get().is_some(), "No valid author set in block");
-			log::trace!(
-				target:"author-inherent",
-				"Finished asserting author was set (apparently it was)"
-			);
+	impl<T: Config> Pallet<T> {
+		/// Record the author of the current block in the history, dropping the entry that just
+		/// left the window.
+		fn record_author(author: &T::AccountId) {
+			let now = frame_system::Pallet::<T>::block_number();
+			<AuthorHistory<T>>::insert(now, author);
+			let depth = T::AuthorHistoryDepth::get();
+			if now >= depth {
+				<AuthorHistory<T>>::remove(now - depth);
+			}
+		}
+
+		/// Authors of the blocks in `from..=to` that are still in the history window.
+		pub fn authors(
+			from: T::BlockNumber,
+			to: T::BlockNumber,
+		) -> Vec<(T::BlockNumber, T::AccountId)> {
+			let now = frame_system::Pallet::<T>::block_number();
+			let oldest = now.saturating_sub(T::AuthorHistoryDepth::get());
+			let mut authors = Vec::new();
+			let mut number = from.max(oldest);
+			while number <= to.min(now) {
+				if let Some(author) = <AuthorHistory<T>>::get(number) {
+					authors.push((number, author));
+				}
+				number += One::one();
+			}
+			authors
 		}
 	}
-}
 
-impl<T: Config> FindAuthor<T::AccountId> for Module<T> {
-	fn find_author<'a, I>(_digests: I) -> Option<T::AccountId>
-	where
-		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
-	{
-		// We don't use the digests at all.
-		// This will only return the correct author _after_ the authorship inherent is processed.
-		<Author<T>>::get()
+	#[pallet::inherent]
+	impl<T: Config> ProvideInherent for Pallet<T> {
+		type Call = Call<T>;
+		type Error = InherentError;
+		const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+		fn is_inherent_required(_: &InherentData) -> Result<Option<Self::Error>, Self::Error> {
+			// Return Ok(Some(_)) unconditionally because this inherent is required in every block
+			// If it is not found, throw an AuthorInherentRequired error.
+			Ok(Some(InherentError::Other(
+				sp_runtime::RuntimeString::Borrowed("AuthorInherentRequired"),
+			)))
+		}
+
+		fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+			// Grab the Vec<u8> labelled with "author__" from the map of all inherent data
+			let author_raw = data
+				.get_data::<InherentType>(&INHERENT_IDENTIFIER)
+				.expect("Gets and decodes authorship inherent data")?;
+
+			//TODO we need to make the author _prove_ their identity, not just claim it.
+			// we should have them sign something here. Best idea so far: parent block hash.
+
+			// Decode the Vec<u8> into an account Id
+			let author = T::AccountId::decode(&mut &author_raw[..])
+				.expect("Decodes author raw inherent data");
+
+			Some(Call::set_author(author))
+		}
+
+		fn check_inherent(call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
+			// We only care to check the inherent provided by this pallet.
+			// This check runs outside of block execution, so it is not weighed. The stricter
+			// `FinalCanAuthor` check (and its weight) is part of `set_author` itself.
+			if let Self::Call::set_author(claimed_author) = call {
+				log::trace!(
+					target:"author-inherent",
+					"In the author inherent's `check_inherent` impl"
+				);
+				ensure!(
+					T::PreliminaryCanAuthor::can_author(&claimed_author),
+					InherentError::Other(sp_runtime::RuntimeString::Borrowed("Cannot Be Author"))
+				);
+			}
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> FindAuthor<T::AccountId> for Pallet<T> {
+		fn find_author<'a, I>(digests: I) -> Option<T::AccountId>
+		where
+			I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+		{
+			// Prefer the digest deposited by `set_author`, which describes the block being
+			// inspected. Fall back on storage, which will only return the correct author _after_
+			// the authorship inherent is processed.
+			digests
+				.into_iter()
+				.find_map(|(id, mut data)| {
+					if id == ENGINE_ID {
+						T::AccountId::decode(&mut data).ok()
+					} else {
+						None
+					}
+				})
+				.or_else(<Author<T>>::get)
+		}
 	}
 }
 
@@ -211,52 +355,6 @@ impl ProvideInherentData for InherentDataProvider {
 	}
 }
 
-impl<T: Config> ProvideInherent for Module<T> {
-	type Call = Call<T>;
-	type Error = InherentError;
-	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
-
-	fn is_inherent_required(_: &InherentData) -> Result<Option<Self::Error>, Self::Error> {
-		// Return Ok(Some(_)) unconditionally because this inherent is required in every block
-		// If it is not found, throw an AuthorInherentRequired error.
-		Ok(Some(InherentError::Other(
-			sp_runtime::RuntimeString::Borrowed("AuthorInherentRequired"),
-		)))
-	}
-
-	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
-		// Grab the Vec<u8> labelled with "author__" from the map of all inherent data
-		let author_raw = data
-			.get_data::<InherentType>(&INHERENT_IDENTIFIER)
-			.expect("Gets and decodes authorship inherent data")?;
-
-		//TODO we need to make the author _prove_ their identity, not just claim it.
-		// we should have them sign something here. Best idea so far: parent block hash.
-
-		// Decode the Vec<u8> into an account Id
-		let author =
-			T::AccountId::decode(&mut &author_raw[..]).expect("Decodes author raw inherent data");
-
-		Some(Call::set_author(author))
-	}
-
-	fn check_inherent(call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
-		// We only care to check the inherent provided by this pallet.
-		if let Self::Call::set_author(claimed_author) = call {
-			log::trace!(
-				target:"author-inherent",
-				"In the author inherent's `check_inherent` impl"
-			);
-			ensure!(
-				T::PreliminaryCanAuthor::can_author(&claimed_author),
-				InherentError::Other(sp_runtime::RuntimeString::Borrowed("Cannot Be Author"))
-			);
-		}
-
-		Ok(())
-	}
-}
-
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -264,7 +362,7 @@ mod tests {
 
 	use frame_support::{
 		assert_noop, assert_ok, parameter_types,
-		traits::{OnFinalize, OnInitialize},
+		traits::{FindAuthor, OnFinalize, OnInitialize},
 	};
 	use sp_core::H256;
 	use sp_io::TestExternalities;
@@ -291,12 +389,13 @@ mod tests {
 			UncheckedExtrinsic = UncheckedExtrinsic,
 		{
 			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-			AuthorInherent: author_inherent::{Pallet, Call, Storage, Inherent},
+			AuthorInherent: author_inherent::{Pallet, Call, Storage, Inherent, Event<T>},
 		}
 	);
 
 	parameter_types! {
 		pub const BlockHashCount: u64 = 250;
+		pub const AuthorHistoryDepth: u64 = 3;
 	}
 	impl frame_system::Config for Test {
 		type BaseCallFilter = ();
@@ -312,7 +411,7 @@ mod tests {
 		type AccountId = u64;
 		type Lookup = IdentityLookup<Self::AccountId>;
 		type Header = Header;
-		type Event = ();
+		type Event = Event;
 		type BlockHashCount = BlockHashCount;
 		type Version = ();
 		type PalletInfo = PalletInfo;
@@ -322,10 +421,25 @@ mod tests {
 		type SystemWeightInfo = ();
 		type SS58Prefix = ();
 	}
+	/// Only odd accounts are eligible to author in the mock.
+	pub struct OddAccountsCanAuthor;
+	impl CanAuthor<u64> for OddAccountsCanAuthor {
+		fn can_author(account: &u64) -> bool {
+			account % 2 == 1
+		}
+	}
+
 	impl Config for Test {
+		type Event = Event;
 		type EventHandler = ();
 		type PreliminaryCanAuthor = ();
-		type FinalCanAuthor = ();
+		type FinalCanAuthor = OddAccountsCanAuthor;
+		type AuthorHistoryDepth = AuthorHistoryDepth;
+		type WeightInfo = ();
+	}
+
+	fn last_event() -> Event {
+		System::events().pop().expect("Event expected").event
 	}
 
 	pub fn roll_to(n: u64) {
@@ -347,6 +461,77 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn set_author_emits_event_and_digest() {
+		new_test_ext().execute_with(|| {
+			roll_to(1);
+			assert_ok!(AuthorInherent::set_author(Origin::none(), 3));
+			assert_eq!(AuthorInherent::author(), Some(3));
+			assert_eq!(
+				last_event(),
+				Event::author_inherent(crate::Event::AuthorSet(3))
+			);
+			let digest = System::digest();
+			assert_eq!(
+				digest.logs(),
+				&[DigestItem::Consensus(ENGINE_ID, 3u64.encode())]
+			);
+		});
+	}
+
+	#[test]
+	fn non_eligible_author_is_rejected() {
+		new_test_ext().execute_with(|| {
+			roll_to(1);
+			assert_noop!(
+				AuthorInherent::set_author(Origin::none(), 2),
+				Error::<Test>::CannotBeAuthor
+			);
+			assert_eq!(AuthorInherent::author(), None);
+			// An eligible author can still be set afterwards.
+			assert_ok!(AuthorInherent::set_author(Origin::none(), 1));
+		});
+	}
+
+	#[test]
+	fn author_history_keeps_a_rolling_window() {
+		new_test_ext().execute_with(|| {
+			for (n, author) in [(1, 1), (2, 3), (3, 5), (4, 7)].iter() {
+				roll_to(*n);
+				assert_ok!(AuthorInherent::set_author(Origin::none(), *author));
+			}
+			// Block 1 left the window when block 4 was authored.
+			assert_eq!(AuthorInherent::author_history(1), None);
+			assert_eq!(AuthorInherent::author_history(2), Some(3));
+			assert_eq!(AuthorInherent::author_history(4), Some(7));
+			assert_eq!(
+				AuthorInherent::authors(0, 10),
+				vec![(2, 3), (3, 5), (4, 7)]
+			);
+			assert_eq!(AuthorInherent::authors(3, 3), vec![(3, 5)]);
+		});
+	}
+
+	#[test]
+	fn find_author_prefers_digest() {
+		new_test_ext().execute_with(|| {
+			roll_to(1);
+			assert_ok!(AuthorInherent::set_author(Origin::none(), 1));
+			let digest_author = 5u64.encode();
+			let digests = vec![(ENGINE_ID, &digest_author[..])];
+			assert_eq!(AuthorInherent::find_author(digests), Some(5));
+			assert_eq!(AuthorInherent::find_author(Vec::<(ConsensusEngineId, &[u8])>::new()), Some(1));
+		});
+	}
+
+	#[test]
+	fn missing_author_does_not_panic_on_finalize() {
+		new_test_ext().execute_with(|| {
+			roll_to(1);
+			AuthorInherent::on_finalize(1);
+		});
+	}
+
 	#[test]
 	fn double_author_fails() {
 		new_test_ext().execute_with(|| {
@@ -0,0 +1,55 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Weights for author_inherent
+//!
+//! These are hand-written estimates until the pallet gets benchmarks. `set_author` accounts for
+//! the runtime's `FinalCanAuthor` (the author filter: selected candidates, eligible ratio,
+//! validation data and randomness reads) and `EventHandler` (staking points and per-collator
+//! statistics of the eligible set) implementations, as well as the author history update.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for author_inherent.
+pub trait WeightInfo {
+	fn set_author() -> Weight;
+}
+
+/// Weights for author_inherent using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn set_author() -> Weight {
+		(30_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(10 as Weight))
+			.saturating_add(T::DbWeight::get().writes(12 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn set_author() -> Weight {
+		(30_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(10 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(12 as Weight))
+	}
+}
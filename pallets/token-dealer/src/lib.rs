@@ -14,158 +14,566 @@
 // You should have received a copy of the GNU General Public License
 // along with Thales.  If not, see <http://www.gnu.org/licenses/>.
 
+//! Token dealer pallet: transfers of the native token to the relay chain and to other parachains.
+//!
+//! A transfer withdraws the tokens from the sender here, and sends an XCM message asking the
+//! destination chain to withdraw the same amount of its own native token from the sovereign
+//! account of this parachain, and to deposit it to the destination account. The relay chain is
+//! reached through an upward message, and other parachains through an HRMP channel that must be
+//! open.
+//!
+//! The pallet also handles these messages when they come from other parachains over HRMP: the
+//! tokens are transferred from the sovereign account of the sending parachain.
+
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, DecodeAll, Encode};
+use cumulus_primitives_core::{
+	HrmpMessageHandler, HrmpMessageSender, InboundHrmpMessage, OutboundHrmpMessage, ParaId,
+	UpwardMessageSender,
+};
 use frame_support::{
-	decl_event, decl_module, dispatch::DispatchResult,
-	traits::{Currency, ExistenceRequirement, WithdrawReason},
+	pallet,
+	traits::{Currency, ExistenceRequirement, WithdrawReasons},
 };
-use frame_system::ensure_signed;
-
-use codec::{Decode, Encode};
-use cumulus_primitives::{
-	relay_chain::DownwardMessage,
-	xcmp::{XCMPMessageHandler, XCMPMessageSender},
-	DownwardMessageHandler, ParaId, UpwardMessageOrigin, UpwardMessageSender,
+use sp_runtime::{
+	traits::{AccountIdConversion, SaturatedConversion},
+	DispatchResult,
+};
+use sp_std::vec;
+use xcm::{
+	v0::{Junction, MultiAsset, MultiLocation, NetworkId, Order, Xcm},
+	VersionedXcm,
 };
-use cumulus_upward_message::BalancesMessage;
-use polkadot_parachain::primitives::AccountIdConversion;
 
-#[derive(Encode, Decode)]
-pub enum XCMPMessage<XAccountId, XBalance> {
-	/// Transfer tokens to the given account from the Parachain account.
-	TransferToken(XAccountId, XBalance),
-}
+pub mod weights;
+
+pub use pallet::*;
 
 type BalanceOf<T> =
-	<<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
-type RelayAccountId = sp_runtime::AccountId32;
+/// Account on the relay chain.
+pub type RelayAccountId = sp_runtime::AccountId32;
 
-/// Configuration trait of this pallet.
-pub trait Trait: frame_system::Trait {
-	/// Event type used by the runtime.
-	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+/// Account on another parachain, which uses 20 bytes keys like this one.
+pub type ParachainAccountKey = [u8; 20];
 
-	/// The sender of upward messages.
-	type UpwardMessageSender: UpwardMessageSender<Self::UpwardMessage>;
+/// The message asking a chain to transfer `amount` of its native token from the sovereign account
+/// of the sending parachain to `dest`.
+fn transfer_message(amount: u128, dest: Junction) -> VersionedXcm {
+	VersionedXcm::V0(Xcm::WithdrawAsset {
+		assets: vec![MultiAsset::ConcreteFungible {
+			id: MultiLocation::Null,
+			amount,
+		}],
+		effects: vec![Order::DepositAsset {
+			assets: vec![MultiAsset::All],
+			dest: MultiLocation::X1(dest),
+		}],
+	})
+}
 
-	/// The upward message type used by the Parachain runtime.
-	type UpwardMessage: codec::Codec + BalancesMessage<RelayAccountId, BalanceOf<Self>>;
+/// Parse a message built by `transfer_message` for an account of this parachain, returning the
+/// key of the account and the amount.
+fn parse_transfer_message(mut data: &[u8]) -> Option<(ParachainAccountKey, u128)> {
+	let (assets, effects) = match VersionedXcm::decode(&mut data).ok()? {
+		VersionedXcm::V0(Xcm::WithdrawAsset { assets, effects }) => (assets, effects),
+		_ => return None,
+	};
+	let amount = match &assets[..] {
+		[MultiAsset::ConcreteFungible {
+			id: MultiLocation::Null,
+			amount,
+		}] => *amount,
+		_ => return None,
+	};
+	match &effects[..] {
+		[Order::DepositAsset {
+			assets,
+			dest: MultiLocation::X1(Junction::AccountKey20 { key, .. }),
+		}] if assets[..] == [MultiAsset::All] => Some((*key, amount)),
+		_ => None,
+	}
+}
 
-	/// Currency of the runtime.
-	type Currency: Currency<Self::AccountId>;
+#[pallet]
+pub mod pallet {
+	use super::*;
+	use crate::weights::WeightInfo;
+	use frame_support::{log, pallet_prelude::*, transactional};
+	use frame_system::pallet_prelude::*;
 
-	/// The sender of XCMP messages.
-	type XCMPMessageSender: XCMPMessageSender<XCMPMessage<Self::AccountId, BalanceOf<Self>>>;
-}
+	/// The Token Dealer pallet
+	#[pallet::pallet]
+	pub struct Pallet<T>(PhantomData<T>);
 
-decl_event! {
-	pub enum Event<T> where
-		AccountId = <T as frame_system::Trait>::AccountId,
-		Balance = BalanceOf<T>
-	{
-		/// Transferred tokens to the account on the relay chain.
-		TransferredTokensToRelayChain(RelayAccountId, Balance),
-		/// Transferred tokens to the account on request from the relay chain.
-		TransferredTokensFromRelayChain(AccountId, Balance),
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Currency of the runtime, the native token being transferred.
+		type Currency: Currency<Self::AccountId>;
+
+		/// The sender of upward messages, to the relay chain.
+		type UpwardMessageSender: UpwardMessageSender;
+
+		/// The sender of HRMP messages, to other parachains.
+		type HrmpMessageSender: HrmpMessageSender;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The message could not be queued, eg. because it is too large or because no HRMP
+		/// channel is open to the destination parachain.
+		MessageNotSent,
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(crate) fn deposit_event)]
+	#[pallet::metadata(T::AccountId = "AccountId", BalanceOf<T> = "Balance")]
+	pub enum Event<T: Config> {
+		/// Transferred tokens from the account to the account on the relay chain.
+		TransferredTokensToRelayChain(T::AccountId, RelayAccountId, BalanceOf<T>),
+		/// Transferred tokens from the account to the account on the given parachain.
+		TransferredTokensToParachain(T::AccountId, ParaId, ParachainAccountKey, BalanceOf<T>),
 		/// Transferred tokens to the account from the given parachain account.
-		TransferredTokensViaXCMP(ParaId, AccountId, Balance, DispatchResult),
+		TransferredTokensViaHrmp(ParaId, T::AccountId, BalanceOf<T>, DispatchResult),
 	}
-}
 
-decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin, system = frame_system {
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
 		/// Transfer `amount` of tokens on the relay chain from the Parachain account to
 		/// the given `dest` account.
-		#[weight = 10]
-		fn transfer_tokens_to_relay_chain(origin, dest: RelayAccountId, amount: BalanceOf<T>) {
+		#[pallet::weight(T::WeightInfo::transfer_tokens_to_relay_chain())]
+		#[transactional]
+		pub fn transfer_tokens_to_relay_chain(
+			origin: OriginFor<T>,
+			dest: RelayAccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
+			Self::withdraw(&who, amount)?;
 
-			let _ = T::Currency::withdraw(
-				&who,
-				amount,
-				WithdrawReason::Transfer.into(),
-				ExistenceRequirement::AllowDeath,
-			)?;
+			let dest_junction = Junction::AccountId32 {
+				network: NetworkId::Any,
+				id: dest.clone().into(),
+			};
+			let message = transfer_message(amount.saturated_into(), dest_junction);
+			T::UpwardMessageSender::send_upward_message(message.encode())
+				.map_err(|_| Error::<T>::MessageNotSent)?;
 
-			let msg = <T as Trait>::UpwardMessage::transfer(dest.clone(), amount.clone());
-			<T as Trait>::UpwardMessageSender::send_upward_message(&msg, UpwardMessageOrigin::Signed)
-				.expect("Should not fail; qed");
+			Self::deposit_event(Event::TransferredTokensToRelayChain(who, dest, amount));
 
-			Self::deposit_event(Event::<T>::TransferredTokensToRelayChain(dest, amount));
+			Ok(().into())
 		}
 
 		/// Transfer `amount` of tokens to another parachain.
-		#[weight = 10]
-		fn transfer_tokens_to_parachain_chain(
-			origin,
+		#[pallet::weight(T::WeightInfo::transfer_tokens_to_parachain_chain())]
+		#[transactional]
+		pub fn transfer_tokens_to_parachain_chain(
+			origin: OriginFor<T>,
 			para_id: u32,
-			dest: T::AccountId,
+			dest: ParachainAccountKey,
 			amount: BalanceOf<T>,
-		) {
+		) -> DispatchResultWithPostInfo {
 			//TODO we don't make sure that the parachain has some tokens on the other parachain.
 			let who = ensure_signed(origin)?;
+			Self::withdraw(&who, amount)?;
+
+			let dest_junction = Junction::AccountKey20 {
+				network: NetworkId::Any,
+				key: dest,
+			};
+			let message = transfer_message(amount.saturated_into(), dest_junction);
+			T::HrmpMessageSender::send_hrmp_message(OutboundHrmpMessage {
+				recipient: para_id.into(),
+				data: message.encode(),
+			})
+			.map_err(|_| Error::<T>::MessageNotSent)?;
+
+			Self::deposit_event(Event::TransferredTokensToParachain(
+				who,
+				para_id.into(),
+				dest,
+				amount,
+			));
+
+			Ok(().into())
+		}
+	}
 
-			let _ = T::Currency::withdraw(
-				&who,
+	impl<T: Config> Pallet<T> {
+		fn withdraw(who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+			T::Currency::withdraw(
+				who,
 				amount,
-				WithdrawReason::Transfer.into(),
+				WithdrawReasons::TRANSFER,
 				ExistenceRequirement::AllowDeath,
 			)?;
-
-			T::XCMPMessageSender::send_xcmp_message(
-				para_id.into(),
-				&XCMPMessage::TransferToken(dest, amount),
-			).expect("Should not fail; qed");
+			Ok(())
 		}
+	}
+
+	impl<T: Config> HrmpMessageHandler for Pallet<T> {
+		fn handle_hrmp_message(src: ParaId, msg: InboundHrmpMessage) {
+			// The account must be encoded the same way as the key, eg. as an `H160`.
+			let transfer = parse_transfer_message(&msg.data).and_then(|(key, amount)| {
+				let dest = T::AccountId::decode_all(&key[..]).ok()?;
+				Some((dest, amount.saturated_into::<BalanceOf<T>>()))
+			});
+			let (dest, amount) = match transfer {
+				Some(transfer) => transfer,
+				None => {
+					log::debug!(
+						target: "token-dealer",
+						"Ignoring unknown HRMP message from {:?}",
+						src
+					);
+					return;
+				}
+			};
 
-		fn deposit_event() = default;
+			let para_account = src.into_account();
+			let res = T::Currency::transfer(
+				&para_account,
+				&dest,
+				amount,
+				ExistenceRequirement::AllowDeath,
+			);
+
+			Self::deposit_event(Event::TransferredTokensViaHrmp(src, dest, amount, res));
+		}
 	}
 }
 
-/// This is a hack to convert from one generic type to another where we are sure that both are the
-/// same type/use the same encoding.
-fn convert_hack<O: Decode>(input: &impl Encode) -> O {
-	input.using_encoded(|e| Decode::decode(&mut &e[..]).expect("Must be compatible; qed"))
-}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate as token_dealer;
 
-impl<T: Trait> DownwardMessageHandler for Module<T> {
-	fn handle_downward_message(msg: &DownwardMessage) {
-		match msg {
-			DownwardMessage::TransferInto(dest, amount, _) => {
-				let dest = convert_hack(&dest);
-				let amount: BalanceOf<T> = convert_hack(amount);
+	use frame_support::{assert_noop, assert_ok, parameter_types};
+	use sp_core::{H160, H256};
+	use sp_io::TestExternalities;
+	use sp_runtime::{
+		testing::Header,
+		traits::{BlakeTwo256, IdentityLookup},
+		DispatchError,
+	};
+	use std::cell::RefCell;
 
-				let _ = T::Currency::deposit_creating(&dest, amount.clone());
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
 
-				Self::deposit_event(Event::<T>::TransferredTokensFromRelayChain(dest, amount));
-			}
-			_ => {}
+	// Configure a mock runtime to test the pallet.
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+			TokenDealer: token_dealer::{Pallet, Call, Event<T>},
 		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+	}
+	impl frame_system::Config for Test {
+		type BaseCallFilter = ();
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Call = Call;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = H160;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = pallet_balances::AccountData<u128>;
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u128 = 1;
+	}
+	impl pallet_balances::Config for Test {
+		type MaxLocks = ();
+		type Balance = u128;
+		type Event = Event;
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type AccountStore = System;
+		type WeightInfo = ();
 	}
-}
 
-impl<T: Trait> XCMPMessageHandler<XCMPMessage<T::AccountId, BalanceOf<T>>> for Module<T> {
-	fn handle_xcmp_message(src: ParaId, msg: &XCMPMessage<T::AccountId, BalanceOf<T>>) {
-		match msg {
-			XCMPMessage::TransferToken(dest, amount) => {
-				let para_account = src.clone().into_account();
+	thread_local! {
+		static UPWARD_MESSAGES: RefCell<Vec<Vec<u8>>> = RefCell::new(Vec::new());
+		static HRMP_MESSAGES: RefCell<Vec<OutboundHrmpMessage>> = RefCell::new(Vec::new());
+		static SENDING_FAILS: RefCell<bool> = RefCell::new(false);
+	}
 
-				let res = T::Currency::transfer(
-					&para_account,
-					dest,
-					amount.clone(),
-					ExistenceRequirement::AllowDeath,
-				);
-
-				Self::deposit_event(Event::<T>::TransferredTokensViaXCMP(
-					src,
-					dest.clone(),
-					amount.clone(),
-					res,
-				));
+	/// Records the sent messages so tests can inspect them, or refuses them all if
+	/// `SENDING_FAILS` is set.
+	pub struct RecordMessages;
+	impl UpwardMessageSender for RecordMessages {
+		fn send_upward_message(msg: Vec<u8>) -> Result<(), ()> {
+			if SENDING_FAILS.with(|f| *f.borrow()) {
+				return Err(());
+			}
+			UPWARD_MESSAGES.with(|m| m.borrow_mut().push(msg));
+			Ok(())
+		}
+	}
+	impl HrmpMessageSender for RecordMessages {
+		fn send_hrmp_message(msg: OutboundHrmpMessage) -> Result<(), ()> {
+			if SENDING_FAILS.with(|f| *f.borrow()) {
+				return Err(());
 			}
+			HRMP_MESSAGES.with(|m| m.borrow_mut().push(msg));
+			Ok(())
 		}
 	}
+
+	impl Config for Test {
+		type Event = Event;
+		type Currency = Balances;
+		type UpwardMessageSender = RecordMessages;
+		type HrmpMessageSender = RecordMessages;
+		type WeightInfo = ();
+	}
+
+	const ALICE: [u8; 20] = [1u8; 20];
+	const BOB: [u8; 20] = [2u8; 20];
+	const OTHER_PARA: u32 = 2000;
+
+	fn new_test_ext(balances: Vec<(H160, u128)>) -> TestExternalities {
+		let mut t = frame_system::GenesisConfig::default()
+			.build_storage::<Test>()
+			.unwrap();
+		pallet_balances::GenesisConfig::<Test> { balances }
+			.assimilate_storage(&mut t)
+			.unwrap();
+		let mut ext = TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	fn last_event() -> Event {
+		System::events().pop().expect("Event expected").event
+	}
+
+	fn sovereign_account(para_id: u32) -> H160 {
+		ParaId::from(para_id).into_account()
+	}
+
+	#[test]
+	fn transfer_to_relay_chain_sends_upward_message() {
+		new_test_ext(vec![(ALICE.into(), 1_000)]).execute_with(|| {
+			let dest = RelayAccountId::new([3u8; 32]);
+			assert_ok!(TokenDealer::transfer_tokens_to_relay_chain(
+				Origin::signed(ALICE.into()),
+				dest.clone(),
+				400
+			));
+
+			assert_eq!(Balances::free_balance(H160::from(ALICE)), 600);
+			assert_eq!(Balances::total_issuance(), 600);
+			let expected = transfer_message(
+				400,
+				Junction::AccountId32 {
+					network: NetworkId::Any,
+					id: [3u8; 32],
+				},
+			);
+			assert_eq!(
+				UPWARD_MESSAGES.with(|m| m.borrow().clone()),
+				vec![expected.encode()]
+			);
+			assert_eq!(
+				last_event(),
+				Event::token_dealer(crate::Event::TransferredTokensToRelayChain(
+					ALICE.into(),
+					dest,
+					400
+				))
+			);
+		});
+	}
+
+	#[test]
+	fn transfer_to_parachain_sends_hrmp_message() {
+		new_test_ext(vec![(ALICE.into(), 1_000)]).execute_with(|| {
+			assert_ok!(TokenDealer::transfer_tokens_to_parachain_chain(
+				Origin::signed(ALICE.into()),
+				OTHER_PARA,
+				BOB,
+				400
+			));
+
+			assert_eq!(Balances::free_balance(H160::from(ALICE)), 600);
+			let sent = HRMP_MESSAGES.with(|m| m.borrow().clone());
+			assert_eq!(sent.len(), 1);
+			assert_eq!(sent[0].recipient, ParaId::from(OTHER_PARA));
+			assert_eq!(parse_transfer_message(&sent[0].data), Some((BOB, 400)));
+			assert_eq!(
+				last_event(),
+				Event::token_dealer(crate::Event::TransferredTokensToParachain(
+					ALICE.into(),
+					OTHER_PARA.into(),
+					BOB,
+					400
+				))
+			);
+		});
+	}
+
+	#[test]
+	fn transfer_without_enough_balance_fails() {
+		new_test_ext(vec![(ALICE.into(), 1_000)]).execute_with(|| {
+			assert_noop!(
+				TokenDealer::transfer_tokens_to_parachain_chain(
+					Origin::signed(ALICE.into()),
+					OTHER_PARA,
+					BOB,
+					1_001
+				),
+				pallet_balances::Error::<Test>::InsufficientBalance
+			);
+			assert!(HRMP_MESSAGES.with(|m| m.borrow().is_empty()));
+		});
+	}
+
+	#[test]
+	fn unsent_message_keeps_the_tokens() {
+		new_test_ext(vec![(ALICE.into(), 1_000)]).execute_with(|| {
+			SENDING_FAILS.with(|f| *f.borrow_mut() = true);
+			assert_noop!(
+				TokenDealer::transfer_tokens_to_relay_chain(
+					Origin::signed(ALICE.into()),
+					RelayAccountId::new([3u8; 32]),
+					400
+				),
+				Error::<Test>::MessageNotSent
+			);
+			assert_noop!(
+				TokenDealer::transfer_tokens_to_parachain_chain(
+					Origin::signed(ALICE.into()),
+					OTHER_PARA,
+					BOB,
+					400
+				),
+				Error::<Test>::MessageNotSent
+			);
+			assert_eq!(Balances::free_balance(H160::from(ALICE)), 1_000);
+		});
+	}
+
+	#[test]
+	fn hrmp_transfer_is_paid_by_the_sovereign_account() {
+		new_test_ext(vec![(sovereign_account(OTHER_PARA), 1_000)]).execute_with(|| {
+			let data = transfer_message(
+				400,
+				Junction::AccountKey20 {
+					network: NetworkId::Any,
+					key: BOB,
+				},
+			)
+			.encode();
+			TokenDealer::handle_hrmp_message(
+				OTHER_PARA.into(),
+				InboundHrmpMessage { sent_at: 1, data },
+			);
+
+			assert_eq!(Balances::free_balance(sovereign_account(OTHER_PARA)), 600);
+			assert_eq!(Balances::free_balance(H160::from(BOB)), 400);
+			assert_eq!(
+				last_event(),
+				Event::token_dealer(crate::Event::TransferredTokensViaHrmp(
+					OTHER_PARA.into(),
+					BOB.into(),
+					400,
+					Ok(())
+				))
+			);
+		});
+	}
+
+	#[test]
+	fn hrmp_transfer_above_the_sovereign_balance_fails() {
+		new_test_ext(vec![(sovereign_account(OTHER_PARA), 1_000)]).execute_with(|| {
+			let data = transfer_message(
+				1_001,
+				Junction::AccountKey20 {
+					network: NetworkId::Any,
+					key: BOB,
+				},
+			)
+			.encode();
+			TokenDealer::handle_hrmp_message(
+				OTHER_PARA.into(),
+				InboundHrmpMessage { sent_at: 1, data },
+			);
+
+			assert_eq!(Balances::free_balance(H160::from(BOB)), 0);
+			assert_eq!(
+				last_event(),
+				Event::token_dealer(crate::Event::TransferredTokensViaHrmp(
+					OTHER_PARA.into(),
+					BOB.into(),
+					1_001,
+					Err(DispatchError::from(
+						pallet_balances::Error::<Test>::InsufficientBalance
+					))
+				))
+			);
+		});
+	}
+
+	#[test]
+	fn unknown_hrmp_message_is_ignored() {
+		new_test_ext(vec![(sovereign_account(OTHER_PARA), 1_000)]).execute_with(|| {
+			// A transfer to a 32 bytes account can't be for this parachain.
+			let data = transfer_message(
+				400,
+				Junction::AccountId32 {
+					network: NetworkId::Any,
+					id: [3u8; 32],
+				},
+			)
+			.encode();
+			TokenDealer::handle_hrmp_message(
+				OTHER_PARA.into(),
+				InboundHrmpMessage { sent_at: 1, data },
+			);
+			TokenDealer::handle_hrmp_message(
+				OTHER_PARA.into(),
+				InboundHrmpMessage {
+					sent_at: 1,
+					data: vec![1, 2, 3],
+				},
+			);
+
+			assert_eq!(Balances::free_balance(sovereign_account(OTHER_PARA)), 1_000);
+			assert!(System::events().is_empty());
+		});
+	}
 }
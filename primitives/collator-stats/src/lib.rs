@@ -0,0 +1,60 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-round block production statistics of collators, and the runtime API exposing them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// These clippy lints are disabled because the macro-generated code triggers them.
+#![allow(clippy::unnecessary_mut_passed)]
+#![allow(clippy::too_many_arguments)]
+
+use parity_scale_codec::{Codec, Decode, Encode};
+use sp_runtime::{Perbill, RuntimeDebug};
+use sp_std::vec::Vec;
+
+/// Block production counters of a single collator during a single round.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct CollatorRoundStats {
+	/// Number of blocks authored by the collator.
+	pub blocks_authored: u32,
+	/// Number of blocks for which the author filter made the collator eligible.
+	pub times_eligible: u32,
+}
+
+impl CollatorRoundStats {
+	/// Share of the blocks the collator was eligible for that it actually authored.
+	pub fn authorship_ratio(&self) -> Perbill {
+		if self.times_eligible == 0 {
+			Perbill::zero()
+		} else {
+			Perbill::from_rational_approximation(self.blocks_authored, self.times_eligible)
+		}
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait CollatorStatsApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// Index of the current staking round.
+		fn current_round() -> u32;
+		/// Statistics of every collator that was eligible or authored during the given round.
+		fn round_stats(round: u32) -> Vec<(AccountId, CollatorRoundStats)>;
+		/// Statistics of a single collator for each of the rounds in `from..=to` still stored.
+		fn collator_stats(collator: AccountId, from: u32, to: u32) -> Vec<(u32, CollatorRoundStats)>;
+	}
+}
+
@@ -34,6 +34,9 @@ pub struct TransactionTrace {
 	#[cfg_attr(feature = "std", serde(serialize_with = "h256_0x_serialize"))]
 	pub block_hash: H256,
 	pub block_number: u32,
+	/// Logs emitted by the traced context, empty if it failed.
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Vec::is_empty"))]
+	pub logs: Vec<super::single::CallLog>,
 	#[cfg_attr(feature = "std", serde(flatten))]
 	pub output: TransactionTraceOutput,
 	pub subtraces: u32,
@@ -77,12 +80,40 @@ pub enum TransactionTraceAction {
 	},
 }
 
+/// Output of a trace, serialized as a "result" field or an "error" field. A revert is reported
+/// like OpenEthereum as a "Reverted" error, with its revert data as "output" and a
+/// "revertReason" field if a reason can be decoded from it.
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(Serialize))]
-#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
 pub enum TransactionTraceOutput {
 	Result(TransactionTraceResult),
-	Error(#[cfg_attr(feature = "std", serde(serialize_with = "string_serialize"))] Vec<u8>),
+	Error(Vec<u8>),
+	Revert(super::RevertOutput),
+}
+
+#[cfg(feature = "std")]
+impl Serialize for TransactionTraceOutput {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		use serde::ser::SerializeMap;
+
+		match self {
+			TransactionTraceOutput::Result(result) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("result", result)?;
+				map.end()
+			}
+			TransactionTraceOutput::Error(error) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("error", &String::from_utf8_lossy(error))?;
+				map.end()
+			}
+			TransactionTraceOutput::Revert(revert) => {
+				revert_output_serialize("Reverted", revert, serializer)
+			}
+		}
+	}
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
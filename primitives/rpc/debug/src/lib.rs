@@ -18,23 +18,54 @@
 
 use codec::{Decode, Encode};
 use ethereum::Transaction;
-use ethereum_types::H160;
+use ethereum_types::{H160, U256};
 use sp_std::vec::Vec;
 
 #[cfg(feature = "std")]
 use serde::Serialize;
 
 sp_api::decl_runtime_apis! {
+	/// Version 2 added `trace_block_transactions` and `trace_call`, and changed the encoding of
+	/// the traces. The traces of version 1 are in the `v1` module, and convert to the current
+	/// ones.
+	#[api_version(2)]
 	pub trait DebugRuntimeApi {
+		#[changed_in(2)]
+		fn trace_transaction(
+			extrinsics: Vec<Block::Extrinsic>,
+			transaction: &Transaction,
+			trace_type: v1::TraceType,
+		) -> Result<v1::TransactionTrace, sp_runtime::DispatchError>;
+
 		fn trace_transaction(
 			extrinsics: Vec<Block::Extrinsic>,
 			transaction: &Transaction,
 			trace_type: single::TraceType,
 		) -> Result<single::TransactionTrace, sp_runtime::DispatchError>;
 
+		#[changed_in(2)]
+		fn trace_block(
+			extrinsics: Vec<Block::Extrinsic>,
+		) -> Result<Vec<v1::BlockTransactionTrace>, sp_runtime::DispatchError>;
+
 		fn trace_block(
 			extrinsics: Vec<Block::Extrinsic>,
 		) -> Result<Vec<block::TransactionTrace>, sp_runtime::DispatchError>;
+
+		fn trace_block_transactions(
+			extrinsics: Vec<Block::Extrinsic>,
+			trace_type: single::TraceType,
+		) -> Result<Vec<single::TransactionTrace>, sp_runtime::DispatchError>;
+
+		fn trace_call(
+			from: H160,
+			to: Option<H160>,
+			data: Vec<u8>,
+			value: U256,
+			gas_limit: U256,
+			state_overrides: Vec<(H160, single::StateOverride)>,
+			trace_type: single::TraceType,
+		) -> Result<single::TransactionTrace, sp_runtime::DispatchError>;
 	}
 }
 
@@ -46,14 +77,39 @@ use crate::serialization::*;
 
 pub mod block;
 pub mod single;
+pub mod v1;
 
+/// Result of a call, serialized as an "output" field or an "error" field. A revert has both, and
+/// a "revertReason" field if a reason can be decoded from its revert data.
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(Serialize))]
-#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
 pub enum CallResult {
-	Output(#[cfg_attr(feature = "std", serde(serialize_with = "bytes_0x_serialize"))] Vec<u8>),
-	// field "error"
-	Error(#[cfg_attr(feature = "std", serde(serialize_with = "string_serialize"))] Vec<u8>),
+	Output(Vec<u8>),
+	Error(Vec<u8>),
+	Revert(RevertOutput),
+}
+
+#[cfg(feature = "std")]
+impl Serialize for CallResult {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		use serde::ser::SerializeMap;
+
+		match self {
+			CallResult::Output(output) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("output", &format!("0x{}", hex::encode(output)))?;
+				map.end()
+			}
+			CallResult::Error(error) => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry("error", &String::from_utf8_lossy(error))?;
+				map.end()
+			}
+			CallResult::Revert(revert) => revert.serialize(serializer),
+		}
+	}
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
@@ -73,6 +129,29 @@ pub enum CreateResult {
 		)]
 		created_contract_code: Vec<u8>,
 	},
+	Revert {
+		#[cfg_attr(feature = "std", serde(flatten))]
+		revert: RevertOutput,
+	},
+}
+
+/// Revert data of a reverted context.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Encode, Decode)]
+pub struct RevertOutput {
+	pub data: Vec<u8>,
+	/// Reason decoded from an `Error(string)` or `Panic(uint256)` revert data, empty otherwise.
+	pub reason: Vec<u8>,
+}
+
+/// Serialized like geth, as an "execution reverted" error with the revert data as output.
+#[cfg(feature = "std")]
+impl Serialize for RevertOutput {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		revert_output_serialize("execution reverted", self, serializer)
+	}
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Encode, Decode)]
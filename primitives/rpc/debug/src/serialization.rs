@@ -16,9 +16,10 @@
 
 //! Provide serialization functions for various types and formats.
 
+use crate::RevertOutput;
 use ethereum_types::{H256, U256};
 use serde::{
-	ser::{Error, SerializeSeq},
+	ser::{Error, SerializeMap, SerializeSeq},
 	Serializer,
 };
 
@@ -41,6 +42,19 @@ where
 	serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
 }
 
+pub fn option_bytes_0x_serialize<S>(
+	bytes: &Option<Vec<u8>>,
+	serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	match bytes {
+		Some(bytes) => bytes_0x_serialize(bytes, serializer),
+		None => serializer.serialize_none(),
+	}
+}
+
 pub fn opcode_serialize<S>(opcode: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
 	S: Serializer,
@@ -61,6 +75,26 @@ where
 	serializer.serialize_str(&d)
 }
 
+/// Reverts are reported as an `error` field, with the revert data as `output` and the reason
+/// decoded from it as `revertReason` if there is one.
+pub fn revert_output_serialize<S>(
+	error: &str,
+	revert: &RevertOutput,
+	serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	let len = if revert.reason.is_empty() { 2 } else { 3 };
+	let mut map = serializer.serialize_map(Some(len))?;
+	map.serialize_entry("error", error)?;
+	map.serialize_entry("output", &format!("0x{}", hex::encode(&revert.data)))?;
+	if !revert.reason.is_empty() {
+		map.serialize_entry("revertReason", &String::from_utf8_lossy(&revert.reason))?;
+	}
+	map.end()
+}
+
 pub fn u256_serialize<S>(data: &U256, serializer: S) -> Result<S::Ok, S::Error>
 where
 	S: Serializer,
@@ -35,9 +35,24 @@ pub enum TraceType {
 		disable_storage: bool,
 		disable_memory: bool,
 		disable_stack: bool,
+		/// Step logs after this amount are not recorded, and the trace is flagged as truncated.
+		max_step_logs: u32,
+		/// Step logs going over this approximate amount of memory are not recorded, and the trace
+		/// is flagged as truncated.
+		max_step_logs_bytes: u32,
+		/// Steps after this amount are not executed: the execution of the transaction stops with
+		/// an error, and the trace is flagged as truncated. Bounds the time of the replay.
+		max_steps: u32,
 	},
 	/// List of calls and subcalls (output Blockscout expects).
 	CallList,
+	/// Nested call frames, matching the geth native `callTracer`. The logs emitted by each frame
+	/// are included with `with_log`.
+	CallTracer { with_log: bool },
+	/// State of the accounts touched by the transaction, matching the geth native
+	/// `prestateTracer`. In diff mode, both the state before and after the transaction are
+	/// returned, restricted to what changed.
+	PrestateTracer { diff_mode: bool },
 }
 
 /// Single transaction trace.
@@ -52,10 +67,17 @@ pub enum TransactionTrace {
 		#[cfg_attr(feature = "std", serde(with = "hex"))]
 		return_value: Vec<u8>,
 		step_logs: Vec<RawStepLog>,
+		/// Whether some step logs were not recorded because of the limits of the trace type.
+		#[cfg_attr(feature = "std", serde(skip_serializing_if = "core::ops::Not::not"))]
+		truncated: bool,
 	},
 	/// Matches the formatter used by Blockscout.
 	/// Is also used to built output of OpenEthereum's `trace_filter`.
 	CallList(Vec<Call>),
+	/// Output of the geth `callTracer`.
+	CallTracer(CallFrame),
+	/// Output of the geth `prestateTracer`.
+	Prestate(PrestateTrace),
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
@@ -65,7 +87,13 @@ pub struct RawStepLog {
 	#[cfg_attr(feature = "std", serde(serialize_with = "u256_serialize"))]
 	pub depth: U256,
 
-	//error: TODO
+	/// Error of the step, empty if it succeeded.
+	#[cfg_attr(
+		feature = "std",
+		serde(serialize_with = "string_serialize", skip_serializing_if = "Vec::is_empty")
+	)]
+	pub error: Vec<u8>,
+
 	#[cfg_attr(feature = "std", serde(serialize_with = "u256_serialize"))]
 	pub gas: U256,
 
@@ -98,6 +126,13 @@ pub struct RawStepLog {
 
 	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Option::is_none"))]
 	pub storage: Option<BTreeMap<H256, H256>>,
+
+	/// Gas refund counter of the transaction before the step.
+	#[cfg_attr(
+		feature = "std",
+		serde(serialize_with = "u256_serialize", skip_serializing_if = "U256::is_zero")
+	)]
+	pub refund: U256,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
@@ -152,4 +187,118 @@ pub struct Call {
 	pub gas_used: U256,
 	#[cfg_attr(feature = "std", serde(flatten))]
 	pub inner: CallInner,
+	/// Logs emitted by this context, empty if it failed.
+	/// Not needed for Blockscout, but included in `crate::block` types.
+	#[cfg_attr(feature = "std", serde(skip))]
+	pub logs: Vec<CallLog>,
+}
+
+/// Log emitted during a call.
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct CallLog {
+	pub address: H160,
+	pub topics: Vec<H256>,
+	#[cfg_attr(feature = "std", serde(serialize_with = "bytes_0x_serialize"))]
+	pub data: Vec<u8>,
+	/// Number of subcalls made by the call before emitting the log.
+	pub position: U256,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "UPPERCASE"))]
+pub enum CallFrameType {
+	Call,
+	CallCode,
+	DelegateCall,
+	StaticCall,
+	Create,
+	SelfDestruct,
+}
+
+/// Call frame of the geth `callTracer`, containing the frames of its subcalls.
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct CallFrame {
+	#[cfg_attr(feature = "std", serde(rename = "type"))]
+	pub call_type: CallFrameType,
+	pub from: H160,
+	/// Called address, or created address if the creation succeeded.
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Option::is_none"))]
+	pub to: Option<H160>,
+	pub value: U256,
+	/// Gas given to the frame.
+	pub gas: U256,
+	pub gas_used: U256,
+	#[cfg_attr(feature = "std", serde(serialize_with = "bytes_0x_serialize"))]
+	pub input: Vec<u8>,
+	/// Returned data, revert data if the frame reverted.
+	#[cfg_attr(feature = "std", serde(serialize_with = "bytes_0x_serialize"))]
+	pub output: Vec<u8>,
+	#[cfg_attr(
+		feature = "std",
+		serde(serialize_with = "string_serialize", skip_serializing_if = "Vec::is_empty")
+	)]
+	pub error: Vec<u8>,
+	/// Reason decoded from the revert data of an `Error(string)` or `Panic(uint256)` revert.
+	#[cfg_attr(
+		feature = "std",
+		serde(serialize_with = "string_serialize", skip_serializing_if = "Vec::is_empty")
+	)]
+	pub revert_reason: Vec<u8>,
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Vec::is_empty"))]
+	pub logs: Vec<CallLog>,
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Vec::is_empty"))]
+	pub calls: Vec<CallFrame>,
+}
+
+/// State of an account in the output of the geth `prestateTracer`. Fields that are not part of
+/// the output are `None`.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct AccountState {
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Option::is_none"))]
+	pub balance: Option<U256>,
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "Option::is_none"))]
+	pub nonce: Option<u64>,
+	#[cfg_attr(
+		feature = "std",
+		serde(
+			serialize_with = "option_bytes_0x_serialize",
+			skip_serializing_if = "Option::is_none"
+		)
+	)]
+	pub code: Option<Vec<u8>>,
+	#[cfg_attr(feature = "std", serde(skip_serializing_if = "BTreeMap::is_empty"))]
+	pub storage: BTreeMap<H256, H256>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[cfg_attr(feature = "std", serde(untagged))]
+pub enum PrestateTrace {
+	/// State before the transaction of all the accounts it touched.
+	Prestate(BTreeMap<H160, AccountState>),
+	/// State before and after the transaction of the accounts it modified, restricted to the
+	/// modified fields and storage entries. Accounts created by the transaction are only in
+	/// `post` and accounts destroyed by it only in `pre`, with their whole state.
+	Diff {
+		pre: BTreeMap<H160, AccountState>,
+		post: BTreeMap<H160, AccountState>,
+	},
+}
+
+/// State of an account replaced before tracing a call. The storage entries of `state` replace
+/// the whole storage of the account, the ones of `state_diff` are set on top of it.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Encode, Decode)]
+pub struct StateOverride {
+	pub balance: Option<U256>,
+	pub nonce: Option<U256>,
+	pub code: Option<Vec<u8>>,
+	pub state: Option<BTreeMap<H256, H256>>,
+	pub state_diff: BTreeMap<H256, H256>,
 }
@@ -0,0 +1,277 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Encoding of the traces of version 1 of the `DebugRuntimeApi`, implemented by the runtimes
+//! that were live before version 2. These runtimes only provide the raw and call list traces of
+//! `trace_transaction` and the block traces of `trace_block`. Their traces are converted to the
+//! current types, the fields they don't provide being left empty.
+
+use codec::{Decode, Encode};
+use ethereum_types::{H160, H256, U256};
+use sp_std::{collections::btree_map::BTreeMap, vec, vec::Vec};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum TraceType {
+	Raw {
+		disable_storage: bool,
+		disable_memory: bool,
+		disable_stack: bool,
+	},
+	CallList,
+}
+
+impl TraceType {
+	/// Trace type of version 1 matching `trace_type`, if version 1 provides it. The limits of the
+	/// raw traces are not enforced by version 1.
+	pub fn from_current(trace_type: crate::single::TraceType) -> Option<Self> {
+		match trace_type {
+			crate::single::TraceType::Raw {
+				disable_storage,
+				disable_memory,
+				disable_stack,
+				..
+			} => Some(TraceType::Raw {
+				disable_storage,
+				disable_memory,
+				disable_stack,
+			}),
+			crate::single::TraceType::CallList => Some(TraceType::CallList),
+			crate::single::TraceType::CallTracer { .. }
+			| crate::single::TraceType::PrestateTracer { .. } => None,
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum TransactionTrace {
+	Raw {
+		gas: U256,
+		return_value: Vec<u8>,
+		step_logs: Vec<RawStepLog>,
+	},
+	CallList(Vec<Call>),
+}
+
+impl From<TransactionTrace> for crate::single::TransactionTrace {
+	fn from(trace: TransactionTrace) -> Self {
+		match trace {
+			TransactionTrace::Raw {
+				gas,
+				return_value,
+				step_logs,
+			} => crate::single::TransactionTrace::Raw {
+				gas,
+				return_value,
+				step_logs: step_logs.into_iter().map(Into::into).collect(),
+				truncated: false,
+			},
+			TransactionTrace::CallList(calls) => crate::single::TransactionTrace::CallList(
+				calls.into_iter().map(Into::into).collect(),
+			),
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub struct RawStepLog {
+	pub depth: U256,
+	pub gas: U256,
+	pub gas_cost: U256,
+	pub memory: Option<Vec<H256>>,
+	pub op: Vec<u8>,
+	pub pc: U256,
+	pub stack: Option<Vec<H256>>,
+	pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+impl From<RawStepLog> for crate::single::RawStepLog {
+	fn from(step_log: RawStepLog) -> Self {
+		crate::single::RawStepLog {
+			depth: step_log.depth,
+			error: vec![],
+			gas: step_log.gas,
+			gas_cost: step_log.gas_cost,
+			memory: step_log.memory,
+			op: step_log.op,
+			pc: step_log.pc,
+			stack: step_log.stack,
+			storage: step_log.storage,
+			refund: U256::zero(),
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum CallResult {
+	Output(Vec<u8>),
+	Error(Vec<u8>),
+}
+
+impl From<CallResult> for crate::CallResult {
+	fn from(res: CallResult) -> Self {
+		match res {
+			CallResult::Output(output) => crate::CallResult::Output(output),
+			CallResult::Error(error) => crate::CallResult::Error(error),
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum CreateResult {
+	Error {
+		error: Vec<u8>,
+	},
+	Success {
+		created_contract_address_hash: H160,
+		created_contract_code: Vec<u8>,
+	},
+}
+
+impl From<CreateResult> for crate::CreateResult {
+	fn from(res: CreateResult) -> Self {
+		match res {
+			CreateResult::Error { error } => crate::CreateResult::Error { error },
+			CreateResult::Success {
+				created_contract_address_hash,
+				created_contract_code,
+			} => crate::CreateResult::Success {
+				created_contract_address_hash,
+				created_contract_code,
+			},
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum CallInner {
+	Call {
+		call_type: crate::CallType,
+		to: H160,
+		input: Vec<u8>,
+		res: CallResult,
+	},
+	Create {
+		init: Vec<u8>,
+		res: CreateResult,
+	},
+	SelfDestruct {
+		balance: U256,
+		refund_address: H160,
+	},
+}
+
+impl From<CallInner> for crate::single::CallInner {
+	fn from(inner: CallInner) -> Self {
+		match inner {
+			CallInner::Call {
+				call_type,
+				to,
+				input,
+				res,
+			} => crate::single::CallInner::Call {
+				call_type,
+				to,
+				input,
+				res: res.into(),
+			},
+			CallInner::Create { init, res } => crate::single::CallInner::Create {
+				init,
+				res: res.into(),
+			},
+			CallInner::SelfDestruct {
+				balance,
+				refund_address,
+			} => crate::single::CallInner::SelfDestruct {
+				balance,
+				refund_address,
+			},
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub struct Call {
+	pub from: H160,
+	pub trace_address: Vec<u32>,
+	pub subtraces: u32,
+	pub value: U256,
+	pub gas: U256,
+	pub gas_used: U256,
+	pub inner: CallInner,
+}
+
+impl From<Call> for crate::single::Call {
+	fn from(call: Call) -> Self {
+		crate::single::Call {
+			from: call.from,
+			trace_address: call.trace_address,
+			subtraces: call.subtraces,
+			value: call.value,
+			gas: call.gas,
+			gas_used: call.gas_used,
+			inner: call.inner.into(),
+			logs: vec![],
+		}
+	}
+}
+
+/// Trace of `trace_block`.
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub struct BlockTransactionTrace {
+	pub action: crate::block::TransactionTraceAction,
+	pub block_hash: H256,
+	pub block_number: u32,
+	pub output: BlockTransactionTraceOutput,
+	pub subtraces: u32,
+	pub trace_address: Vec<u32>,
+	pub transaction_hash: H256,
+	pub transaction_position: u32,
+}
+
+impl From<BlockTransactionTrace> for crate::block::TransactionTrace {
+	fn from(trace: BlockTransactionTrace) -> Self {
+		crate::block::TransactionTrace {
+			action: trace.action,
+			block_hash: trace.block_hash,
+			block_number: trace.block_number,
+			logs: vec![],
+			output: trace.output.into(),
+			subtraces: trace.subtraces,
+			trace_address: trace.trace_address,
+			transaction_hash: trace.transaction_hash,
+			transaction_position: trace.transaction_position,
+		}
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
+pub enum BlockTransactionTraceOutput {
+	Result(crate::block::TransactionTraceResult),
+	Error(Vec<u8>),
+}
+
+impl From<BlockTransactionTraceOutput> for crate::block::TransactionTraceOutput {
+	fn from(output: BlockTransactionTraceOutput) -> Self {
+		match output {
+			BlockTransactionTraceOutput::Result(result) => {
+				crate::block::TransactionTraceOutput::Result(result)
+			}
+			BlockTransactionTraceOutput::Error(error) => {
+				crate::block::TransactionTraceOutput::Error(error)
+			}
+		}
+	}
+}
@@ -16,11 +16,17 @@
 
 use crate::executor::util::opcodes;
 use thales_rpc_primitives_debug::{
-	single::{Call, CallInner, RawStepLog, TraceType},
-	CallResult, CallType, CreateResult,
+	single::{
+		AccountState, Call, CallFrame, CallFrameType, CallInner, CallLog, PrestateTrace,
+		RawStepLog, TraceType,
+	},
+	CallResult, CallType, CreateResult, RevertOutput,
 };
 
+extern crate alloc;
+
 use ethereum_types::{H160, H256, U256};
+use precompile_utils::{collect_logs_and_calls, NestedCall};
 pub use evm::{
 	backend::{Apply, Backend as BackendT, Log},
 	executor::{StackExecutor, StackExitKind, StackState as StackStateT},
@@ -29,23 +35,49 @@ pub use evm::{
 	Handler as HandlerT, Opcode, Runtime, Stack, Transfer,
 };
 use sp_std::{
-	cmp::min, collections::btree_map::BTreeMap, convert::Infallible, rc::Rc, vec, vec::Vec,
+	cell::RefCell, cmp::min, collections::btree_map::BTreeMap, convert::Infallible, mem, rc::Rc,
+	vec, vec::Vec,
 };
 
+/// Precompiles of the traced executor, with the signature expected by `StackExecutor`.
+pub type PrecompileFn = fn(
+	H160,
+	&[u8],
+	Option<u64>,
+	&Context,
+) -> Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>>;
+
 pub struct TraceExecutorWrapper<'config, S> {
 	// Common parts.
 	pub inner: &'config mut StackExecutor<'config, S>,
+	precompile: PrecompileFn,
 	is_tracing: bool,
 	trace_type: TraceType,
 
 	// Raw state.
 	pub step_logs: Vec<RawStepLog>,
+	step_logs_bytes: usize,
+	// Steps executed so far, by all the contexts.
+	steps: u32,
+	pub truncated: bool,
+	// Storage entries accessed during the transaction, by storage owner. Shared by all the
+	// contexts, as a delegate call accesses the storage of its caller.
+	storage_cache: BTreeMap<H160, BTreeMap<H256, H256>>,
+	// Gas refund counter of the transaction at the last step.
+	refund: i64,
 
 	// Blockscout state.
 	pub entries: BTreeMap<u32, Call>,
 	entries_next_index: u32,
 	call_type: Option<CallType>,
 	trace_address: Vec<u32>,
+	// Entry indices of the contexts being executed, and logs they emitted.
+	context_entries: Vec<u32>,
+	logs: BTreeMap<u32, Vec<CallLog>>,
+
+	// Prestate tracer state: state of the touched accounts before being modified. Filled by
+	// handler methods that only borrow the wrapper, hence the `RefCell`.
+	prestate: RefCell<BTreeMap<H160, AccountState>>,
 }
 
 enum ContextType {
@@ -56,18 +88,28 @@ enum ContextType {
 impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 	pub fn new(
 		inner: &'config mut StackExecutor<'config, S>,
+		precompile: PrecompileFn,
 		is_tracing: bool,
 		trace_type: TraceType,
 	) -> TraceExecutorWrapper<'config, S> {
 		TraceExecutorWrapper {
 			inner,
+			precompile,
 			is_tracing,
 			trace_type,
 			step_logs: vec![],
+			step_logs_bytes: 0,
+			steps: 0,
+			truncated: false,
+			storage_cache: BTreeMap::new(),
+			refund: 0,
 			entries: BTreeMap::new(),
 			entries_next_index: 0,
 			call_type: None,
 			trace_address: vec![],
+			context_entries: vec![],
+			logs: BTreeMap::new(),
+			prestate: RefCell::new(BTreeMap::new()),
 		}
 	}
 
@@ -82,8 +124,20 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 				disable_storage,
 				disable_memory,
 				disable_stack,
-			} => self.trace_raw(runtime, disable_storage, disable_memory, disable_stack),
-			TraceType::CallList => self.trace_call_list(runtime, context_type, code),
+				max_steps,
+				..
+			} => self.trace_raw(
+				runtime,
+				disable_storage,
+				disable_memory,
+				disable_stack,
+				max_steps,
+			),
+			TraceType::CallList
+			| TraceType::CallTracer { .. }
+			| TraceType::PrestateTracer { .. } => {
+				self.trace_call_list(runtime, context_type, code)
+			}
 		}
 	}
 
@@ -93,41 +147,45 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		disable_storage: bool,
 		disable_memory: bool,
 		disable_stack: bool,
+		max_steps: u32,
 	) -> ExitReason {
-		// TODO : If subcalls on a same contract access more storage, does it cache it here too ?
-		// (not done yet)
-		let mut storage_cache: BTreeMap<H256, H256> = BTreeMap::new();
+		// Owner of the storage accessed by this context, which is the caller for delegate calls.
 		let address = runtime.context().address;
+		// The gasometer of this context only counts its own refunds, and the ones of the
+		// subcontexts it committed.
+		let parent_refund = self.refund;
 
 		loop {
-			let mut storage_complete_scan = false;
-			let mut storage_key_scan: Option<H256> = None;
+			// Once the step budget is spent, the execution stops, and so do the contexts calling
+			// this one at their next step.
+			if self.steps >= max_steps {
+				self.truncated = true;
+				break ExitReason::Error(ExitError::Other("Trace step budget exhausted".into()));
+			}
+			self.steps += 1;
+
+			let mut storage_access = None;
 			let mut steplog = None;
 
-			if let Some((opcode, stack)) = runtime.machine().inspect() {
-				// Will opcode modify storage
-				if matches!(
-					opcode,
-					Opcode(0x54) | // sload
-					Opcode(0x55) // sstore
-				) {
-					if let Ok(key) = stack.peek(0) {
-						storage_key_scan = Some(key);
-					}
-				}
+			// Once truncated, the execution continues without recording anything.
+			let inspected = if self.truncated {
+				None
+			} else {
+				runtime.machine().inspect()
+			};
 
-				// Any call might modify the storage values outside if this instance of the loop,
-				// rendering the cache obsolete. In this case we'll refresh the cache after the next
-				// step.
-				storage_complete_scan = matches!(
-					opcode,
-					Opcode(240) | // create
-					Opcode(241) | // call
-					Opcode(242) | // call code
-					Opcode(244) | // delegate call
-					Opcode(245) | // create 2
-					Opcode(250) // static call
-				);
+			if let Some((opcode, stack)) = inspected {
+				// Storage entry accessed by the opcode, and the value stored by SSTORE.
+				storage_access = match opcode {
+					// sload
+					Opcode(0x54) => stack.peek(0).ok().map(|key| (key, None)),
+					// sstore
+					Opcode(0x55) => match (stack.peek(0), stack.peek(1)) {
+						(Ok(key), Ok(value)) => Some((key, Some(value))),
+						_ => None,
+					},
+					_ => None,
+				};
 
 				let gas = self.inner.state().metadata().gasometer().gas();
 
@@ -161,11 +219,15 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 					Err(reason) => break reason.clone(),
 				};
 
+				self.refund =
+					parent_refund + self.inner.state().metadata().gasometer().refunded_gas();
+
 				steplog = Some(RawStepLog {
 					// EVM's returned depth is depth output format - 1.
 					depth: U256::from(
 						self.inner.state().metadata().depth().unwrap_or_default() + 1,
 					),
+					error: vec![],
 					gas: U256::from(self.inner.gas()),
 					gas_cost: U256::from(gas_cost),
 					memory: if disable_memory {
@@ -205,30 +267,37 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 					} else {
 						Some(BTreeMap::new())
 					},
+					refund: U256::from(self.refund.max(0) as u64),
 				});
 			}
 
 			let step_result = runtime.step(self);
 
-			// Update cache if needed.
-			if let Some(key) = storage_key_scan {
-				let _ = storage_cache.insert(key, self.storage(address, key));
-			}
-
-			if storage_complete_scan {
-				for (key, value) in storage_cache.iter_mut() {
-					*value = self.storage(address, *key);
-				}
+			// Update the accessed entry once the opcode succeeded.
+			if let (Some((key, stored)), Ok(_)) = (storage_access, &step_result) {
+				let value = stored.unwrap_or_else(|| self.inner.storage(address, key));
+				self.storage_cache
+					.entry(address)
+					.or_default()
+					.insert(key, value);
 			}
 
 			// Push log into vec here instead here (for SLOAD/STORE "early" update).
 			if let Some(mut steplog) = steplog {
+				if let Err(Capture::Exit(ExitReason::Error(error))) = &step_result {
+					steplog.error = Self::error_message(error);
+				}
 				steplog.storage = if disable_storage {
 					None
 				} else {
-					Some(storage_cache.clone())
+					Some(
+						self.storage_cache
+							.get(&address)
+							.cloned()
+							.unwrap_or_default(),
+					)
 				};
-				self.step_logs.push(steplog);
+				self.push_step_log(steplog);
 			}
 
 			// Do we continue ?
@@ -244,6 +313,34 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		}
 	}
 
+	/// Record a step log, unless it goes over the limits of the trace type.
+	fn push_step_log(&mut self, step_log: RawStepLog) {
+		let (max_step_logs, max_step_logs_bytes) = match self.trace_type {
+			TraceType::Raw {
+				max_step_logs,
+				max_step_logs_bytes,
+				..
+			} => (max_step_logs as usize, max_step_logs_bytes as usize),
+			_ => return,
+		};
+
+		// Approximate memory used by the step log.
+		let bytes = mem::size_of::<RawStepLog>()
+			+ step_log.op.len()
+			+ step_log.memory.as_ref().map_or(0, |memory| memory.len() * 32)
+			+ step_log.stack.as_ref().map_or(0, |stack| stack.len() * 32)
+			+ step_log.storage.as_ref().map_or(0, |storage| storage.len() * 64);
+
+		if self.step_logs.len() >= max_step_logs
+			|| self.step_logs_bytes.saturating_add(bytes) > max_step_logs_bytes
+		{
+			self.truncated = true;
+		} else {
+			self.step_logs_bytes += bytes;
+			self.step_logs.push(step_log);
+		}
+	}
+
 	fn trace_call_list(
 		&mut self,
 		runtime: &mut Runtime,
@@ -261,6 +358,7 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 
 		let entries_index = self.entries_next_index;
 		self.entries_next_index += 1;
+		self.context_entries.push(entries_index);
 
 		// Fetch all data we currently can for the entry.
 		let call_type = self.call_type;
@@ -269,8 +367,6 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		let value = runtime.context().apparent_value;
 
 		let gas_at_start = self.inner.gas();
-		let mut return_stack_offset = None;
-		let mut return_stack_len = None;
 		let mut suicide_info = None;
 
 		// Execute the call/create.
@@ -288,14 +384,6 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 
 				subcall = self.call_type.is_some();
 
-				// RETURN
-				if opcode == Opcode(0xf3) {
-					let stack = runtime.machine().stack().data();
-
-					return_stack_offset = stack.get(stack.len() - 1).cloned();
-					return_stack_len = stack.get(stack.len() - 2).cloned();
-				}
-
 				// SELFDESTRUCT
 				if opcode == Opcode(0xff) {
 					let stack = runtime.machine().stack().data();
@@ -347,6 +435,7 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 						refund_address,
 						balance,
 					},
+					logs: vec![],
 				},
 			);
 		}
@@ -354,18 +443,38 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		// We pop the children item, giving back this context trace_address.
 		let subtraces = self.trace_address.pop().unwrap();
 
+		// Like geth, the logs of a failed context and of its subcontexts are discarded, as they
+		// are reverted.
+		self.context_entries.pop();
+		let logs = self.logs.remove(&entries_index).unwrap_or_default();
+		let logs = if let ExitReason::Succeed(_) = exit_reason {
+			logs
+		} else {
+			for (_, entry) in self.entries.range_mut(entries_index..) {
+				entry.logs.clear();
+			}
+			vec![]
+		};
+
+		// Returned data, or revert data.
+		let output = runtime.machine().return_value();
+		let revert = || RevertOutput {
+			reason: Self::revert_reason(&output),
+			data: output.clone(),
+		};
+
 		self.entries.insert(
 			entries_index,
 			match context_type {
 				ContextType::Call => {
 					let res = match &exit_reason {
 						ExitReason::Succeed(ExitSucceed::Returned) => {
-							CallResult::Output(runtime.machine().return_value())
+							CallResult::Output(output.clone())
 						}
 						ExitReason::Succeed(_) => CallResult::Output(vec![]),
 						ExitReason::Error(error) => CallResult::Error(Self::error_message(error)),
 
-						ExitReason::Revert(_) => CallResult::Error(b"execution reverted".to_vec()),
+						ExitReason::Revert(_) => CallResult::Revert(revert()),
 						ExitReason::Fatal(_) => CallResult::Error(vec![]),
 					};
 
@@ -382,39 +491,21 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 							input: data,
 							res,
 						},
+						logs,
 					}
 				}
 				ContextType::Create => {
-					// let offset = runtine.machine().stack().data();
-					let contract_code = if let (Some(offset), Some(len)) =
-						(return_stack_offset, return_stack_len)
-					{
-						let offset = offset.to_low_u64_be() as usize;
-						let len = len.to_low_u64_be() as usize;
-
-						let memory = runtime.machine().memory().data();
-
-						if memory.len() >= offset + len {
-							memory[offset..offset + len].to_vec()
-						} else {
-							vec![] // TODO : Should not be possible
-						}
-					} else {
-						vec![] // TODO : Should not be possible
-					};
-
 					let res = match &exit_reason {
+						// The returned data is the code of the created contract.
 						ExitReason::Succeed(_) => CreateResult::Success {
 							created_contract_address_hash: to,
-							created_contract_code: contract_code,
+							created_contract_code: output.clone(),
 						},
 						ExitReason::Error(error) => CreateResult::Error {
 							error: Self::error_message(error),
 						},
 
-						ExitReason::Revert(_) => CreateResult::Error {
-							error: b"execution reverted".to_vec(),
-						},
+						ExitReason::Revert(_) => CreateResult::Revert { revert: revert() },
 						ExitReason::Fatal(_) => CreateResult::Error { error: vec![] },
 					};
 
@@ -426,6 +517,7 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 						gas_used: U256::from(gas_used),
 						from,
 						inner: CallInner::Create { init: data, res },
+						logs,
 					}
 				}
 			},
@@ -434,6 +526,288 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		exit_reason
 	}
 
+	/// Record the state of `address` before the traced transaction modifies it, for the prestate
+	/// tracer. Must be called before any access to the account.
+	fn record_account(&self, address: H160) {
+		if let TraceType::PrestateTracer { .. } = self.trace_type {
+			let mut prestate = self.prestate.borrow_mut();
+			if !prestate.contains_key(&address) {
+				let basic = self.inner.state().basic(address);
+				prestate.insert(
+					address,
+					AccountState {
+						balance: Some(basic.balance),
+						nonce: Some(basic.nonce.low_u64()),
+						code: Some(self.inner.code(address)),
+						storage: BTreeMap::new(),
+					},
+				);
+			}
+		}
+	}
+
+	/// Record the value of a storage entry before the traced transaction modifies it, for the
+	/// prestate tracer. Must be called before any access to the entry.
+	fn record_storage(&self, address: H160, index: H256) {
+		if let TraceType::PrestateTracer { .. } = self.trace_type {
+			self.record_account(address);
+			let value = self.inner.storage(address, index);
+			if let Some(account) = self.prestate.borrow_mut().get_mut(&address) {
+				account.storage.entry(index).or_insert(value);
+			}
+		}
+	}
+
+	/// Output of the prestate tracer, once the transaction is executed.
+	pub fn prestate(&self, diff_mode: bool) -> PrestateTrace {
+		let prestate = self.prestate.borrow().clone();
+
+		if !diff_mode {
+			return PrestateTrace::Prestate(
+				prestate
+					.into_iter()
+					.map(|(address, mut account)| {
+						// Like geth, omit zero nonces and empty codes.
+						if account.nonce == Some(0) {
+							account.nonce = None;
+						}
+						if account.code.as_ref().map_or(false, |code| code.is_empty()) {
+							account.code = None;
+						}
+						(address, account)
+					})
+					.collect(),
+			);
+		}
+
+		// Like geth, accounts created by the transaction are only in `post`, with their whole
+		// state, and accounts destroyed by it only in `pre`.
+		let is_empty = |balance: Option<U256>, nonce: Option<u64>, code: Option<&Vec<u8>>| {
+			balance.unwrap_or_default().is_zero()
+				&& nonce.unwrap_or_default() == 0
+				&& code.map_or(true, |code| code.is_empty())
+		};
+
+		let mut pre = BTreeMap::new();
+		let mut post = BTreeMap::new();
+		for (address, before) in prestate {
+			let basic = self.inner.state().basic(address);
+			let code = self.inner.code(address);
+
+			let existed = !is_empty(before.balance, before.nonce, before.code.as_ref());
+			let exists = !is_empty(Some(basic.balance), Some(basic.nonce.low_u64()), Some(&code));
+			if existed && !exists {
+				pre.insert(address, before);
+				continue;
+			}
+			if !existed {
+				if exists {
+					let storage = before
+						.storage
+						.keys()
+						.map(|index| (*index, self.inner.storage(address, *index)))
+						.filter(|(_, value)| !value.is_zero())
+						.collect();
+					let account_post = AccountState {
+						balance: Some(basic.balance),
+						nonce: Some(basic.nonce.low_u64()),
+						code: Some(code),
+						storage,
+					};
+					post.insert(address, account_post);
+				}
+				continue;
+			}
+
+			let mut account_pre = AccountState::default();
+			let mut account_post = AccountState::default();
+			if before.balance != Some(basic.balance) {
+				account_pre.balance = before.balance;
+				account_post.balance = Some(basic.balance);
+			}
+			if before.nonce != Some(basic.nonce.low_u64()) {
+				account_pre.nonce = before.nonce;
+				account_post.nonce = Some(basic.nonce.low_u64());
+			}
+			if before.code.as_ref() != Some(&code) {
+				account_pre.code = before.code;
+				account_post.code = Some(code);
+			}
+			for (index, value) in before.storage {
+				let new_value = self.inner.storage(address, index);
+				if new_value != value {
+					account_pre.storage.insert(index, value);
+					account_post.storage.insert(index, new_value);
+				}
+			}
+
+			if account_pre != AccountState::default() {
+				pre.insert(address, account_pre);
+				post.insert(address, account_post);
+			}
+		}
+
+		PrestateTrace::Diff { pre, post }
+	}
+
+	/// Output of the call tracer, nesting the call list entries into frames.
+	pub fn call_frame(&mut self) -> Option<CallFrame> {
+		let with_log = matches!(self.trace_type, TraceType::CallTracer { with_log: true });
+
+		// Entries are ordered by the start of their context, so a frame is complete once an entry
+		// that is not one of its descendants is reached.
+		let mut stack: Vec<CallFrame> = vec![];
+		for (_, entry) in mem::take(&mut self.entries) {
+			let depth = entry.trace_address.len();
+			while stack.len() > depth.max(1) {
+				Self::close_frame(&mut stack);
+			}
+			stack.push(Self::to_call_frame(entry, with_log));
+		}
+		while stack.len() > 1 {
+			Self::close_frame(&mut stack);
+		}
+
+		stack.pop()
+	}
+
+	fn close_frame(stack: &mut Vec<CallFrame>) {
+		if let Some(frame) = stack.pop() {
+			if let Some(parent) = stack.last_mut() {
+				parent.calls.push(frame);
+			}
+		}
+	}
+
+	fn to_call_frame(entry: Call, with_log: bool) -> CallFrame {
+		let mut frame = CallFrame {
+			call_type: CallFrameType::Call,
+			from: entry.from,
+			to: None,
+			value: entry.value,
+			// `gas` of the entry is the gas left at the end of the context.
+			gas: entry.gas.saturating_add(entry.gas_used),
+			gas_used: entry.gas_used,
+			input: vec![],
+			output: vec![],
+			error: vec![],
+			revert_reason: vec![],
+			logs: if with_log { entry.logs } else { vec![] },
+			calls: vec![],
+		};
+
+		match entry.inner {
+			CallInner::Call {
+				call_type,
+				to,
+				input,
+				res,
+			} => {
+				frame.call_type = match call_type {
+					CallType::Call => CallFrameType::Call,
+					CallType::CallCode => CallFrameType::CallCode,
+					CallType::DelegateCall => CallFrameType::DelegateCall,
+					CallType::StaticCall => CallFrameType::StaticCall,
+				};
+				frame.to = Some(to);
+				frame.input = input;
+				match res {
+					CallResult::Output(output) => frame.output = output,
+					CallResult::Error(error) => frame.error = error,
+					CallResult::Revert(revert) => Self::revert_frame(&mut frame, revert),
+				}
+			}
+			CallInner::Create { init, res } => {
+				frame.call_type = CallFrameType::Create;
+				frame.input = init;
+				match res {
+					CreateResult::Success {
+						created_contract_address_hash,
+						created_contract_code,
+					} => {
+						frame.to = Some(created_contract_address_hash);
+						frame.output = created_contract_code;
+					}
+					CreateResult::Error { error } => frame.error = error,
+					CreateResult::Revert { revert } => Self::revert_frame(&mut frame, revert),
+				}
+			}
+			CallInner::SelfDestruct {
+				balance,
+				refund_address,
+			} => {
+				frame.call_type = CallFrameType::SelfDestruct;
+				frame.to = Some(refund_address);
+				frame.value = balance;
+				frame.gas = U256::zero();
+				frame.gas_used = U256::zero();
+			}
+		}
+
+		frame
+	}
+
+	fn revert_frame(frame: &mut CallFrame, revert: RevertOutput) {
+		frame.error = b"execution reverted".to_vec();
+		frame.output = revert.data;
+		frame.revert_reason = revert.reason;
+	}
+
+	/// Reason of a revert: the message of an `Error(string)` revert data, or the description of
+	/// the code of a `Panic(uint256)` one like geth. Empty if the data is neither of them.
+	fn revert_reason(output: &[u8]) -> Vec<u8> {
+		const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+		const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+		// Selector, offset and length of the message.
+		const HEADER_LEN: usize = 4 + 32 + 32;
+
+		if output.len() == 4 + 32 && output[0..4] == PANIC_SELECTOR {
+			return Self::panic_reason(U256::from_big_endian(&output[4..]));
+		}
+
+		if output.len() < HEADER_LEN || output[0..4] != ERROR_STRING_SELECTOR {
+			return vec![];
+		}
+
+		let len = U256::from_big_endian(&output[36..HEADER_LEN]);
+		if len > U256::from(output.len() - HEADER_LEN) {
+			return vec![];
+		}
+
+		let message = &output[HEADER_LEN..HEADER_LEN + len.low_u64() as usize];
+		match core::str::from_utf8(message) {
+			Ok(_) => message.to_vec(),
+			Err(_) => vec![],
+		}
+	}
+
+	/// Description of a Solidity panic code.
+	fn panic_reason(code: U256) -> Vec<u8> {
+		let code_u64 = if code > U256::from(u64::MAX) {
+			u64::MAX
+		} else {
+			code.low_u64()
+		};
+		let reason = match code_u64 {
+			0x00 => Some("generic panic"),
+			0x01 => Some("assert(false)"),
+			0x11 => Some("arithmetic underflow or overflow"),
+			0x12 => Some("division or modulo by zero"),
+			0x21 => Some("enum overflow"),
+			0x22 => Some("invalid encoded storage byte array accessed"),
+			0x31 => Some("out-of-bounds array access; popping on an empty array"),
+			0x32 => Some("out-of-bounds access of an array or bytesN"),
+			0x41 => Some("out of memory"),
+			0x51 => Some("uninitialized function"),
+			_ => None,
+		};
+
+		match reason {
+			Some(reason) => reason.as_bytes().to_vec(),
+			None => alloc::format!("unknown panic code: {:#x}", code).into_bytes(),
+		}
+	}
+
 	fn error_message(error: &ExitError) -> Vec<u8> {
 		match error {
 			ExitError::StackUnderflow => "stack underflow",
@@ -512,6 +886,12 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 			}
 		}
 
+		self.record_account(address);
+		if let Some(transfer) = transfer.as_ref() {
+			self.record_account(transfer.source);
+			self.record_account(transfer.target);
+		}
+
 		let code = self.inner.code(address);
 		self.inner.enter_substate(gas_limit, is_static);
 		self.inner.state_mut().touch(context.address);
@@ -533,6 +913,10 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 			}
 		}
 
+		if let Some(exit) = self.trace_precompile(address, &data, gas_limit, &context) {
+			return exit;
+		}
+
 		let mut runtime = Runtime::new(
 			Rc::new(code),
 			Rc::new(data.clone()),
@@ -562,6 +946,176 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 		}
 	}
 
+	/// Execute the call to `address` if it is a precompile, in the substate entered for it. The
+	/// logs deposited by the precompile are collected like the runtime runner does, and the call
+	/// is recorded in the call list with them, followed by the calls the precompile executed.
+	fn trace_precompile(
+		&mut self,
+		address: H160,
+		data: &[u8],
+		gas_limit: u64,
+		context: &Context,
+	) -> Option<Capture<(ExitReason, Vec<u8>), Infallible>> {
+		let precompile = self.precompile;
+		let (result, logs, calls) =
+			collect_logs_and_calls(|| precompile(address, data, Some(gas_limit), context));
+		let result = result?;
+
+		let gas_at_start = self.inner.gas();
+		let (exit_reason, output) = match result {
+			Ok((exit_succeed, output, cost)) => match self
+				.inner
+				.state_mut()
+				.metadata_mut()
+				.gasometer_mut()
+				.record_cost(cost)
+			{
+				Ok(()) => (ExitReason::Succeed(exit_succeed), output),
+				Err(e) => (ExitReason::Error(e), Vec::new()),
+			},
+			Err(e) => (ExitReason::Error(e), Vec::new()),
+		};
+		// Like in the executor, a failed precompile consumes all the gas of its call.
+		let succeeded = matches!(exit_reason, ExitReason::Succeed(_));
+		if !succeeded {
+			self.inner.state_mut().metadata_mut().gasometer_mut().fail();
+		}
+		let gas_at_end = self.inner.gas();
+
+		if succeeded {
+			for log in &logs {
+				let _ = self.inner.log(log.address, log.topics.clone(), log.data.clone());
+			}
+		}
+
+		if !matches!(self.trace_type, TraceType::Raw { .. }) {
+			let entries_index = self.entries_next_index;
+			self.entries_next_index += 1;
+
+			let res = match &exit_reason {
+				ExitReason::Succeed(_) => CallResult::Output(output.clone()),
+				ExitReason::Error(error) => CallResult::Error(Self::error_message(error)),
+				_ => CallResult::Error(vec![]),
+			};
+			self.entries.insert(
+				entries_index,
+				Call {
+					from: context.caller,
+					trace_address: self.trace_address.clone(),
+					subtraces: calls.len() as u32,
+					value: context.apparent_value,
+					gas: U256::from(gas_at_end),
+					gas_used: U256::from(gas_at_start - gas_at_end),
+					inner: CallInner::Call {
+						call_type: CallType::Call,
+						to: address,
+						input: data.to_vec(),
+						res,
+					},
+					logs: if succeeded {
+						Self::own_logs(logs, &calls)
+					} else {
+						vec![]
+					},
+				},
+			);
+			let trace_address = self.trace_address.clone();
+			self.trace_nested_calls(calls, trace_address, succeeded);
+		}
+
+		Some(match exit_reason {
+			ExitReason::Succeed(s) => {
+				let _ = self.inner.exit_substate(StackExitKind::Succeeded);
+				Capture::Exit((ExitReason::Succeed(s), output))
+			}
+			exit_reason => {
+				let _ = self.inner.exit_substate(StackExitKind::Failed);
+				Capture::Exit((exit_reason, Vec::new()))
+			}
+		})
+	}
+
+	/// Add the entries of the calls executed by a precompile, such as the subcalls of a batch,
+	/// below the entry of the precompile call at `trace_address`. Their logs are kept if the
+	/// precompile call succeeded. The calls made by contracts during these calls are not traced.
+	fn trace_nested_calls(
+		&mut self,
+		calls: Vec<NestedCall>,
+		trace_address: Vec<u32>,
+		keep_logs: bool,
+	) {
+		for (index, call) in calls.into_iter().enumerate() {
+			let entries_index = self.entries_next_index;
+			self.entries_next_index += 1;
+
+			let mut call_trace_address = trace_address.clone();
+			call_trace_address.push(index as u32);
+
+			let res = match &call.exit_reason {
+				ExitReason::Succeed(_) => CallResult::Output(call.output),
+				ExitReason::Error(error) => CallResult::Error(Self::error_message(error)),
+				ExitReason::Revert(_) => CallResult::Revert(RevertOutput {
+					reason: Self::revert_reason(&call.output),
+					data: call.output,
+				}),
+				ExitReason::Fatal(_) => CallResult::Error(vec![]),
+			};
+			let keep_logs = keep_logs && matches!(call.exit_reason, ExitReason::Succeed(_));
+
+			self.entries.insert(
+				entries_index,
+				Call {
+					from: call.source,
+					trace_address: call_trace_address.clone(),
+					subtraces: call.calls.len() as u32,
+					value: call.value,
+					gas: U256::from(call.gas_limit.saturating_sub(call.used_gas)),
+					gas_used: U256::from(call.used_gas),
+					inner: CallInner::Call {
+						call_type: CallType::Call,
+						to: call.target,
+						input: call.input,
+						res,
+					},
+					logs: if keep_logs {
+						Self::own_logs(call.logs, &call.calls)
+					} else {
+						vec![]
+					},
+				},
+			);
+			self.trace_nested_calls(call.calls, call_trace_address, keep_logs);
+		}
+	}
+
+	/// Logs emitted by a call itself, out of `logs` which also contains the logs of its nested
+	/// `calls`. Their position is the number of nested calls made before them.
+	fn own_logs(logs: Vec<Log>, calls: &[NestedCall]) -> Vec<CallLog> {
+		let mut own_logs = vec![];
+		// Number of nested calls started, and end of the logs of the last one.
+		let mut position = 0;
+		let mut nested_logs_end = 0;
+		for (index, log) in logs.into_iter().enumerate() {
+			while let Some(call) = calls.get(position) {
+				if call.log_index > index {
+					break;
+				}
+				nested_logs_end = call.log_index + call.logs.len();
+				position += 1;
+			}
+			if index < nested_logs_end {
+				continue;
+			}
+			own_logs.push(CallLog {
+				address: log.address,
+				topics: log.topics,
+				data: log.data,
+				position: U256::from(position),
+			});
+		}
+		own_logs
+	}
+
 	pub fn trace_create(
 		&mut self,
 		caller: H160,
@@ -596,6 +1150,8 @@ impl<'config, S: StackStateT<'config>> TraceExecutorWrapper<'config, S> {
 			.gasometer_mut()
 			.record_cost(gas_limit));
 		let address = self.inner.create_address(scheme);
+		self.record_account(caller);
+		self.record_account(address);
 		self.inner.state_mut().inc_nonce(caller);
 		self.inner.enter_substate(gas_limit, false);
 
@@ -679,22 +1235,27 @@ impl<'config, S: StackStateT<'config>> HandlerT for TraceExecutorWrapper<'config
 	type CallFeedback = Infallible;
 
 	fn balance(&self, address: H160) -> U256 {
+		self.record_account(address);
 		self.inner.balance(address)
 	}
 
 	fn code_size(&self, address: H160) -> U256 {
+		self.record_account(address);
 		self.inner.code_size(address)
 	}
 
 	fn code_hash(&self, address: H160) -> H256 {
+		self.record_account(address);
 		self.inner.code_hash(address)
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
+		self.record_account(address);
 		self.inner.code(address)
 	}
 
 	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.record_storage(address, index);
 		self.inner.storage(address, index)
 	}
 
@@ -703,6 +1264,7 @@ impl<'config, S: StackStateT<'config>> HandlerT for TraceExecutorWrapper<'config
 	}
 
 	fn exists(&self, address: H160) -> bool {
+		self.record_account(address);
 		self.inner.exists(address)
 	}
 
@@ -743,14 +1305,28 @@ impl<'config, S: StackStateT<'config>> HandlerT for TraceExecutorWrapper<'config
 	}
 
 	fn set_storage(&mut self, address: H160, index: H256, value: H256) -> Result<(), ExitError> {
+		self.record_storage(address, index);
 		self.inner.set_storage(address, index, value)
 	}
 
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		// Logs are recorded in the context being traced by `trace_call_list`, if any, with the
+		// number of subcalls it made so far.
+		if let Some(&entries_index) = self.context_entries.last() {
+			let position = self.trace_address.last().cloned().unwrap_or_default();
+			self.logs.entry(entries_index).or_default().push(CallLog {
+				address,
+				topics: topics.clone(),
+				data: data.clone(),
+				position: U256::from(position),
+			});
+		}
 		self.inner.log(address, topics, data)
 	}
 
 	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
+		self.record_account(address);
+		self.record_account(target);
 		self.inner.mark_delete(address, target)
 	}
 
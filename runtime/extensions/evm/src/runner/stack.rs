@@ -83,7 +83,8 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 			&mut TraceExecutorWrapper<'config, SubstrateStackState<'_, 'config, T>>,
 		) -> Capture<(ExitReason, Vec<u8>), Infallible>,
 	{
-		let mut wrapper = TraceExecutorWrapper::new(executor, true, trace_type);
+		let mut wrapper =
+			TraceExecutorWrapper::new(executor, T::Precompiles::execute, true, trace_type);
 
 		let execution_result = match f(&mut wrapper) {
 			Capture::Exit((_reason, result)) => result,
@@ -95,6 +96,7 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 				gas: U256::from(wrapper.inner.state().metadata().gasometer().gas()),
 				return_value: execution_result,
 				step_logs: wrapper.step_logs,
+				truncated: wrapper.truncated,
 			}),
 			TraceType::CallList => Ok(TransactionTrace::CallList(
 				wrapper
@@ -103,6 +105,13 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 					.map(|(_, value)| value)
 					.collect(),
 			)),
+			TraceType::CallTracer { .. } => wrapper
+				.call_frame()
+				.map(TransactionTrace::CallTracer)
+				.ok_or_else(|| ExitError::Other("Missing call frame".into())),
+			TraceType::PrestateTracer { diff_mode } => {
+				Ok(TransactionTrace::Prestate(wrapper.prestate(diff_mode)))
+			}
 		}
 	}
 
@@ -116,7 +125,8 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 			&mut TraceExecutorWrapper<'config, SubstrateStackState<'_, 'config, T>>,
 		) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Infallible>,
 	{
-		let mut wrapper = TraceExecutorWrapper::new(executor, true, trace_type);
+		let mut wrapper =
+			TraceExecutorWrapper::new(executor, T::Precompiles::execute, true, trace_type);
 
 		let execution_result = match f(&mut wrapper) {
 			Capture::Exit((_reason, _address, result)) => result,
@@ -128,6 +138,7 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 				gas: U256::from(wrapper.inner.state().metadata().gasometer().gas()),
 				return_value: execution_result,
 				step_logs: wrapper.step_logs,
+				truncated: wrapper.truncated,
 			}),
 			TraceType::CallList => Ok(TransactionTrace::CallList(
 				wrapper
@@ -136,6 +147,13 @@ impl<T: Config> TraceRunner<T> for Runner<T> {
 					.map(|(_, value)| value)
 					.collect(),
 			)),
+			TraceType::CallTracer { .. } => wrapper
+				.call_frame()
+				.map(TransactionTrace::CallTracer)
+				.ok_or_else(|| ExitError::Other("Missing call frame".into())),
+			TraceType::PrestateTracer { diff_mode } => {
+				Ok(TransactionTrace::Prestate(wrapper.prestate(diff_mode)))
+			}
 		}
 	}
 
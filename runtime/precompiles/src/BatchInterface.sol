@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-only
+pragma solidity >=0.8.0;
+
+/// The interface through which solidity contracts will batch calls, implemented by the precompile
+/// at address 0x0000000000000000000000000000000000000803 (2051)
+/// The subcalls are sent by the caller of the precompile, not by the precompile itself. Each one
+/// has the value, call data and gas limit at the same index of the arrays. Missing values and
+/// call data default to 0 and empty, and a missing or zero gas limit forwards all remaining gas.
+/// The logs of the subcalls are part of the logs of the transaction. Subcalls are visible in the
+/// traces of the transaction as calls of the precompile, without the calls they make.
+interface Batch {
+    /// Emitted when the subcall at `index` succeeded
+    event SubcallSucceeded(uint256 index);
+
+    /// Emitted when the subcall at `index` failed
+    event SubcallFailed(uint256 index);
+
+    /// Execute all the subcalls, skipping the ones that fail
+    function batchSome(
+        address[] memory to,
+        uint256[] memory value,
+        bytes[] memory call_data,
+        uint64[] memory gas_limit
+    ) external;
+
+    /// Execute the subcalls until one fails, skipping the next ones
+    function batchSomeUntilFailure(
+        address[] memory to,
+        uint256[] memory value,
+        bytes[] memory call_data,
+        uint64[] memory gas_limit
+    ) external;
+
+    /// Execute all the subcalls, failing with the reason of the first failed subcall (for
+    /// example `Subcall 1 failed: reverted with "nope"`) and reverting them all if one fails
+    function batchAll(
+        address[] memory to,
+        uint256[] memory value,
+        bytes[] memory call_data,
+        uint64[] memory gas_limit
+    ) external;
+}
+
+// The precompile computes the four-byte selectors from the function signatures:
+// {
+// 	"96e292b8": "batchAll(address[],uint256[],bytes[],uint64[])",
+// 	"79df4b9c": "batchSome(address[],uint256[],bytes[],uint64[])",
+// 	"cf0491c7": "batchSomeUntilFailure(address[],uint256[],bytes[],uint64[])"
+// }
+
+// The first topic of each event is the Keccak-256 hash of its signature:
+// {
+// 	"SubcallFailed(uint256)": "0xdbc5d06f4f877f959b1ff12d2161cdd693fa8e442ee53f1790b2804b24881f05",
+// 	"SubcallSucceeded(uint256)": "0xbf855484633929c3d6688eb3caf8eff910fb4bef030a8d7dbc9390d26759714d"
+// }
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-3.0-only
+pragma solidity >=0.8.0;
+
+/// The interface through which solidity contracts will interact with pallet_democracy
+/// We follow this same interface including four-byte function selectors, in the precompile that
+/// wraps the pallet, at address 0x0000000000000000000000000000000000000802 (2050)
+/// The dispatchables are called with the caller as origin. When one fails, the call fails with the
+/// name of the pallet_democracy error as message (for example "ValueLow")
+interface Democracy {
+    // First some simple accessors
+
+    /// Get the total number of public proposals made so far
+    function public_prop_count() external view returns (uint256);
+
+    /// Get the deposit locked for a public proposal, 0 if none
+    function deposit_of(uint256 prop_index) external view returns (uint256);
+
+    /// Get the index of the lowest unbaked referendum
+    function lowest_unbaked() external view returns (uint256);
+
+    /// Get the details of an ongoing referendum. Fails if the referendum is unknown or finished.
+    /// `threshold` is 0 for super majority approve, 1 for super majority against and 2 for simple
+    /// majority
+    function ongoing_referendum_info(uint256 ref_index)
+        external
+        view
+        returns (
+            uint256 end,
+            bytes32 proposal_hash,
+            uint256 threshold,
+            uint256 delay,
+            uint256 ayes,
+            uint256 nays,
+            uint256 turnout
+        );
+
+    /// Get the result of a finished referendum. Fails if the referendum is unknown or ongoing.
+    function finished_referendum_info(uint256 ref_index)
+        external
+        view
+        returns (bool approved, uint256 end);
+
+    // Now the dispatchables
+
+    /// Make a public proposal of the given preimage hash, locking `value` as deposit
+    function propose(bytes32 proposal_hash, uint256 value) external;
+
+    /// Second a public proposal. `seconds_upper_bound` is an upper bound on the current number of
+    /// seconds of the proposal, used to compute the weight of the call
+    function second(uint256 prop_index, uint256 seconds_upper_bound) external;
+
+    /// Vote in a referendum, locking `vote_amount` with the given conviction (0 for no lock up to
+    /// 6 for a 32x lock period)
+    function standard_vote(
+        uint256 ref_index,
+        bool aye,
+        uint256 vote_amount,
+        uint256 conviction
+    ) external;
+
+    /// Remove a vote from a referendum
+    function remove_vote(uint256 ref_index) external;
+
+    /// Delegate the voting power of `amount`, with the given conviction, to `representative`
+    function delegate(
+        address representative,
+        uint256 conviction,
+        uint256 amount
+    ) external;
+
+    /// Undelegate the voting power
+    function un_delegate() external;
+
+    /// Register the preimage of a proposal, the encoded runtime call
+    function note_preimage(bytes memory encoded_proposal) external;
+}
+
+// The precompile computes the four-byte selectors from the function signatures:
+// {
+// 	"0185921e": "delegate(address,uint256,uint256)",
+// 	"a30305e9": "deposit_of(uint256)",
+// 	"b1fd383f": "finished_referendum_info(uint256)",
+// 	"0388f282": "lowest_unbaked()",
+// 	"200881f5": "note_preimage(bytes)",
+// 	"8b93d11a": "ongoing_referendum_info(uint256)",
+// 	"7824e7d1": "propose(bytes32,uint256)",
+// 	"56fdf547": "public_prop_count()",
+// 	"2042f50b": "remove_vote(uint256)",
+// 	"c7a76601": "second(uint256,uint256)",
+// 	"3f3c21cc": "standard_vote(uint256,bool,uint256,uint256)",
+// 	"cb37b8ea": "un_delegate()"
+// }
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-3.0-only
+pragma solidity >=0.8.0;
+
+/// The ERC-20 interface of the native token, implemented by the precompile at address
+/// 0x0000000000000000000000000000000000000801 (2049) over the balances of pallet_balances
+/// See https://eips.ethereum.org/EIPS/eip-20
+/// Transfers are dispatched to pallet_balances: when one fails, the call fails with the name of
+/// the pallet_balances error as message (for example "InsufficientBalance")
+/// An allowance of 2^256-1 is never decreased by transferFrom, and an account can transferFrom
+/// its own balance without allowance
+interface IERC20 {
+    /// Emitted when `value` tokens are moved from `from` to `to`
+    event Transfer(address indexed from, address indexed to, uint256 value);
+
+    /// Emitted when the allowance of `spender` for `owner` is set to `value` by `approve`
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+
+    /// Get the name of the token
+    function name() external view returns (string memory);
+
+    /// Get the symbol of the token
+    function symbol() external view returns (string memory);
+
+    /// Get the number of decimals of the token
+    function decimals() external view returns (uint8);
+
+    /// Get the total issuance of the token
+    function totalSupply() external view returns (uint256);
+
+    /// Get the free balance of an account
+    function balanceOf(address owner) external view returns (uint256);
+
+    /// Get the amount `spender` is allowed to transfer from the balance of `owner`
+    function allowance(address owner, address spender) external view returns (uint256);
+
+    /// Transfer `value` from the caller to `to`
+    function transfer(address to, uint256 value) external returns (bool);
+
+    /// Allow `spender` to transfer up to `value` from the balance of the caller
+    function approve(address spender, uint256 value) external returns (bool);
+
+    /// Transfer `value` from `from` to `to`, using the allowance of the caller
+    function transferFrom(
+        address from,
+        address to,
+        uint256 value
+    ) external returns (bool);
+}
+
+// The precompile computes the four-byte selectors from the function signatures:
+// {
+// 	"dd62ed3e": "allowance(address,address)",
+// 	"095ea7b3": "approve(address,uint256)",
+// 	"70a08231": "balanceOf(address)",
+// 	"313ce567": "decimals()",
+// 	"06fdde03": "name()",
+// 	"95d89b41": "symbol()",
+// 	"18160ddd": "totalSupply()",
+// 	"a9059cbb": "transfer(address,uint256)",
+// 	"23b872dd": "transferFrom(address,address,uint256)"
+// }
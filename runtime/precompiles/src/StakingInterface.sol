@@ -4,8 +4,50 @@ pragma solidity >=0.8.0;
 /// The interface through which solidity contracts will interact with Parachain Staking
 /// We follow this same interface including four-byte function selectors, in the precompile that
 /// wraps the pallet
+/// When a dispatchable fails, the call reverts with the name of the parachain_staking error as
+/// `Error(string)` reason (for example "NomBondBelowMin" or "TooManyNominators"), which Ethereum
+/// clients report in the error of eth_call and eth_estimateGas. This only holds when the
+/// precompile is the target of the transaction: called from a contract, the call fails without
+/// revert data
+/// Each successful dispatchable emits one of the events below from the precompile address, with
+/// the caller as first indexed parameter. Amounts are the ones given to the call.
 interface ParachainStaking {
-    // First some simple accessors
+    // The events emitted by the dispatchables
+
+    /// The caller joined the set of collator candidates with `amount` bonded
+    event JoinedCandidates(address indexed candidate, uint256 amount);
+
+    /// The caller requested to leave the set of collator candidates
+    event LeftCandidates(address indexed candidate);
+
+    /// The caller temporarily left the set of collator candidates
+    event WentOffline(address indexed candidate);
+
+    /// The caller rejoined the set of collator candidates
+    event WentOnline(address indexed candidate);
+
+    /// The caller increased its collator bond by `more`
+    event CandidateBondedMore(address indexed candidate, uint256 more);
+
+    /// The caller decreased its collator bond by `less`
+    event CandidateBondedLess(address indexed candidate, uint256 less);
+
+    /// The caller nominated `collator` with `amount`
+    event Nominated(address indexed nominator, address indexed collator, uint256 amount);
+
+    /// The caller left the set of nominators, revoking all its nominations
+    event LeftNominators(address indexed nominator);
+
+    /// The caller revoked its nomination of `collator`
+    event NominationRevoked(address indexed nominator, address indexed collator);
+
+    /// The caller increased its nomination of `collator` by `more`
+    event NominationIncreased(address indexed nominator, address indexed collator, uint256 more);
+
+    /// The caller decreased its nomination of `collator` by `less`
+    event NominationDecreased(address indexed nominator, address indexed collator, uint256 less);
+
+    // Then some simple accessors
 
     /// Check whether the specified address is currently a staking nominator
     function is_nominator(address nominator) external view returns (bool);
@@ -16,6 +58,42 @@ interface ParachainStaking {
     /// Get the minimum nomination amount
     function min_nomination() external view returns (uint256);
 
+    /// Get the total backing stake of a collator candidate (bond and nominations), 0 if none
+    function candidate_total(address collator) external view returns (uint256);
+
+    /// Get the self bond of a collator candidate, 0 if none
+    function candidate_bond(address collator) external view returns (uint256);
+
+    /// Get the number of nominations of a collator candidate
+    function collator_nomination_count(address collator) external view returns (uint256);
+
+    /// Get the amount nominated by a nominator to a collator candidate, 0 if none
+    function nomination_amount(address nominator, address collator)
+        external
+        view
+        returns (uint256);
+
+    /// Get the total amount nominated by a nominator, 0 if none
+    function nominator_total(address nominator) external view returns (uint256);
+
+    /// Get the collator candidates selected for the current round
+    function selected_candidates() external view returns (address[] memory);
+
+    /// Get the current round index
+    function round() external view returns (uint256);
+
+    /// Get the first block of the current round
+    function round_first_block() external view returns (uint256);
+
+    /// Get the total points awarded to collators in a round
+    function points(uint256 round_index) external view returns (uint256);
+
+    /// Get the points awarded to a collator in a round
+    function awarded_points(uint256 round_index, address collator) external view returns (uint256);
+
+    /// Get the commission taken by collators on rewards, in parts per billion
+    function collator_commission() external view returns (uint256);
+
     // Now the dispatchables
 
     /// Join the set of collator candidates
@@ -55,12 +133,17 @@ interface ParachainStaking {
     function nominator_bond_less(address candidate, uint256 less) external;
 }
 
-// These are the selectors generated by remix following this advice
-// https://ethereum.stackexchange.com/a/73405/9963
-// Eventually we will probably want a better way of generating these and copying them to Rust
+// The precompile computes the four-byte selectors from the function signatures, for example
+// "nominate(address,uint256)" gives 82f2c8df. Here are all of them, as generated by remix
+// following this advice https://ethereum.stackexchange.com/a/73405/9963
 // {
+// 	"075a156e": "awarded_points(uint256,address)",
+// 	"1b4c6ef1": "candidate_bond(address)",
 // 	"289b6ba7": "candidate_bond_less(uint256)",
 // 	"c57bd3a8": "candidate_bond_more(uint256)",
+// 	"a29d46c6": "candidate_total(address)",
+// 	"b0c0081f": "collator_commission()",
+// 	"0ad6a7be": "collator_nomination_count(address)",
 // 	"767e0450": "go_offline()",
 // 	"d2f73ceb": "go_online()",
 // 	"8545c833": "is_candidate(address)",
@@ -69,8 +152,29 @@ interface ParachainStaking {
 // 	"b7694219": "leave_candidates()",
 // 	"e8d68a37": "leave_nominators()",
 // 	"c9f593b2": "min_nomination()",
+// 	"a19a26cb": "nomination_amount(address,address)",
 // 	"82f2c8df": "nominate(address,uint256)",
 // 	"f6a52569": "nominator_bond_less(address,uint256)",
 // 	"971d44c8": "nominator_bond_more(address,uint256)",
-// 	"4b65c34b": "revoke_nomination(address)"
+// 	"b6c29bcd": "nominator_total(address)",
+// 	"9799b4e7": "points(uint256)",
+// 	"4b65c34b": "revoke_nomination(address)",
+// 	"146ca531": "round()",
+// 	"e0811720": "round_first_block()",
+// 	"89f47a21": "selected_candidates()"
+// }
+
+// The first topic of each event is the Keccak-256 hash of its signature:
+// {
+// 	"CandidateBondedLess(address,uint256)": "0x749627b695335383fe5b8e5c5735eb6fce45bb92a6f3eafa46740edca0757972",
+// 	"CandidateBondedMore(address,uint256)": "0x9ecef6fd689de69f199b676b859992613c9b4108192b721956b02e8a572d2adc",
+// 	"JoinedCandidates(address,uint256)": "0x68cfce642ce6ac3644b1ff67575f0ec9fceaa8bb1d1083d3a961335a898e4bfc",
+// 	"LeftCandidates(address)": "0x41e968c1166c65d12a14f0b66400fe7dbc2b43f5916b495e02f69b3cf40c08f6",
+// 	"LeftNominators(address)": "0xe8081767e4ae40dc167e692981cd606d9382f02026c1d62595f3be21d7a94874",
+// 	"Nominated(address,address,uint256)": "0xe89e9c9c7af6c43992fa46c3d7e955f6b3d01d8e594eeda65b0b746412d93d47",
+// 	"NominationDecreased(address,address,uint256)": "0x724f01fb79f365d8972eab71f18a705b7f73a157dc4261c674a7decc31715b6a",
+// 	"NominationIncreased(address,address,uint256)": "0xf46d90310dd88d93ab777637cd5a29b5e391e68aa198c313214afaf10ae6004e",
+// 	"NominationRevoked(address,address)": "0xb222dc33217c4c704f7cdb44179e2c0517c57390969d454375ead7be45c2631e",
+// 	"WentOffline(address)": "0x1b7c80563a17e55395e11794ea3cff55f87e9aa230168ca385bc63f622c5d277",
+// 	"WentOnline(address)": "0xddbeda5ba1552ebc25a0fced60868ee8b8e68da51f0b67c012623a453fe23bdb"
 // }
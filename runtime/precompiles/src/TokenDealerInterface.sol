@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-only
+pragma solidity >=0.8.0;
+
+/// The interface through which solidity contracts will interact with pallet_token_dealer
+/// We follow this same interface including four-byte function selectors, in the precompile that
+/// wraps the pallet, at address 0x0000000000000000000000000000000000000804 (2052)
+/// The transfers withdraw the tokens from the caller, and ask the destination chain to pay the
+/// destination account from the account it holds for this parachain. When one fails, the call
+/// fails with the name of the pallet_token_dealer error as message (for example "MessageNotSent"
+/// when no HRMP channel is open to the destination parachain)
+/// Each successful transfer emits one of the events below from the precompile address, with the
+/// caller as first indexed parameter
+interface TokenDealer {
+    // The events emitted by the transfers
+
+    /// The caller sent `amount` to the `destination` account of the relay chain
+    event TransferredToRelayChain(
+        address indexed from,
+        bytes32 indexed destination,
+        uint256 amount
+    );
+
+    /// The caller sent `amount` to the `destination` account of the parachain `para_id`
+    event TransferredToParachain(
+        address indexed from,
+        uint256 indexed para_id,
+        address destination,
+        uint256 amount
+    );
+
+    // Now the transfers
+
+    /// Send `amount` to the `destination` account of the relay chain
+    function transfer_to_relay_chain(bytes32 destination, uint256 amount) external;
+
+    /// Send `amount` to the `destination` account of the parachain `para_id`, through an HRMP
+    /// channel that must be open
+    function transfer_to_parachain(
+        uint256 para_id,
+        address destination,
+        uint256 amount
+    ) external;
+}
+
+// The precompile computes the four-byte selectors from the function signatures:
+// {
+// 	"0b8bd79f": "transfer_to_parachain(uint256,address,uint256)",
+// 	"536787a8": "transfer_to_relay_chain(bytes32,uint256)"
+// }
+
+// The first topic of each event is the Keccak-256 hash of its signature:
+// {
+// 	"TransferredToParachain(address,uint256,address,uint256)": "0x9a513d92f8649bbca4ca7b73de1f461b2a621f1d1b0efadfa113797101a6a39c",
+// 	"TransferredToRelayChain(address,bytes32,uint256)": "0x77af7632ea8d44fbed9841ec8c6a159fa1820f489b36f34dceae9845008c41f3"
+// }
@@ -0,0 +1,289 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile executing several EVM calls in a row, on behalf of the caller
+use crate::hash;
+use alloc::{format, string::String};
+use evm::{Context, ExitError, ExitReason, ExitSucceed};
+use frame_support::{
+	storage::{with_transaction, TransactionOutcome},
+	traits::Get,
+};
+use pallet_evm::{AddressMapping, Precompile, Runner};
+use precompile_utils::{
+	deposit_log, error, generate_function_selector, keccak256, log1, log_cost, Bytes,
+	EvmDataReader, EvmDataWriter, EvmResult, Gasometer,
+};
+use rustc_hex::ToHex;
+use sp_core::{H160, U256};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+/// Solidity selectors of the functions of the BatchInterface.sol, computed from their
+/// signatures according to the solidity specification
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+#[generate_function_selector]
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	BatchSome = "batchSome(address[],uint256[],bytes[],uint64[])",
+	BatchSomeUntilFailure = "batchSomeUntilFailure(address[],uint256[],bytes[],uint64[])",
+	BatchAll = "batchAll(address[],uint256[],bytes[],uint64[])",
+}
+
+/// Topics of the events of the BatchInterface.sol.
+const SELECTOR_LOG_SUBCALL_SUCCEEDED: [u8; 32] = keccak256!("SubcallSucceeded(uint256)");
+const SELECTOR_LOG_SUBCALL_FAILED: [u8; 32] = keccak256!("SubcallFailed(uint256)");
+
+/// Selector of the `Error(string)` revert data produced by Solidity `require` and `revert`.
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+environmental::environmental!(batching: bool);
+
+/// What happens to the batch when one of its subcalls fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+	/// The failed subcall is skipped, the next ones are executed.
+	BatchSome,
+	/// The failed subcall and the next ones are skipped.
+	BatchSomeUntilFailure,
+	/// The whole batch fails, reverting the subcalls already executed.
+	BatchAll,
+}
+
+/// A precompile executing several calls with the caller of the precompile as sender, which a
+/// helper contract can't do since it would become the sender of the calls.
+///
+/// Each subcall has its own value, taken from the balance of the caller, and its own gas limit.
+/// A missing or zero gas limit gives all the remaining gas to the subcall. The logs of the
+/// subcalls are included in the logs of the transaction, followed by a `SubcallSucceeded` or
+/// `SubcallFailed` log for each executed subcall.
+///
+/// The subcalls are separate executions of the runtime EVM runner, so they don't increase the
+/// nonce of the caller. Their changes are made in a storage transaction, rolled back when the
+/// batch fails. The runner records them for the tracer, which shows them as subcalls of the
+/// precompile call, but the calls made by contracts during a subcall are not traced. A
+/// precompile can't return revert data either, so `batchAll` fails with an error which includes
+/// the raw revert data of the failed subcall.
+pub struct BatchWrapper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for BatchWrapper<Runtime>
+where
+	Runtime: pallet_evm::Config,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		log::trace!(target: "batch-precompile", "In batch wrapper");
+
+		// Through DELEGATECALL or CALLCODE, the subcalls would be sent on behalf of the caller of
+		// the calling contract.
+		if context.address != hash(2051) {
+			return Err(error("Cannot be called with DELEGATECALL or CALLCODE"));
+		}
+
+		let mut input = EvmDataReader::new(input);
+
+		let mode = match input.read_selector()? {
+			Action::BatchSome => Mode::BatchSome,
+			Action::BatchSomeUntilFailure => Mode::BatchSomeUntilFailure,
+			Action::BatchAll => Mode::BatchAll,
+		};
+
+		Self::batch(input, target_gas, context, mode)
+	}
+}
+
+impl<Runtime> BatchWrapper<Runtime>
+where
+	Runtime: pallet_evm::Config,
+{
+	fn batch(
+		mut input: EvmDataReader,
+		target_gas: Option<u64>,
+		context: &Context,
+		mode: Mode,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		// The precompile can't do anything with a value sent to it.
+		if !context.apparent_value.is_zero() {
+			return Err(error("Batch doesn't accept value"));
+		}
+
+		// Each subcall starts a new EVM execution, which is not limited by the call stack limit
+		// of the outer one.
+		if batching::with(|batching| *batching).unwrap_or(false) {
+			return Err(error("Nested batches are not allowed"));
+		}
+
+		let addresses: Vec<H160> = input.read()?;
+		let values: Vec<U256> = input.read()?;
+		let calls_data: Vec<Bytes> = input.read()?;
+		let gas_limits: Vec<u64> = input.read()?;
+
+		if values.len() > addresses.len()
+			|| calls_data.len() > addresses.len()
+			|| gas_limits.len() > addresses.len()
+		{
+			return Err(error("Value, call data and gas limit arrays are longer than addresses"));
+		}
+
+		let mut gasometer = Gasometer::new(target_gas);
+
+		// Like a failed call, a failed batch has no effect, even when it is not executed in a
+		// transaction.
+		with_transaction(|| {
+			let result = Self::subcalls(
+				addresses,
+				values,
+				calls_data,
+				gas_limits,
+				context,
+				mode,
+				&mut gasometer,
+			);
+			if result.is_ok() {
+				TransactionOutcome::Commit(result)
+			} else {
+				TransactionOutcome::Rollback(result)
+			}
+		})?;
+
+		Ok((ExitSucceed::Stopped, Default::default(), gasometer.used_gas()))
+	}
+
+	/// Execute the subcalls of a batch, depositing a log for each of them.
+	fn subcalls(
+		addresses: Vec<H160>,
+		values: Vec<U256>,
+		calls_data: Vec<Bytes>,
+		gas_limits: Vec<u64>,
+		context: &Context,
+		mode: Mode,
+		gasometer: &mut Gasometer,
+	) -> EvmResult {
+		batching::using(&mut true, || {
+			for (index, address) in addresses.into_iter().enumerate() {
+				let value = values.get(index).cloned().unwrap_or_default();
+				let call_data = calls_data.get(index).cloned().unwrap_or_default();
+				let gas_limit = gas_limits.get(index).cloned().unwrap_or_default();
+
+				let result = Self::subcall(
+					context.caller,
+					address,
+					value,
+					call_data,
+					gas_limit,
+					gasometer,
+				)?;
+
+				let topic = match &result {
+					Ok(()) => SELECTOR_LOG_SUBCALL_SUCCEEDED,
+					Err(reason) if mode == Mode::BatchAll => {
+						return Err(error(format!("Subcall {} failed: {}", index, reason)));
+					}
+					Err(_) => SELECTOR_LOG_SUBCALL_FAILED,
+				};
+
+				let log = log1(
+					context.address,
+					topic,
+					EvmDataWriter::new().write(U256::from(index)).build(),
+				);
+				gasometer.record_cost(log_cost(&log))?;
+				deposit_log(log);
+
+				if result.is_err() && mode == Mode::BatchSomeUntilFailure {
+					break;
+				}
+			}
+
+			Ok(())
+		})
+	}
+
+	/// Execute a subcall on behalf of `caller`, charging its gas. Returns the reason of the
+	/// failure if the subcall didn't succeed.
+	fn subcall(
+		caller: H160,
+		address: H160,
+		value: U256,
+		call_data: Bytes,
+		gas_limit: u64,
+		gasometer: &mut Gasometer,
+	) -> EvmResult<Result<(), String>> {
+		let remaining_gas = match gasometer.remaining_gas()? {
+			Some(remaining_gas) => remaining_gas,
+			None => Runtime::BlockGasLimit::get().low_u64(),
+		};
+		let gas_limit = match gas_limit {
+			0 => remaining_gas,
+			gas_limit if gas_limit > remaining_gas => return Err(ExitError::OutOfGas),
+			gas_limit => gas_limit,
+		};
+
+		let account = Runtime::AddressMapping::into_account_id(caller);
+		let nonce = frame_system::Pallet::<Runtime>::account_nonce(&account);
+
+		let result = Runtime::Runner::call(
+			caller,
+			address,
+			call_data.0,
+			value,
+			gas_limit,
+			Some(U256::zero()),
+			None,
+			Runtime::config(),
+		);
+
+		// Executing a call increases the nonce of its sender, as if it was a transaction.
+		frame_system::Account::<Runtime>::mutate(&account, |info| info.nonce = nonce);
+
+		let info = match result {
+			Ok(info) => info,
+			Err(_) => return Ok(Err("could not be executed".into())),
+		};
+
+		gasometer.record_cost(info.used_gas.low_u64())?;
+
+		Ok(match info.exit_reason {
+			ExitReason::Succeed(_) => Ok(()),
+			ExitReason::Revert(_) => Err(revert_reason(&info.value)),
+			ExitReason::Error(e) => Err(format!("{:?}", e)),
+			ExitReason::Fatal(e) => Err(format!("{:?}", e)),
+		})
+	}
+}
+
+/// Human readable reason of a revert: the message of an `Error(string)` revert followed by the
+/// hex encoded revert data, or the hex encoded revert data alone.
+fn revert_reason(output: &[u8]) -> String {
+	if output.is_empty() {
+		return "reverted".into();
+	}
+
+	let data = output.to_hex::<String>();
+	if output.len() >= 4 && output[0..4] == ERROR_STRING_SELECTOR {
+		let message = EvmDataReader::new(&output[4..])
+			.read::<Bytes>()
+			.ok()
+			.and_then(|message| String::from_utf8(message.0).ok());
+		if let Some(message) = message {
+			return format!("reverted with \"{}\" (0x{})", message, data);
+		}
+	}
+
+	format!("reverted with 0x{}", data)
+}
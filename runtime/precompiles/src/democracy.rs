@@ -0,0 +1,369 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile to call pallet-democracy runtime methods via the EVM
+use crate::{balance_to_u256, hash, read_amount};
+use evm::{Context, ExitError, ExitSucceed};
+use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
+use frame_support::traits::Currency;
+use pallet_democracy::{AccountVote, Conviction, ReferendumInfo, Vote, VoteThreshold};
+use pallet_evm::{AddressMapping, Precompile};
+use precompile_utils::{
+	error, generate_function_selector, Bytes, EvmDataReader, EvmDataWriter, EvmResult,
+	Gasometer, RuntimeHelper,
+};
+use sp_core::{H160, H256, U256};
+use sp_std::convert::{TryFrom, TryInto};
+use sp_std::fmt::Debug;
+use sp_std::{marker::PhantomData, vec::Vec};
+
+type BalanceOf<Runtime> = <<Runtime as pallet_democracy::Config>::Currency as Currency<
+	<Runtime as frame_system::Config>::AccountId,
+>>::Balance;
+
+/// Solidity selectors of the functions of the DemocracyInterface.sol, computed from their
+/// signatures according to the solidity specification
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+#[generate_function_selector]
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	PublicPropCount = "public_prop_count()",
+	DepositOf = "deposit_of(uint256)",
+	LowestUnbaked = "lowest_unbaked()",
+	OngoingReferendumInfo = "ongoing_referendum_info(uint256)",
+	FinishedReferendumInfo = "finished_referendum_info(uint256)",
+	Propose = "propose(bytes32,uint256)",
+	Second = "second(uint256,uint256)",
+	StandardVote = "standard_vote(uint256,bool,uint256,uint256)",
+	RemoveVote = "remove_vote(uint256)",
+	Delegate = "delegate(address,uint256,uint256)",
+	UnDelegate = "un_delegate()",
+	NotePreimage = "note_preimage(bytes)",
+}
+
+/// A precompile to wrap the functionality from pallet_democracy, so that accounts only able to
+/// sign Ethereum transactions can take part in governance.
+pub struct DemocracyWrapper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for DemocracyWrapper<Runtime>
+where
+	Runtime: pallet_democracy::Config + pallet_evm::Config,
+	Runtime::AccountId: From<H160>,
+	Runtime::Hash: From<H256> + Into<H256>,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_democracy::Call<Runtime>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		log::trace!(target: "democracy-precompile", "In democracy wrapper");
+
+		// Through DELEGATECALL or CALLCODE, the calling contract could propose, second, vote and
+		// delegate with the funds of its own caller.
+		if context.address != hash(2050) {
+			return Err(error("Cannot be called with DELEGATECALL or CALLCODE"));
+		}
+
+		let mut input = EvmDataReader::new(input);
+		let mut gasometer = Gasometer::new(target_gas);
+
+		let inner_call = match input.read_selector()? {
+			// Check for accessor methods first. These return results immediately
+			Action::PublicPropCount => return Self::public_prop_count(gasometer),
+			Action::DepositOf => return Self::deposit_of(input, gasometer),
+			Action::LowestUnbaked => return Self::lowest_unbaked(gasometer),
+			Action::OngoingReferendumInfo => {
+				return Self::ongoing_referendum_info(input, gasometer)
+			}
+			Action::FinishedReferendumInfo => {
+				return Self::finished_referendum_info(input, gasometer)
+			}
+
+			// If not an accessor, check for dispatchables. These calls ready for dispatch below.
+			Action::Propose => Self::propose(input)?,
+			Action::Second => Self::second(input)?,
+			Action::StandardVote => Self::standard_vote(input)?,
+			Action::RemoveVote => Self::remove_vote(input)?,
+			Action::Delegate => Self::delegate(input)?,
+			Action::UnDelegate => Self::un_delegate()?,
+			Action::NotePreimage => Self::note_preimage(input)?,
+		};
+
+		// Dispatch that call
+		let origin = Runtime::AddressMapping::into_account_id(context.caller);
+
+		log::trace!(target: "democracy-precompile", "Gonna call with origin {:?}", origin);
+
+		RuntimeHelper::<Runtime>::try_dispatch(Some(origin).into(), inner_call, &mut gasometer)?;
+
+		Ok((ExitSucceed::Stopped, Default::default(), gasometer.used_gas()))
+	}
+}
+
+/// Parses a conviction from the input, as its index in `Conviction` (0 for `None` up to 6 for
+/// `Locked6x`).
+fn read_conviction(input: &mut EvmDataReader) -> EvmResult<Conviction> {
+	let conviction: u8 = input.read()?;
+	Conviction::try_from(conviction).map_err(|_| error("Must be an integer between 0 and 6"))
+}
+
+impl<Runtime> DemocracyWrapper<Runtime>
+where
+	Runtime: pallet_democracy::Config + pallet_evm::Config,
+	Runtime::AccountId: From<H160>,
+	Runtime::Hash: From<H256> + Into<H256>,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_democracy::Call<Runtime>>,
+{
+	// The accessors are first. They directly return their result.
+
+	fn public_prop_count(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let prop_count = pallet_democracy::Pallet::<Runtime>::public_prop_count();
+
+		log::trace!(target: "democracy-precompile", "Prop count from pallet is {:?}", prop_count);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(prop_count).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn deposit_of(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let prop_index: u32 = input.read()?;
+
+		// The deposit is zero for proposals which don't exist (anymore).
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let deposit = pallet_democracy::Pallet::<Runtime>::deposit_of(prop_index)
+			.map(|(_, deposit)| deposit)
+			.unwrap_or_default();
+
+		log::trace!(target: "democracy-precompile", "Deposit of {:?} is {:?}", prop_index, deposit);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(deposit)?).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn lowest_unbaked(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let lowest_unbaked = pallet_democracy::Pallet::<Runtime>::lowest_unbaked();
+
+		log::trace!(
+			target: "democracy-precompile",
+			"Lowest unbaked referendum is {:?}",
+			lowest_unbaked
+		);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(lowest_unbaked).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	/// Returns the end block, proposal hash, vote threshold (0 for super majority approve, 1 for
+	/// super majority against, 2 for simple majority), enactment delay, ayes, nays and turnout of
+	/// an ongoing referendum.
+	fn ongoing_referendum_info(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let ref_index: u32 = input.read()?;
+
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let status = match pallet_democracy::Pallet::<Runtime>::referendum_info(ref_index) {
+			Some(ReferendumInfo::Ongoing(status)) => status,
+			Some(ReferendumInfo::Finished { .. }) => return Err(error("Referendum is finished")),
+			None => return Err(error("Unknown referendum")),
+		};
+
+		let threshold: u8 = match status.threshold {
+			VoteThreshold::SuperMajorityApprove => 0,
+			VoteThreshold::SuperMajorityAgainst => 1,
+			VoteThreshold::SimpleMajority => 2,
+		};
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new()
+				.write(block_number_to_u128::<Runtime>(status.end)?)
+				.write::<H256>(status.proposal_hash.into())
+				.write(threshold)
+				.write(block_number_to_u128::<Runtime>(status.delay)?)
+				.write(balance_to_u256(status.tally.ayes)?)
+				.write(balance_to_u256(status.tally.nays)?)
+				.write(balance_to_u256(status.tally.turnout)?)
+				.build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	/// Returns whether a finished referendum was approved, and the block at which it ended.
+	fn finished_referendum_info(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let ref_index: u32 = input.read()?;
+
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let info = pallet_democracy::Pallet::<Runtime>::referendum_info(ref_index);
+		let (approved, end) = match info {
+			Some(ReferendumInfo::Finished { approved, end }) => (approved, end),
+			Some(ReferendumInfo::Ongoing(_)) => return Err(error("Referendum is ongoing")),
+			None => return Err(error("Unknown referendum")),
+		};
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new()
+				.write(approved)
+				.write(block_number_to_u128::<Runtime>(end)?)
+				.build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	// The dispatchable wrappers are next. They return a substrate inner Call ready for dispatch.
+
+	fn propose(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(2)?;
+		let proposal_hash: H256 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+
+		log::trace!(
+			target: "democracy-precompile",
+			"Proposing {:?} with a deposit of {:?}",
+			proposal_hash,
+			amount
+		);
+
+		Ok(pallet_democracy::Call::<Runtime>::propose(
+			proposal_hash.into(),
+			amount,
+		))
+	}
+
+	fn second(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(2)?;
+		let prop_index: u32 = input.read()?;
+		let seconds_upper_bound: u32 = input.read()?;
+
+		log::trace!(target: "democracy-precompile", "Seconding proposal {:?}", prop_index);
+
+		Ok(pallet_democracy::Call::<Runtime>::second(
+			prop_index,
+			seconds_upper_bound,
+		))
+	}
+
+	fn standard_vote(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(4)?;
+		let ref_index: u32 = input.read()?;
+		let aye: bool = input.read()?;
+		let balance = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+		let conviction = read_conviction(&mut input)?;
+
+		log::trace!(
+			target: "democracy-precompile",
+			"Voting {:?} on referendum {:?} with {:?} and conviction {:?}",
+			aye,
+			ref_index,
+			balance,
+			conviction
+		);
+
+		let vote = AccountVote::Standard {
+			vote: Vote { aye, conviction },
+			balance,
+		};
+
+		Ok(pallet_democracy::Call::<Runtime>::vote(ref_index, vote))
+	}
+
+	fn remove_vote(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(1)?;
+		let ref_index: u32 = input.read()?;
+
+		log::trace!(target: "democracy-precompile", "Removing vote from {:?}", ref_index);
+
+		Ok(pallet_democracy::Call::<Runtime>::remove_vote(ref_index))
+	}
+
+	fn delegate(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(3)?;
+		let representative: H160 = input.read()?;
+		let conviction = read_conviction(&mut input)?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+
+		log::trace!(
+			target: "democracy-precompile",
+			"Delegating {:?} to {:?} with conviction {:?}",
+			amount,
+			representative,
+			conviction
+		);
+
+		Ok(pallet_democracy::Call::<Runtime>::delegate(
+			representative.into(),
+			conviction,
+			amount,
+		))
+	}
+
+	fn un_delegate() -> EvmResult<pallet_democracy::Call<Runtime>> {
+		Ok(pallet_democracy::Call::<Runtime>::undelegate())
+	}
+
+	fn note_preimage(mut input: EvmDataReader) -> EvmResult<pallet_democracy::Call<Runtime>> {
+		input.expect_arguments(1)?;
+		let encoded_proposal: Bytes = input.read()?;
+
+		log::trace!(
+			target: "democracy-precompile",
+			"Noting preimage of {} bytes",
+			encoded_proposal.0.len()
+		);
+
+		Ok(pallet_democracy::Call::<Runtime>::note_preimage(
+			encoded_proposal.0,
+		))
+	}
+}
+
+/// Converts a block number of the runtime to a Solidity uint256.
+fn block_number_to_u128<Runtime: frame_system::Config>(
+	block_number: Runtime::BlockNumber,
+) -> EvmResult<u128> {
+	block_number
+		.try_into()
+		.map_err(|_| error("Block number is too large"))
+}
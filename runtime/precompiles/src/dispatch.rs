@@ -0,0 +1,75 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile dispatching SCALE encoded runtime calls, restricted by a call filter
+use codec::Decode;
+use evm::{Context, ExitError, ExitSucceed};
+use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
+use frame_support::traits::Filter;
+use pallet_evm::{AddressMapping, Precompile};
+use precompile_utils::{error, Gasometer, RuntimeHelper};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+environmental::environmental!(dispatching: bool);
+
+/// Configuration of the calls that contracts can dispatch through the `Dispatch` precompile.
+pub trait DispatchFilter: frame_system::Config {
+	/// Calls allowed through the precompile. Anything it rejects fails before being dispatched.
+	type CallFilter: Filter<<Self as frame_system::Config>::Call>;
+}
+
+/// A precompile dispatching the SCALE encoded runtime call given as input, with the caller as
+/// signed origin. It replaces `pallet_evm_precompile_dispatch::Dispatch`, which dispatches any
+/// call and bypasses the runtime `BaseCallFilter`.
+///
+/// The call must be allowed by the `CallFilter` of the runtime, its weight is charged as gas and
+/// it fails if it dispatches a call that ends up calling this precompile again.
+pub struct FilteredDispatch<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for FilteredDispatch<Runtime>
+where
+	Runtime: pallet_evm::Config + DispatchFilter,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		log::trace!(target: "dispatch-precompile", "In filtered dispatch");
+
+		let mut gasometer = Gasometer::new(target_gas);
+
+		let call = Runtime::Call::decode(&mut &input[..])
+			.map_err(|_| error("Failed to decode call"))?;
+
+		if !Runtime::CallFilter::filter(&call) {
+			return Err(error("Call is not allowed"));
+		}
+
+		if dispatching::with(|dispatching| *dispatching).unwrap_or(false) {
+			return Err(error("Reentrant dispatch is not allowed"));
+		}
+
+		let origin = Runtime::AddressMapping::into_account_id(context.caller);
+		dispatching::using(&mut true, || {
+			RuntimeHelper::<Runtime>::try_dispatch(Some(origin).into(), call, &mut gasometer)
+		})?;
+
+		Ok((ExitSucceed::Stopped, Default::default(), gasometer.used_gas()))
+	}
+}
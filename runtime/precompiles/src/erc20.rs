@@ -0,0 +1,390 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile exposing the native balance of pallet_balances as an ERC-20 token
+use crate::{balance_to_u256, hash, read_amount};
+use evm::{Context, ExitError, ExitSucceed};
+use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
+use frame_support::storage::types::{StorageDoubleMap, ValueQuery};
+use frame_support::traits::{Currency, StorageInstance};
+use frame_support::Blake2_128Concat;
+use pallet_evm::{AddressMapping, Precompile};
+use precompile_utils::{
+	deposit_log, error, generate_function_selector, keccak256, log3, log_cost, Bytes,
+	EvmDataReader, EvmDataWriter, EvmResult, Gasometer, RuntimeHelper,
+};
+use sp_core::{H160, U256};
+use sp_runtime::traits::StaticLookup;
+use sp_std::convert::{TryFrom, TryInto};
+use sp_std::fmt::Debug;
+use sp_std::{marker::PhantomData, vec::Vec};
+
+type BalanceOf<Runtime> = <Runtime as pallet_balances::Config>::Balance;
+
+/// Solidity selectors of the functions of the Erc20Interface.sol, computed from their
+/// signatures according to the solidity specification
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+#[generate_function_selector]
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	Name = "name()",
+	Symbol = "symbol()",
+	Decimals = "decimals()",
+	TotalSupply = "totalSupply()",
+	BalanceOf = "balanceOf(address)",
+	Allowance = "allowance(address,address)",
+	Transfer = "transfer(address,uint256)",
+	Approve = "approve(address,uint256)",
+	TransferFrom = "transferFrom(address,address,uint256)",
+}
+
+/// Topics of the events of the Erc20Interface.sol.
+const SELECTOR_LOG_TRANSFER: [u8; 32] = keccak256!("Transfer(address,address,uint256)");
+const SELECTOR_LOG_APPROVAL: [u8; 32] = keccak256!("Approval(address,address,uint256)");
+
+/// Metadata of the native token, as returned by the ERC-20 precompile.
+pub trait Erc20Metadata {
+	/// Name of the token.
+	fn name() -> &'static str;
+	/// Symbol of the token.
+	fn symbol() -> &'static str;
+	/// Number of decimals of the token.
+	fn decimals() -> u8;
+}
+
+/// Storage prefix of the allowances map.
+pub struct AllowancesPrefix;
+
+impl StorageInstance for AllowancesPrefix {
+	fn pallet_prefix() -> &'static str {
+		"NativeErc20"
+	}
+
+	const STORAGE_PREFIX: &'static str = "Allowances";
+}
+
+/// Amounts that spenders are allowed to transfer from the balances of owners, as set by
+/// `approve`. The precompile is not a pallet, so the map is declared here with its own prefix.
+/// Keys are the owner and the spender.
+pub type AllowancesStorage<Runtime> = StorageDoubleMap<
+	AllowancesPrefix,
+	Blake2_128Concat,
+	<Runtime as frame_system::Config>::AccountId,
+	Blake2_128Concat,
+	<Runtime as frame_system::Config>::AccountId,
+	U256,
+	ValueQuery,
+>;
+
+/// A precompile exposing the native balance as an ERC-20 token. Transfers are dispatched to
+/// pallet_balances, so they follow the same rules as the ones done through extrinsics.
+///
+/// Like with OpenZeppelin's implementation, an allowance of `uint256(-1)` is never decreased by
+/// `transferFrom`. An account doesn't need an allowance to `transferFrom` its own balance.
+pub struct NativeErc20Wrapper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for NativeErc20Wrapper<Runtime>
+where
+	Runtime: pallet_balances::Config + pallet_evm::Config + Erc20Metadata,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_balances::Call<Runtime>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		log::trace!(target: "erc20-precompile", "In native ERC-20 wrapper");
+
+		// Through DELEGATECALL or CALLCODE, the precompile would act on behalf of the caller of
+		// the calling contract and emit its logs in the name of that contract.
+		if context.address != hash(2049) {
+			return Err(error("Cannot be called with DELEGATECALL or CALLCODE"));
+		}
+
+		let mut input = EvmDataReader::new(input);
+		let gasometer = Gasometer::new(target_gas);
+
+		match input.read_selector()? {
+			Action::Name => Self::name(gasometer),
+			Action::Symbol => Self::symbol(gasometer),
+			Action::Decimals => Self::decimals(gasometer),
+			Action::TotalSupply => Self::total_supply(gasometer),
+			Action::BalanceOf => Self::balance_of(input, gasometer),
+			Action::Allowance => Self::allowance(input, gasometer),
+			Action::Transfer => Self::transfer(input, gasometer, context),
+			Action::Approve => Self::approve(input, gasometer, context),
+			Action::TransferFrom => Self::transfer_from(input, gasometer, context),
+		}
+	}
+}
+
+impl<Runtime> NativeErc20Wrapper<Runtime>
+where
+	Runtime: pallet_balances::Config + pallet_evm::Config + Erc20Metadata,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_balances::Call<Runtime>>,
+{
+	// The metadata are constants of the runtime, no storage is read.
+
+	fn name(gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new()
+				.write(Bytes::from(Runtime::name().as_bytes()))
+				.build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn symbol(gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new()
+				.write(Bytes::from(Runtime::symbol().as_bytes()))
+				.build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn decimals(gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(Runtime::decimals()).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn total_supply(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let total_issuance = pallet_balances::Pallet::<Runtime>::total_issuance();
+
+		log::trace!(target: "erc20-precompile", "Total issuance is {:?}", total_issuance);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new()
+				.write(balance_to_u256(total_issuance)?)
+				.build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn balance_of(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let owner: H160 = input.read()?;
+
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let owner = Runtime::AddressMapping::into_account_id(owner);
+		let balance = <pallet_balances::Pallet<Runtime> as Currency<_>>::free_balance(&owner);
+
+		log::trace!(target: "erc20-precompile", "Balance of {:?} is {:?}", owner, balance);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(balance)?).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn allowance(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(2)?;
+		let owner: H160 = input.read()?;
+		let spender: H160 = input.read()?;
+
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let allowance = AllowancesStorage::<Runtime>::get(
+			Runtime::AddressMapping::into_account_id(owner),
+			Runtime::AddressMapping::into_account_id(spender),
+		);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(allowance).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn transfer(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+		context: &Context,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(2)?;
+		let to: H160 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+
+		let log = Self::transfer_log(context, context.caller, to, amount)?;
+		gasometer.record_cost(log_cost(&log))?;
+		Self::dispatch_transfer(context.caller, to, amount, &mut gasometer)?;
+		deposit_log(log);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(true).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn approve(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+		context: &Context,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(2)?;
+		let spender: H160 = input.read()?;
+		let amount: U256 = input.read()?;
+
+		let log = log3(
+			context.address,
+			SELECTOR_LOG_APPROVAL,
+			context.caller,
+			spender,
+			EvmDataWriter::new().write(amount).build(),
+		);
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_write_gas_cost())?;
+		gasometer.record_cost(log_cost(&log))?;
+
+		let owner = Runtime::AddressMapping::into_account_id(context.caller);
+		let spender = Runtime::AddressMapping::into_account_id(spender);
+		log::trace!(
+			target: "erc20-precompile",
+			"{:?} allows {:?} to spend {:?}",
+			owner,
+			spender,
+			amount
+		);
+
+		// Zero allowances are not kept in storage.
+		if amount.is_zero() {
+			AllowancesStorage::<Runtime>::remove(owner, spender);
+		} else {
+			AllowancesStorage::<Runtime>::insert(owner, spender, amount);
+		}
+		deposit_log(log);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(true).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn transfer_from(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+		context: &Context,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(3)?;
+		let from: H160 = input.read()?;
+		let to: H160 = input.read()?;
+		let amount_word: U256 = input.read()?;
+		let amount: BalanceOf<Runtime> = amount_word
+			.try_into()
+			.map_err(|_| error("Amount is too large for provided balance type"))?;
+
+		let log = Self::transfer_log(context, from, to, amount)?;
+		gasometer.record_cost(log_cost(&log))?;
+
+		// The new allowance is only written once the transfer succeeded.
+		let new_allowance = if from != context.caller {
+			gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+			let owner = Runtime::AddressMapping::into_account_id(from);
+			let spender = Runtime::AddressMapping::into_account_id(context.caller);
+			let allowance = AllowancesStorage::<Runtime>::get(&owner, &spender);
+
+			if allowance == U256::max_value() {
+				None
+			} else {
+				let new_allowance = allowance
+					.checked_sub(amount_word)
+					.ok_or_else(|| error("trying to spend more than allowed"))?;
+				gasometer.record_cost(RuntimeHelper::<Runtime>::db_write_gas_cost())?;
+				Some((owner, spender, new_allowance))
+			}
+		} else {
+			None
+		};
+
+		Self::dispatch_transfer(from, to, amount, &mut gasometer)?;
+
+		if let Some((owner, spender, new_allowance)) = new_allowance {
+			if new_allowance.is_zero() {
+				AllowancesStorage::<Runtime>::remove(owner, spender);
+			} else {
+				AllowancesStorage::<Runtime>::insert(owner, spender, new_allowance);
+			}
+		}
+		deposit_log(log);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(true).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	/// Dispatch a pallet_balances transfer on behalf of `from`.
+	fn dispatch_transfer(
+		from: H160,
+		to: H160,
+		amount: BalanceOf<Runtime>,
+		gasometer: &mut Gasometer,
+	) -> EvmResult {
+		let origin = Runtime::AddressMapping::into_account_id(from);
+		let dest = Runtime::Lookup::unlookup(Runtime::AddressMapping::into_account_id(to));
+
+		log::trace!(
+			target: "erc20-precompile",
+			"Transfer of {:?} from {:?} to {:?}",
+			amount,
+			origin,
+			to
+		);
+
+		RuntimeHelper::<Runtime>::try_dispatch(
+			Some(origin).into(),
+			pallet_balances::Call::<Runtime>::transfer(dest, amount),
+			gasometer,
+		)
+	}
+
+	/// Log of the `Transfer` event.
+	fn transfer_log(
+		context: &Context,
+		from: H160,
+		to: H160,
+		amount: BalanceOf<Runtime>,
+	) -> EvmResult<evm::backend::Log> {
+		Ok(log3(
+			context.address,
+			SELECTOR_LOG_TRANSFER,
+			from,
+			to,
+			EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+		))
+	}
+}
@@ -16,26 +16,48 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+mod batch;
+mod democracy;
+mod dispatch;
+mod erc20;
 mod staking;
+mod token_dealer;
+use batch::BatchWrapper;
 use codec::Decode;
+use democracy::DemocracyWrapper;
+use dispatch::FilteredDispatch;
+use erc20::NativeErc20Wrapper;
 use evm::{Context, ExitError, ExitSucceed};
 use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
 use pallet_evm::{Precompile, PrecompileSet};
 use pallet_evm_precompile_bn128::{Bn128Add, Bn128Mul, Bn128Pairing};
-use pallet_evm_precompile_dispatch::Dispatch;
 use pallet_evm_precompile_modexp::Modexp;
 use pallet_evm_precompile_sha3fips::Sha3FIPS256;
 use pallet_evm_precompile_simple::{ECRecover, Identity, Ripemd160, Sha256};
-use sp_core::H160;
-use sp_std::convert::TryFrom;
+use precompile_utils::{error, EvmDataReader, EvmResult};
+use sp_core::{H160, H256, U256};
+use sp_std::convert::{TryFrom, TryInto};
 use sp_std::fmt::Debug;
 use sp_std::{marker::PhantomData, vec::Vec};
 use staking::ParachainStakingWrapper;
+use token_dealer::TokenDealerWrapper;
+
+pub use dispatch::DispatchFilter;
+pub use erc20::{AllowancesStorage, Erc20Metadata};
 
 use frame_support::traits::Currency;
 type BalanceOf<Runtime> = <<Runtime as parachain_staking::Config>::Currency as Currency<
 	<Runtime as frame_system::Config>::AccountId,
 >>::Balance;
+type DemocracyBalanceOf<Runtime> = <<Runtime as pallet_democracy::Config>::Currency as Currency<
+	<Runtime as frame_system::Config>::AccountId,
+>>::Balance;
+type TokenDealerBalanceOf<Runtime> =
+	<<Runtime as pallet_token_dealer::Config>::Currency as Currency<
+		<Runtime as frame_system::Config>::AccountId,
+	>>::Balance;
 
 //TODO Maybe we don't need to / shouldn't be generic over the runtime.
 // Pros: Would simplify trait bounds and speed up compile time (maybe not noticeably).
@@ -43,7 +65,8 @@ type BalanceOf<Runtime> = <<Runtime as parachain_staking::Config>::Currency as C
 /// The PrecompileSet installed in the Thales runtime.
 /// We include the nine Istanbul precompiles
 /// (https://github.com/ethereum/go-ethereum/blob/3c46f557/core/vm/contracts.go#L69)
-/// as well as a special precompile for dispatching Substrate extrinsics
+/// as well as a special precompile for dispatching the Substrate extrinsics allowed by the
+/// runtime `DispatchFilter`
 #[derive(Debug, Clone, Copy)]
 pub struct ThalesPrecompiles<R>(PhantomData<R>);
 
@@ -53,12 +76,11 @@ impl<R: frame_system::Config> ThalesPrecompiles<R>
 where
 	R::AccountId: From<H160>,
 {
-	/// Return all addresses that contain precompiles. This can be used to populate dummy code
-	/// under the precompile, and potentially in the future to prevent using accounts that have
-	/// precompiles at their addresses explicitly using something like SignedExtra.
-	#[allow(dead_code)]
-	fn used_addresses() -> impl Iterator<Item = R::AccountId> {
-		sp_std::vec![1, 2, 3, 4, 5, 6, 7, 8, 1024, 1025, 2048]
+	/// Return all addresses that contain precompiles. This is used to populate dummy code under
+	/// the precompiles at genesis, and potentially in the future to prevent using accounts that
+	/// have precompiles at their addresses explicitly using something like SignedExtra.
+	pub fn used_addresses() -> impl Iterator<Item = R::AccountId> {
+		sp_std::vec![1, 2, 3, 4, 5, 6, 7, 8, 1024, 1025, 2048, 2049, 2050, 2051, 2052]
 			.into_iter()
 			.map(|x| hash(x).into())
 	}
@@ -72,10 +94,17 @@ impl<R> PrecompileSet for ThalesPrecompiles<R>
 where
 	R::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
 	<R::Call as Dispatchable>::Origin: From<Option<R::AccountId>>,
-	R: parachain_staking::Config + pallet_evm::Config,
-	R::AccountId: From<H160>,
-	BalanceOf<R>: TryFrom<sp_core::U256> + Debug,
-	R::Call: From<parachain_staking::Call<R>>,
+	R: parachain_staking::Config + pallet_balances::Config + pallet_democracy::Config,
+	R: pallet_token_dealer::Config,
+	R: pallet_evm::Config + Erc20Metadata + DispatchFilter,
+	R::AccountId: From<H160> + Into<H160>,
+	R::Hash: From<H256> + Into<H256>,
+	BalanceOf<R>: TryFrom<U256> + TryInto<u128> + Debug,
+	<R as pallet_balances::Config>::Balance: TryFrom<U256> + TryInto<u128> + Debug,
+	DemocracyBalanceOf<R>: TryFrom<U256> + TryInto<u128> + Debug,
+	TokenDealerBalanceOf<R>: TryFrom<U256> + TryInto<u128> + Debug,
+	R::Call: From<parachain_staking::Call<R>> + From<pallet_balances::Call<R>>,
+	R::Call: From<pallet_democracy::Call<R>> + From<pallet_token_dealer::Call<R>>,
 {
 	fn execute(
 		address: H160,
@@ -94,17 +123,48 @@ where
 			a if a == hash(7) => Some(Bn128Mul::execute(input, target_gas, context)),
 			a if a == hash(8) => Some(Bn128Pairing::execute(input, target_gas, context)),
 			// Non-Thales specific nor Ethereum precompiles :
-			a if a == hash(1024) => Some(Dispatch::<R>::execute(input, target_gas, context)),
+			a if a == hash(1024) => Some(FilteredDispatch::<R>::execute(
+				input, target_gas, context,
+			)),
 			a if a == hash(1025) => Some(Sha3FIPS256::execute(input, target_gas, context)),
 			// Thales specific precompiles :
 			a if a == hash(2048) => Some(ParachainStakingWrapper::<R>::execute(
 				input, target_gas, context,
 			)),
+			a if a == hash(2049) => Some(NativeErc20Wrapper::<R>::execute(
+				input, target_gas, context,
+			)),
+			a if a == hash(2050) => Some(DemocracyWrapper::<R>::execute(
+				input, target_gas, context,
+			)),
+			a if a == hash(2051) => Some(BatchWrapper::<R>::execute(input, target_gas, context)),
+			a if a == hash(2052) => Some(TokenDealerWrapper::<R>::execute(
+				input, target_gas, context,
+			)),
 			_ => None,
 		}
 	}
 }
 
+/// Converts a balance of the generic type to a Solidity uint256.
+fn balance_to_u256<Balance: TryInto<u128>>(balance: Balance) -> EvmResult<U256> {
+	let balance: u128 = balance
+		.try_into()
+		.map_err(|_| error("Amount is too large for provided balance type"))?;
+	Ok(balance.into())
+}
+
+/// Parses an amount of ether from the input. The balance type is generic.
+fn read_amount<Balance>(input: &mut EvmDataReader) -> EvmResult<Balance>
+where
+	Balance: TryFrom<U256>,
+{
+	input
+		.read::<U256>()?
+		.try_into()
+		.map_err(|_| error("Amount is too large for provided balance type"))
+}
+
 fn hash(a: u64) -> H160 {
 	H160::from_low_u64_be(a)
 }
@@ -15,12 +15,16 @@
 // along with Thales.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Precompile to call parachain-staking runtime methods via the EVM
-use evm::{Context, ExitError, ExitSucceed};
+use crate::{balance_to_u256, hash, read_amount};
+use evm::{backend::Log, Context, ExitError, ExitSucceed};
 use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
 use frame_support::traits::{Currency, Get};
 use pallet_evm::AddressMapping;
-use pallet_evm::GasWeightMapping;
 use pallet_evm::Precompile;
+use precompile_utils::{
+	deposit_log, error, generate_function_selector, keccak256, log2, log3, log_cost,
+	EvmDataReader, EvmDataWriter, EvmResult, Gasometer, RuntimeHelper,
+};
 use sp_core::H160;
 use sp_core::U256;
 use sp_std::convert::TryFrom;
@@ -32,6 +36,57 @@ type BalanceOf<Runtime> = <<Runtime as parachain_staking::Config>::Currency as C
 	<Runtime as frame_system::Config>::AccountId,
 >>::Balance;
 
+/// Solidity selectors of the functions of the StakingInterface.sol, computed from their
+/// signatures according to the solidity specification
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+#[generate_function_selector]
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	IsNominator = "is_nominator(address)",
+	IsCandidate = "is_candidate(address)",
+	MinNomination = "min_nomination()",
+	CandidateTotal = "candidate_total(address)",
+	CandidateBond = "candidate_bond(address)",
+	CollatorNominationCount = "collator_nomination_count(address)",
+	NominationAmount = "nomination_amount(address,address)",
+	NominatorTotal = "nominator_total(address)",
+	SelectedCandidates = "selected_candidates()",
+	Round = "round()",
+	RoundFirstBlock = "round_first_block()",
+	Points = "points(uint256)",
+	AwardedPoints = "awarded_points(uint256,address)",
+	CollatorCommission = "collator_commission()",
+	JoinCandidates = "join_candidates(uint256)",
+	LeaveCandidates = "leave_candidates()",
+	GoOffline = "go_offline()",
+	GoOnline = "go_online()",
+	CandidateBondLess = "candidate_bond_less(uint256)",
+	CandidateBondMore = "candidate_bond_more(uint256)",
+	Nominate = "nominate(address,uint256)",
+	LeaveNominators = "leave_nominators()",
+	RevokeNomination = "revoke_nomination(address)",
+	NominatorBondLess = "nominator_bond_less(address,uint256)",
+	NominatorBondMore = "nominator_bond_more(address,uint256)",
+}
+
+/// Topics of the events of the StakingInterface.sol, emitted as EVM logs by the dispatchables.
+/// The first topic of an event is the Keccak-256 hash of its signature.
+const SELECTOR_LOG_JOINED_CANDIDATES: [u8; 32] = keccak256!("JoinedCandidates(address,uint256)");
+const SELECTOR_LOG_LEFT_CANDIDATES: [u8; 32] = keccak256!("LeftCandidates(address)");
+const SELECTOR_LOG_WENT_OFFLINE: [u8; 32] = keccak256!("WentOffline(address)");
+const SELECTOR_LOG_WENT_ONLINE: [u8; 32] = keccak256!("WentOnline(address)");
+const SELECTOR_LOG_CANDIDATE_BONDED_MORE: [u8; 32] =
+	keccak256!("CandidateBondedMore(address,uint256)");
+const SELECTOR_LOG_CANDIDATE_BONDED_LESS: [u8; 32] =
+	keccak256!("CandidateBondedLess(address,uint256)");
+const SELECTOR_LOG_NOMINATED: [u8; 32] = keccak256!("Nominated(address,address,uint256)");
+const SELECTOR_LOG_LEFT_NOMINATORS: [u8; 32] = keccak256!("LeftNominators(address)");
+const SELECTOR_LOG_NOMINATION_REVOKED: [u8; 32] = keccak256!("NominationRevoked(address,address)");
+const SELECTOR_LOG_NOMINATION_INCREASED: [u8; 32] =
+	keccak256!("NominationIncreased(address,address,uint256)");
+const SELECTOR_LOG_NOMINATION_DECREASED: [u8; 32] =
+	keccak256!("NominationDecreased(address,address,uint256)");
+
 /// A precompile to wrap the functionality from parachain_staking.
 ///
 /// EXAMPLE USECASE:
@@ -44,8 +99,8 @@ pub struct ParachainStakingWrapper<Runtime>(PhantomData<Runtime>);
 impl<Runtime> Precompile for ParachainStakingWrapper<Runtime>
 where
 	Runtime: parachain_staking::Config + pallet_evm::Config,
-	Runtime::AccountId: From<H160>,
-	BalanceOf<Runtime>: TryFrom<U256> + Debug,
+	Runtime::AccountId: From<H160> + Into<H160>,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
 	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
 	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
 	Runtime::Call: From<parachain_staking::Call<Runtime>>,
@@ -56,148 +111,70 @@ where
 		context: &Context,
 	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
 		log::trace!(target: "staking-precompile", "In parachain staking wrapper");
+		log::trace!(target: "staking-precompile", "context.caller is {:?}", context.caller);
 
-		// Basic sanity checking for length
-		// https://solidity-by-example.org/primitives/
-
-		const SELECTOR_SIZE_BYTES: usize = 4;
-
-		if input.len() < 4 {
-			return Err(ExitError::Other("input length less than 4 bytes".into()));
+		// Through DELEGATECALL or CALLCODE, the calling contract would stake with the funds of its
+		// own caller and the staking logs would be emitted in its name.
+		if context.address != hash(2048) {
+			return Err(error("Cannot be called with DELEGATECALL or CALLCODE"));
 		}
 
-		log::trace!(target: "staking-precompile", "Made it past preliminary length check");
-		log::trace!(target: "staking-precompile", "context.caller is {:?}", context.caller);
+		let mut input = EvmDataReader::new(input);
+		let mut gasometer = Gasometer::new(target_gas);
 
-		// Parse the function selector
-		// These are the four-byte function selectors calculated from the StakingInterface.sol
-		// according to the solidity specification
-		// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
-		let inner_call = match input[0..SELECTOR_SIZE_BYTES] {
+		let (inner_call, log) = match input.read_selector()? {
 			// Check for accessor methods first. These return results immediately
-			[0x8e, 0x50, 0x80, 0xe7] => {
-				return Self::is_nominator(&input[SELECTOR_SIZE_BYTES..]);
-			}
-			[0x85, 0x45, 0xc8, 0x33] => {
-				return Self::is_candidate(&input[SELECTOR_SIZE_BYTES..]);
-			}
-			// c9f593b2
-			[0xc9, 0xf5, 0x93, 0xb2] => {
-				return Self::min_nomination();
-			}
-
-			// If not an accessor, check for dispatchables. These calls ready for dispatch below.
-			[0xad, 0x76, 0xed, 0x5a] => Self::join_candidates(&input[SELECTOR_SIZE_BYTES..])?,
-			[0xb7, 0x69, 0x42, 0x19] => Self::leave_candidates()?,
-			[0x76, 0x7e, 0x04, 0x50] => Self::go_offline()?,
-			[0xd2, 0xf7, 0x3c, 0xeb] => Self::go_online()?,
-			[0x28, 0x9b, 0x6b, 0xa7] => Self::candidate_bond_less(&input[SELECTOR_SIZE_BYTES..])?,
-			[0xc5, 0x7b, 0xd3, 0xa8] => Self::candidate_bond_more(&input[SELECTOR_SIZE_BYTES..])?,
-			[0x82, 0xf2, 0xc8, 0xdf] => Self::nominate(&input[SELECTOR_SIZE_BYTES..])?,
-			[0xe8, 0xd6, 0x8a, 0x37] => Self::leave_nominators()?,
-			[0x4b, 0x65, 0xc3, 0x4b] => Self::revoke_nomination(&input[SELECTOR_SIZE_BYTES..])?,
-			[0xf6, 0xa5, 0x25, 0x69] => Self::nominator_bond_less(&input[SELECTOR_SIZE_BYTES..])?,
-			[0x97, 0x1d, 0x44, 0xc8] => Self::nominator_bond_more(&input[SELECTOR_SIZE_BYTES..])?,
-			_ => {
-				log::trace!(
-					target: "staking-precompile",
-					"Failed to match function selector in staking wrapper precompile"
-				);
-				return Err(ExitError::Other(
-					"No staking wrapper method at selector given selector".into(),
-				));
+			Action::IsNominator => return Self::is_nominator(input, gasometer),
+			Action::IsCandidate => return Self::is_candidate(input, gasometer),
+			Action::MinNomination => return Self::min_nomination(gasometer),
+			Action::CandidateTotal => return Self::candidate_total(input, gasometer),
+			Action::CandidateBond => return Self::candidate_bond(input, gasometer),
+			Action::CollatorNominationCount => {
+				return Self::collator_nomination_count(input, gasometer)
 			}
+			Action::NominationAmount => return Self::nomination_amount(input, gasometer),
+			Action::NominatorTotal => return Self::nominator_total(input, gasometer),
+			Action::SelectedCandidates => return Self::selected_candidates(gasometer),
+			Action::Round => return Self::round(gasometer),
+			Action::RoundFirstBlock => return Self::round_first_block(gasometer),
+			Action::Points => return Self::points(input, gasometer),
+			Action::AwardedPoints => return Self::awarded_points(input, gasometer),
+			Action::CollatorCommission => return Self::collator_commission(gasometer),
+
+			// If not an accessor, check for dispatchables. These calls ready for dispatch below,
+			// along with the log to emit if they succeed.
+			Action::JoinCandidates => Self::join_candidates(input, context)?,
+			Action::LeaveCandidates => Self::leave_candidates(context)?,
+			Action::GoOffline => Self::go_offline(context)?,
+			Action::GoOnline => Self::go_online(context)?,
+			Action::CandidateBondLess => Self::candidate_bond_less(input, context)?,
+			Action::CandidateBondMore => Self::candidate_bond_more(input, context)?,
+			Action::Nominate => Self::nominate(input, context)?,
+			Action::LeaveNominators => Self::leave_nominators(context)?,
+			Action::RevokeNomination => Self::revoke_nomination(input, context)?,
+			Action::NominatorBondLess => Self::nominator_bond_less(input, context)?,
+			Action::NominatorBondMore => Self::nominator_bond_more(input, context)?,
 		};
 
-		let outer_call: Runtime::Call = inner_call.into();
-		let info = outer_call.get_dispatch_info();
-
-		// Make sure enough gas
-		if let Some(gas_limit) = target_gas {
-			let required_gas = Runtime::GasWeightMapping::weight_to_gas(info.weight);
-			if required_gas > gas_limit {
-				return Err(ExitError::OutOfGas);
-			}
-		}
-		log::trace!(target: "staking-precompile", "Made it past gas check");
-
 		// Dispatch that call
 		let origin = Runtime::AddressMapping::into_account_id(context.caller);
 
 		log::trace!(target: "staking-precompile", "Gonna call with origin {:?}", origin);
 
-		match outer_call.dispatch(Some(origin).into()) {
-			Ok(post_info) => {
-				let gas_used = Runtime::GasWeightMapping::weight_to_gas(
-					post_info.actual_weight.unwrap_or(info.weight),
-				);
-				Ok((ExitSucceed::Stopped, Default::default(), gas_used))
-			}
-			Err(e) => {
-				log::trace!(
-					target: "staking-precompile",
-					"Parachain staking call via evm failed {:?}",
-					e
-				);
-				Err(ExitError::Other(
-					"Parachain staking call via EVM failed".into(),
-				))
-			}
-		}
-	}
-}
-
-/// Parses an H160 account address from a 256 bit (32 byte) buffer. Only the last 20 bytes are used.
-fn parse_account(input: &[u8]) -> Result<H160, ExitError> {
-	const PADDING_SIZE_BYTES: usize = 12;
-	const ACCOUNT_SIZE_BYTES: usize = 20;
-	const TOTAL_SIZE_BYTES: usize = PADDING_SIZE_BYTES + ACCOUNT_SIZE_BYTES;
-
-	if input.len() != TOTAL_SIZE_BYTES {
-		log::trace!(target: "staking-precompile",
-			"Unable to parse address. Got {} bytes, expected {}",
-			input.len(),
-			TOTAL_SIZE_BYTES,
-		);
-		return Err(ExitError::Other(
-			"Incorrect input length for account parsing".into(),
-		));
-	}
+		gasometer.record_cost(log_cost(&log))?;
+		RuntimeHelper::<Runtime>::try_dispatch(Some(origin).into(), inner_call, &mut gasometer)?;
 
-	Ok(H160::from_slice(
-		&input[PADDING_SIZE_BYTES..TOTAL_SIZE_BYTES],
-	))
-}
+		// The log is only emitted once the call succeeded.
+		deposit_log(log);
 
-/// Parses an amount of ether from a 256 bit (32 byte) slice. The balance type is generic.
-fn parse_amount<Balance>(input: &[u8]) -> Result<Balance, ExitError>
-where
-	Balance: TryFrom<U256>,
-{
-	// In solidity all values are encoded to this width
-	const AMOUNT_SIZE_BYTES: usize = 32;
-
-	if input.len() != AMOUNT_SIZE_BYTES {
-		log::trace!(target: "staking-precompile",
-			"Unable to parse amount. Got {} bytes, expected {}",
-			input.len(),
-			AMOUNT_SIZE_BYTES,
-		);
-		return Err(ExitError::Other(
-			"Incorrect input length for amount parsing".into(),
-		));
+		Ok((ExitSucceed::Stopped, Default::default(), gasometer.used_gas()))
 	}
-
-	let amount: Balance = U256::from_big_endian(&input[0..AMOUNT_SIZE_BYTES])
-		.try_into()
-		.map_err(|_| ExitError::Other("Amount is too large for provided balance type".into()))?;
-	Ok(amount)
 }
 
 impl<Runtime> ParachainStakingWrapper<Runtime>
 where
 	Runtime: parachain_staking::Config + pallet_evm::Config,
-	Runtime::AccountId: From<H160>,
+	Runtime::AccountId: From<H160> + Into<H160>,
 	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
 	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
 	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
@@ -205,9 +182,13 @@ where
 {
 	// The accessors are first. They directly return their result.
 
-	fn is_nominator(input: &[u8]) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+	fn is_nominator(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
 		// parse the address
-		let nominator = H160::from_slice(&input[12..32]);
+		input.expect_arguments(1)?;
+		let nominator: H160 = input.read()?;
 
 		log::trace!(
 			target: "staking-precompile",
@@ -216,28 +197,25 @@ where
 		);
 
 		// fetch data from pallet
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
 		let is_nominator = parachain_staking::Pallet::<Runtime>::is_nominator(&nominator.into());
 
 		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", is_nominator);
 
-		// Solidity's bool type is 256 bits as shown by these examples
-		// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html
-		let mut result_bytes = [0u8; 32];
-		if is_nominator {
-			result_bytes[31] = 1;
-		}
-
-		log::trace!(target: "staking-precompile", "Result bytes are {:?}", result_bytes);
-
-		// TODO find gas cost of single storage read
-		let gas_consumed = 0;
-
-		return Ok((ExitSucceed::Returned, result_bytes.to_vec(), gas_consumed));
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(is_nominator).build(),
+			gasometer.used_gas(),
+		))
 	}
 
-	fn is_candidate(input: &[u8]) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+	fn is_candidate(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
 		// parse the address
-		let candidate = H160::from_slice(&input[12..32]);
+		input.expect_arguments(1)?;
+		let candidate: H160 = input.read()?;
 
 		log::trace!(
 			target: "staking-precompile",
@@ -246,141 +224,428 @@ where
 		);
 
 		// fetch data from pallet
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
 		let is_candidate = parachain_staking::Pallet::<Runtime>::is_candidate(&candidate.into());
 
 		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", is_candidate);
 
-		// Solidity's bool type is 256 bits as shown by these examples
-		// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html
-		let mut result_bytes = [0u8; 32];
-		if is_candidate {
-			result_bytes[31] = 1;
-		}
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(is_candidate).build(),
+			gasometer.used_gas(),
+		))
+	}
 
-		log::trace!(target: "staking-precompile", "Result bytes are {:?}", result_bytes);
+	fn min_nomination(gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		// fetch data from pallet. This is a constant of the runtime, no storage is read.
+		let min_nomination =
+			balance_to_u256(<Runtime as parachain_staking::Config>::MinNomination::get())?;
 
-		// TODO find gas cost of single storage read
-		let gas_consumed = 0;
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", min_nomination);
 
-		return Ok((ExitSucceed::Returned, result_bytes.to_vec(), gas_consumed));
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(min_nomination).build(),
+			gasometer.used_gas(),
+		))
 	}
 
-	fn min_nomination() -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
-		// fetch data from pallet
-		let raw_min_nomination: u128 = <
-			<Runtime as parachain_staking::Config>::MinNomination
-				as Get<BalanceOf<Runtime>>
-			>::get().try_into()
-				.map_err(|_|
-					ExitError::Other("Amount is too large for provided balance type".into())
-				)?;
-		let min_nomination: U256 = raw_min_nomination.into();
+	fn candidate_total(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let candidate: H160 = input.read()?;
+
+		// The total is zero for accounts which are not candidates.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let total = parachain_staking::Pallet::<Runtime>::collator_state(&candidate.into())
+			.map(|state| state.total)
+			.unwrap_or_default();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", total);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(total)?).build(),
+			gasometer.used_gas(),
+		))
+	}
 
-		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", min_nomination);
-		// TODO find cost of Config associated type read
-		let gas_consumed = 0;
+	fn candidate_bond(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let candidate: H160 = input.read()?;
+
+		// The bond is zero for accounts which are not candidates.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let bond = parachain_staking::Pallet::<Runtime>::collator_state(&candidate.into())
+			.map(|state| state.bond)
+			.unwrap_or_default();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", bond);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(bond)?).build(),
+			gasometer.used_gas(),
+		))
+	}
 
-		let mut buffer = [0u8; 32];
-		min_nomination.to_big_endian(&mut buffer);
+	fn collator_nomination_count(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let collator: H160 = input.read()?;
+
+		// The count is zero for accounts which are not candidates.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let count = parachain_staking::Pallet::<Runtime>::collator_state(&collator.into())
+			.map(|state| state.nominators.0.len())
+			.unwrap_or_default();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", count);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(U256::from(count)).build(),
+			gasometer.used_gas(),
+		))
+	}
 
-		return Ok((ExitSucceed::Returned, buffer.to_vec(), gas_consumed));
+	fn nomination_amount(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(2)?;
+		let nominator: H160 = input.read()?;
+		let collator: Runtime::AccountId = input.read::<H160>()?.into();
+
+		// The amount is zero if there is no such nomination.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let amount = parachain_staking::Pallet::<Runtime>::nominator_state(&nominator.into())
+			.and_then(|state| {
+				state
+					.nominations
+					.0
+					.into_iter()
+					.find(|nomination| nomination.owner == collator)
+			})
+			.map(|nomination| nomination.amount)
+			.unwrap_or_default();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", amount);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			gasometer.used_gas(),
+		))
 	}
 
-	// The dispatchable wrappers are next. They return a substrate inner Call ready for dispatch.
+	fn nominator_total(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let nominator: H160 = input.read()?;
+
+		// The total is zero for accounts which are not nominators.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let total = parachain_staking::Pallet::<Runtime>::nominator_state(&nominator.into())
+			.map(|state| state.total)
+			.unwrap_or_default();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", total);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(balance_to_u256(total)?).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn selected_candidates(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let selected: Vec<H160> = parachain_staking::Pallet::<Runtime>::selected_candidates()
+			.into_iter()
+			.map(Into::into)
+			.collect();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", selected);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(selected).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn round(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let round = parachain_staking::Pallet::<Runtime>::round().current;
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", round);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(round).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn round_first_block(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let first: u128 = parachain_staking::Pallet::<Runtime>::round()
+			.first
+			.try_into()
+			.map_err(|_| error("Block number is too large"))?;
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", first);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(first).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn points(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(1)?;
+		let round: u32 = input.read()?;
+
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let points = parachain_staking::Pallet::<Runtime>::points(round);
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", points);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(points).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn awarded_points(
+		mut input: EvmDataReader,
+		mut gasometer: Gasometer,
+	) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		input.expect_arguments(2)?;
+		let round: u32 = input.read()?;
+		let collator: H160 = input.read()?;
 
-	fn join_candidates(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		let amount = parse_amount::<BalanceOf<Runtime>>(input)?;
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let points = parachain_staking::Pallet::<Runtime>::awarded_pts(round, &collator.into());
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", points);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(points).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	fn collator_commission(mut gasometer: Gasometer) -> EvmResult<(ExitSucceed, Vec<u8>, u64)> {
+		// The commission is returned in parts per billion.
+		gasometer.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let commission = parachain_staking::Pallet::<Runtime>::collator_commission().deconstruct();
+
+		log::trace!(target: "staking-precompile", "Result from pallet is {:?}", commission);
+
+		Ok((
+			ExitSucceed::Returned,
+			EvmDataWriter::new().write(commission).build(),
+			gasometer.used_gas(),
+		))
+	}
+
+	// The dispatchable wrappers are next. They return a substrate inner Call ready for dispatch,
+	// and the log of the StakingInterface.sol event it emits.
+
+	fn join_candidates(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		input.expect_arguments(1)?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator stake amount is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::join_candidates(amount))
+		Ok((
+			parachain_staking::Call::<Runtime>::join_candidates(amount),
+			log2(
+				context.address,
+				SELECTOR_LOG_JOINED_CANDIDATES,
+				context.caller,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
+		))
 	}
 
-	fn leave_candidates() -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		Ok(parachain_staking::Call::<Runtime>::leave_candidates())
+	fn leave_candidates(context: &Context) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		Ok((
+			parachain_staking::Call::<Runtime>::leave_candidates(),
+			log2(context.address, SELECTOR_LOG_LEFT_CANDIDATES, context.caller, Vec::new()),
+		))
 	}
 
-	fn go_offline() -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		Ok(parachain_staking::Call::<Runtime>::go_offline())
+	fn go_offline(context: &Context) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		Ok((
+			parachain_staking::Call::<Runtime>::go_offline(),
+			log2(context.address, SELECTOR_LOG_WENT_OFFLINE, context.caller, Vec::new()),
+		))
 	}
 
-	fn go_online() -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		Ok(parachain_staking::Call::<Runtime>::go_online())
+	fn go_online(context: &Context) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		Ok((
+			parachain_staking::Call::<Runtime>::go_online(),
+			log2(context.address, SELECTOR_LOG_WENT_ONLINE, context.caller, Vec::new()),
+		))
 	}
 
-	fn candidate_bond_more(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		let amount = parse_amount::<BalanceOf<Runtime>>(input)?;
+	fn candidate_bond_more(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		input.expect_arguments(1)?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator bond increment is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::candidate_bond_more(
-			amount,
+		Ok((
+			parachain_staking::Call::<Runtime>::candidate_bond_more(amount),
+			log2(
+				context.address,
+				SELECTOR_LOG_CANDIDATE_BONDED_MORE,
+				context.caller,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
 		))
 	}
 
-	fn candidate_bond_less(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		let amount = parse_amount::<BalanceOf<Runtime>>(input)?;
+	fn candidate_bond_less(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		input.expect_arguments(1)?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator bond decrement is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::candidate_bond_less(
-			amount,
+		Ok((
+			parachain_staking::Call::<Runtime>::candidate_bond_less(amount),
+			log2(
+				context.address,
+				SELECTOR_LOG_CANDIDATE_BONDED_LESS,
+				context.caller,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
 		))
 	}
 
-	fn nominate(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
+	fn nominate(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
 		log::trace!(target: "staking-precompile", "In nominate dispatchable wrapper");
-		log::trace!(target: "staking-precompile", "input is {:?}", input);
-		let collator = parse_account(&input[..32])?;
-		let amount = parse_amount::<BalanceOf<Runtime>>(&input[32..])?;
+		input.expect_arguments(2)?;
+		let collator: H160 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator account is {:?}", collator);
 		log::trace!(target: "staking-precompile", "Nomination amount is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::nominate(
-			collator.into(),
-			amount,
+		Ok((
+			parachain_staking::Call::<Runtime>::nominate(collator.into(), amount),
+			log3(
+				context.address,
+				SELECTOR_LOG_NOMINATED,
+				context.caller,
+				collator,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
 		))
 	}
 
-	fn leave_nominators() -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		Ok(parachain_staking::Call::<Runtime>::leave_nominators())
+	fn leave_nominators(context: &Context) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		Ok((
+			parachain_staking::Call::<Runtime>::leave_nominators(),
+			log2(context.address, SELECTOR_LOG_LEFT_NOMINATORS, context.caller, Vec::new()),
+		))
 	}
 
-	fn revoke_nomination(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
+	fn revoke_nomination(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
 		log::trace!(target: "staking-precompile", "In revoke nomination dispatchable wrapper");
-		let collator = parse_account(&input[..32])?;
+		input.expect_arguments(1)?;
+		let collator: H160 = input.read()?;
 
 		log::trace!(target: "staking-precompile", "Collator account is {:?}", collator);
 
-		Ok(parachain_staking::Call::<Runtime>::revoke_nomination(
-			collator.into(),
+		Ok((
+			parachain_staking::Call::<Runtime>::revoke_nomination(collator.into()),
+			log3(
+				context.address,
+				SELECTOR_LOG_NOMINATION_REVOKED,
+				context.caller,
+				collator,
+				Vec::new(),
+			),
 		))
 	}
 
-	fn nominator_bond_more(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		let collator = parse_account(&input[..32])?;
-		let amount = parse_amount::<BalanceOf<Runtime>>(&input[32..])?;
+	fn nominator_bond_more(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		input.expect_arguments(2)?;
+		let collator: H160 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator account is {:?}", collator);
 		log::trace!(target: "staking-precompile", "Nomination increment is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::nominator_bond_more(
-			collator.into(),
-			amount,
+		Ok((
+			parachain_staking::Call::<Runtime>::nominator_bond_more(collator.into(), amount),
+			log3(
+				context.address,
+				SELECTOR_LOG_NOMINATION_INCREASED,
+				context.caller,
+				collator,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
 		))
 	}
 
-	fn nominator_bond_less(input: &[u8]) -> Result<parachain_staking::Call<Runtime>, ExitError> {
-		let collator = parse_account(&input[..32])?;
-		let amount = parse_amount::<BalanceOf<Runtime>>(&input[32..])?;
+	fn nominator_bond_less(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(parachain_staking::Call<Runtime>, Log)> {
+		input.expect_arguments(2)?;
+		let collator: H160 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
 
 		log::trace!(target: "staking-precompile", "Collator account is {:?}", collator);
 		log::trace!(target: "staking-precompile", "Nomination decrement is {:?}", amount);
 
-		Ok(parachain_staking::Call::<Runtime>::nominator_bond_less(
-			collator.into(),
-			amount,
+		Ok((
+			parachain_staking::Call::<Runtime>::nominator_bond_less(collator.into(), amount),
+			log3(
+				context.address,
+				SELECTOR_LOG_NOMINATION_DECREASED,
+				context.caller,
+				collator,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
 		))
 	}
 }
@@ -0,0 +1,182 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Precompile to start cross-chain transfers of pallet-token-dealer via the EVM
+use crate::{balance_to_u256, hash, read_amount};
+use evm::{backend::Log, Context, ExitError, ExitSucceed};
+use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
+use frame_support::traits::Currency;
+use pallet_evm::{AddressMapping, Precompile};
+use pallet_token_dealer::RelayAccountId;
+use precompile_utils::{
+	deposit_log, error, generate_function_selector, keccak256, log3, log_cost, EvmDataReader,
+	EvmDataWriter, EvmResult, Gasometer, RuntimeHelper,
+};
+use sp_core::{H160, H256, U256};
+use sp_std::convert::{TryFrom, TryInto};
+use sp_std::fmt::Debug;
+use sp_std::{marker::PhantomData, vec::Vec};
+
+type BalanceOf<Runtime> = <<Runtime as pallet_token_dealer::Config>::Currency as Currency<
+	<Runtime as frame_system::Config>::AccountId,
+>>::Balance;
+
+/// Solidity selectors of the functions of the TokenDealerInterface.sol, computed from their
+/// signatures according to the solidity specification
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+#[generate_function_selector]
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+	TransferToRelayChain = "transfer_to_relay_chain(bytes32,uint256)",
+	TransferToParachain = "transfer_to_parachain(uint256,address,uint256)",
+}
+
+/// Topics of the events of the TokenDealerInterface.sol, emitted as EVM logs by the transfers.
+/// The first topic of an event is the Keccak-256 hash of its signature.
+const SELECTOR_LOG_TRANSFERRED_TO_RELAY_CHAIN: [u8; 32] =
+	keccak256!("TransferredToRelayChain(address,bytes32,uint256)");
+const SELECTOR_LOG_TRANSFERRED_TO_PARACHAIN: [u8; 32] =
+	keccak256!("TransferredToParachain(address,uint256,address,uint256)");
+
+/// A precompile to wrap the functionality from pallet_token_dealer, so that contracts can send
+/// native tokens to the relay chain and to other parachains.
+pub struct TokenDealerWrapper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for TokenDealerWrapper<Runtime>
+where
+	Runtime: pallet_token_dealer::Config + pallet_evm::Config,
+	Runtime::AccountId: From<H160>,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_token_dealer::Call<Runtime>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		log::trace!(target: "token-dealer-precompile", "In token dealer wrapper");
+
+		// Through DELEGATECALL or CALLCODE, the calling contract could send the funds of its own
+		// caller to another chain.
+		if context.address != hash(2052) {
+			return Err(error("Cannot be called with DELEGATECALL or CALLCODE"));
+		}
+
+		let mut input = EvmDataReader::new(input);
+		let mut gasometer = Gasometer::new(target_gas);
+
+		// Both functions are dispatchables, ready for dispatch below along with the log to emit
+		// if they succeed.
+		let (inner_call, log) = match input.read_selector()? {
+			Action::TransferToRelayChain => Self::transfer_to_relay_chain(input, context)?,
+			Action::TransferToParachain => Self::transfer_to_parachain(input, context)?,
+		};
+
+		// Dispatch that call
+		let origin = Runtime::AddressMapping::into_account_id(context.caller);
+
+		log::trace!(target: "token-dealer-precompile", "Gonna call with origin {:?}", origin);
+
+		gasometer.record_cost(log_cost(&log))?;
+		RuntimeHelper::<Runtime>::try_dispatch(Some(origin).into(), inner_call, &mut gasometer)?;
+
+		// The log is only emitted once the call succeeded.
+		deposit_log(log);
+
+		Ok((ExitSucceed::Stopped, Default::default(), gasometer.used_gas()))
+	}
+}
+
+impl<Runtime> TokenDealerWrapper<Runtime>
+where
+	Runtime: pallet_token_dealer::Config + pallet_evm::Config,
+	Runtime::AccountId: From<H160>,
+	BalanceOf<Runtime>: TryFrom<U256> + TryInto<u128> + Debug,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<Runtime::Call as Dispatchable>::Origin: From<Option<Runtime::AccountId>>,
+	Runtime::Call: From<pallet_token_dealer::Call<Runtime>>,
+{
+	// The dispatchable wrappers return a substrate inner Call ready for dispatch, and the log of
+	// the TokenDealerInterface.sol event it emits.
+
+	fn transfer_to_relay_chain(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(pallet_token_dealer::Call<Runtime>, Log)> {
+		input.expect_arguments(2)?;
+		let destination: H256 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+
+		log::trace!(
+			target: "token-dealer-precompile",
+			"Sending {:?} to {:?} on the relay chain",
+			amount,
+			destination
+		);
+
+		Ok((
+			pallet_token_dealer::Call::<Runtime>::transfer_tokens_to_relay_chain(
+				RelayAccountId::new(destination.0),
+				amount,
+			),
+			log3(
+				context.address,
+				SELECTOR_LOG_TRANSFERRED_TO_RELAY_CHAIN,
+				context.caller,
+				destination,
+				EvmDataWriter::new().write(balance_to_u256(amount)?).build(),
+			),
+		))
+	}
+
+	fn transfer_to_parachain(
+		mut input: EvmDataReader,
+		context: &Context,
+	) -> EvmResult<(pallet_token_dealer::Call<Runtime>, Log)> {
+		input.expect_arguments(3)?;
+		let para_id: u32 = input.read()?;
+		let destination: H160 = input.read()?;
+		let amount = read_amount::<BalanceOf<Runtime>>(&mut input)?;
+
+		log::trace!(
+			target: "token-dealer-precompile",
+			"Sending {:?} to {:?} on parachain {:?}",
+			amount,
+			destination,
+			para_id
+		);
+
+		Ok((
+			pallet_token_dealer::Call::<Runtime>::transfer_tokens_to_parachain_chain(
+				para_id,
+				destination.0,
+				amount,
+			),
+			log3(
+				context.address,
+				SELECTOR_LOG_TRANSFERRED_TO_PARACHAIN,
+				context.caller,
+				H256::from_low_u64_be(para_id.into()),
+				EvmDataWriter::new()
+					.write(destination)
+					.write(balance_to_u256(amount)?)
+					.build(),
+			),
+		))
+	}
+}
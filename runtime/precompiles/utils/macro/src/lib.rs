@@ -0,0 +1,126 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Procedural macros used by the Thales precompiles.
+
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use quote::quote;
+use sha3::{Digest, Keccak256};
+use syn::{parse_macro_input, Expr, ExprLit, ItemEnum, Lit, LitStr};
+
+/// Compute the Keccak-256 hash of a string literal at compile time, as a `[u8; 32]`. This is
+/// typically used to get the first topic of a Solidity event from its signature.
+///
+/// ```ignore
+/// const SELECTOR_LOG_TRANSFER: [u8; 32] = keccak256!("Transfer(address,address,uint256)");
+/// ```
+#[proc_macro]
+pub fn keccak256(input: TokenStream) -> TokenStream {
+	let signature = parse_macro_input!(input as LitStr);
+	let hash = Keccak256::digest(signature.value().as_bytes());
+	let bytes = hash.iter().map(|byte| Literal::u8_suffixed(*byte));
+
+	(quote! { [#(#bytes),*] }).into()
+}
+
+/// Turn an enum whose variants are assigned Solidity function signatures into a `#[repr(u32)]`
+/// enum whose discriminants are the matching four-byte function selectors, as defined by
+/// https://docs.soliditylang.org/en/v0.8.0/abi-spec.html#function-selector
+///
+/// A `TryFrom<u32>` implementation is generated as well, so a selector read from the input can
+/// be matched against the variants.
+///
+/// ```ignore
+/// #[generate_function_selector]
+/// #[derive(Debug, PartialEq)]
+/// enum Action {
+///     IsNominator = "is_nominator(address)",
+///     MinNomination = "min_nomination()",
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn generate_function_selector(_: TokenStream, input: TokenStream) -> TokenStream {
+	let item = parse_macro_input!(input as ItemEnum);
+
+	let ItemEnum {
+		attrs,
+		vis,
+		enum_token,
+		ident,
+		variants,
+		..
+	} = item;
+
+	let mut variant_attrs = Vec::new();
+	let mut variant_idents = Vec::new();
+	let mut variant_selectors = Vec::new();
+
+	for variant in variants {
+		let signature = match &variant.discriminant {
+			Some((
+				_,
+				Expr::Lit(ExprLit {
+					lit: Lit::Str(signature),
+					..
+				}),
+			)) => signature.value(),
+			Some((_, expr)) => {
+				return syn::Error::new_spanned(expr, "Expected a function signature literal")
+					.to_compile_error()
+					.into();
+			}
+			None => {
+				return syn::Error::new_spanned(
+					&variant,
+					"Each variant must be assigned a function signature",
+				)
+				.to_compile_error()
+				.into();
+			}
+		};
+
+		let hash = Keccak256::digest(signature.as_bytes());
+		let selector = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+
+		variant_attrs.push(variant.attrs);
+		variant_idents.push(variant.ident);
+		variant_selectors.push(Literal::u32_suffixed(selector));
+	}
+
+	(quote! {
+		#(#attrs)*
+		#[repr(u32)]
+		#vis #enum_token #ident {
+			#(
+				#(#variant_attrs)*
+				#variant_idents = #variant_selectors,
+			)*
+		}
+
+		impl ::core::convert::TryFrom<u32> for #ident {
+			type Error = ();
+
+			fn try_from(selector: u32) -> ::core::result::Result<Self, ()> {
+				match selector {
+					#(#variant_selectors => Ok(Self::#variant_idents),)*
+					_ => Err(()),
+				}
+			}
+		}
+	})
+	.into()
+}
@@ -0,0 +1,338 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Solidity ABI encoding and decoding of precompile input and output data.
+//! https://docs.soliditylang.org/en/v0.8.0/abi-spec.html
+
+use crate::{error, EvmResult};
+use sp_core::{H160, H256, U256};
+use sp_std::{convert::TryFrom, ops::Range, vec::Vec};
+
+/// Size of a Solidity ABI word, in bytes.
+const WORD_SIZE: usize = 32;
+
+/// Wrapper around an EVM input slice, reading ABI encoded values one after the other.
+#[derive(Clone, Copy, Debug)]
+pub struct EvmDataReader<'a> {
+	input: &'a [u8],
+	cursor: usize,
+}
+
+impl<'a> EvmDataReader<'a> {
+	pub fn new(input: &'a [u8]) -> Self {
+		Self { input, cursor: 0 }
+	}
+
+	/// Read the four-byte function selector. The reader then only covers the arguments, which
+	/// is what the offsets of dynamic data are relative to.
+	pub fn read_selector<T: TryFrom<u32>>(&mut self) -> EvmResult<T> {
+		let range = self.move_cursor(4)?;
+		let selector = self
+			.input
+			.get(range)
+			.ok_or_else(|| error("tried to parse selector out of bounds"))?;
+		let selector = u32::from_be_bytes([selector[0], selector[1], selector[2], selector[3]]);
+
+		let selector = T::try_from(selector).map_err(|_| {
+			log::trace!(
+				target: "precompile-utils",
+				"Failed to match function selector {:#010x}",
+				selector
+			);
+			error("unknown selector")
+		})?;
+
+		self.input = &self.input[self.cursor..];
+		self.cursor = 0;
+
+		Ok(selector)
+	}
+
+	/// Check that the input contains at least `args` more words.
+	pub fn expect_arguments(&self, args: usize) -> EvmResult {
+		let required = args
+			.saturating_mul(WORD_SIZE)
+			.saturating_add(self.cursor);
+		if self.input.len() >= required {
+			Ok(())
+		} else {
+			Err(error("input doesn't match expected length"))
+		}
+	}
+
+	/// Read the next value.
+	pub fn read<T: EvmData>(&mut self) -> EvmResult<T> {
+		T::read(self)
+	}
+
+	/// Read the next 32 bytes word.
+	fn read_word(&mut self) -> EvmResult<&'a [u8]> {
+		let range = self.move_cursor(WORD_SIZE)?;
+		self.input
+			.get(range)
+			.ok_or_else(|| error("tried to parse word out of bounds"))
+	}
+
+	/// Read a word as a length or an offset. Such values must fit in 32 bits.
+	fn read_usize(&mut self) -> EvmResult<usize> {
+		let value = U256::from_big_endian(self.read_word()?);
+		if value > U256::from(u32::MAX) {
+			return Err(error("length or offset too large"));
+		}
+		Ok(value.low_u32() as usize)
+	}
+
+	/// Read an offset to some dynamic data, and return a reader starting at the pointed data.
+	fn read_pointer(&mut self) -> EvmResult<Self> {
+		let offset = self.read_usize()?;
+		let input = self
+			.input
+			.get(offset..)
+			.ok_or_else(|| error("pointer points out of bounds"))?;
+		Ok(Self::new(input))
+	}
+
+	/// Move the cursor by `len` bytes, returning the range it moved over. The range is not
+	/// checked against the input length.
+	fn move_cursor(&mut self, len: usize) -> EvmResult<Range<usize>> {
+		let start = self.cursor;
+		let end = start
+			.checked_add(len)
+			.ok_or_else(|| error("data reading out of bounds"))?;
+		self.cursor = end;
+		Ok(start..end)
+	}
+}
+
+/// Help build the ABI encoded output of a precompile, or the data of a log.
+#[derive(Clone, Debug, Default)]
+pub struct EvmDataWriter {
+	data: Vec<u8>,
+	offset_data: Vec<OffsetDatum>,
+	selector: Option<u32>,
+}
+
+/// Dynamic data to be appended at the end of the encoding, with the position of its offset.
+#[derive(Clone, Debug)]
+struct OffsetDatum {
+	offset_position: usize,
+	data: Vec<u8>,
+	// Offsets are computed from the start of the encoding, minus this shift.
+	offset_shift: usize,
+}
+
+impl EvmDataWriter {
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	/// Start the encoding of a call to the function of the given selector.
+	pub fn new_with_selector(selector: u32) -> Self {
+		Self {
+			selector: Some(selector),
+			..Default::default()
+		}
+	}
+
+	/// Write a value.
+	pub fn write<T: EvmData>(mut self, value: T) -> Self {
+		T::write(&mut self, value);
+		self
+	}
+
+	/// Return the encoded data, dynamic data being appended after the static part.
+	pub fn build(self) -> Vec<u8> {
+		let mut output = self.data;
+
+		for datum in self.offset_data {
+			let offset = U256::from(output.len() - datum.offset_shift);
+			let position = datum.offset_position;
+			offset.to_big_endian(&mut output[position..position + WORD_SIZE]);
+			output.extend_from_slice(&datum.data);
+		}
+
+		match self.selector {
+			Some(selector) => {
+				let mut with_selector = selector.to_be_bytes().to_vec();
+				with_selector.append(&mut output);
+				with_selector
+			}
+			None => output,
+		}
+	}
+
+	/// Write a placeholder for the offset of the given dynamic data, which is filled in `build`.
+	fn write_pointer(&mut self, data: Vec<u8>) {
+		let offset_position = self.data.len();
+		self.data.extend_from_slice(&[0u8; WORD_SIZE]);
+		self.offset_data.push(OffsetDatum {
+			offset_position,
+			data,
+			offset_shift: 0,
+		});
+	}
+}
+
+/// Data that can be ABI encoded and decoded.
+pub trait EvmData: Sized {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self>;
+	fn write(writer: &mut EvmDataWriter, value: Self);
+}
+
+impl EvmData for H256 {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		Ok(H256::from_slice(reader.read_word()?))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		writer.data.extend_from_slice(value.as_bytes());
+	}
+}
+
+impl EvmData for H160 {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		// Addresses are left padded with 12 zero bytes.
+		Ok(H160::from_slice(&reader.read_word()?[12..]))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		H256::write(writer, value.into());
+	}
+}
+
+impl EvmData for U256 {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		Ok(U256::from_big_endian(reader.read_word()?))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let mut buffer = [0u8; WORD_SIZE];
+		value.to_big_endian(&mut buffer);
+		writer.data.extend_from_slice(&buffer);
+	}
+}
+
+macro_rules! impl_evmdata_for_uints {
+	($($uint:ty),*) => {
+		$(
+			impl EvmData for $uint {
+				fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+					let value: U256 = reader.read()?;
+					if value > U256::from(<$uint>::MAX) {
+						return Err(error(concat!("value too large for ", stringify!($uint))));
+					}
+					Ok(value.low_u128() as $uint)
+				}
+
+				fn write(writer: &mut EvmDataWriter, value: Self) {
+					U256::write(writer, value.into());
+				}
+			}
+		)*
+	};
+}
+
+impl_evmdata_for_uints!(u8, u16, u32, u64, u128);
+
+impl EvmData for bool {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let value: U256 = reader.read()?;
+		if value > U256::one() {
+			return Err(error("invalid bool value"));
+		}
+		Ok(!value.is_zero())
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		U256::write(writer, if value { U256::one() } else { U256::zero() });
+	}
+}
+
+/// Dynamic arrays.
+impl<T: EvmData> EvmData for Vec<T> {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let mut array_reader = reader.read_pointer()?;
+		let len = array_reader.read_usize()?;
+
+		// Offsets of the elements are relative to the start of the elements, after the length.
+		let mut elements_reader = EvmDataReader::new(&array_reader.input[WORD_SIZE..]);
+
+		// The length is not trusted to preallocate: each element read consumes input.
+		let mut array = Vec::new();
+		for _ in 0..len {
+			array.push(elements_reader.read()?);
+		}
+
+		Ok(array)
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let mut inner_writer = EvmDataWriter::new().write(U256::from(value.len()));
+
+		for element in value {
+			T::write(&mut inner_writer, element);
+		}
+
+		// Offsets inside the array don't account for the length.
+		for datum in inner_writer.offset_data.iter_mut() {
+			datum.offset_shift += WORD_SIZE;
+		}
+
+		writer.write_pointer(inner_writer.build());
+	}
+}
+
+/// Dynamic `bytes`, as opposed to a dynamic array of `uint8`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl From<Vec<u8>> for Bytes {
+	fn from(bytes: Vec<u8>) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<&[u8]> for Bytes {
+	fn from(bytes: &[u8]) -> Self {
+		Self(bytes.to_vec())
+	}
+}
+
+impl EvmData for Bytes {
+	fn read(reader: &mut EvmDataReader) -> EvmResult<Self> {
+		let mut bytes_reader = reader.read_pointer()?;
+		let len = bytes_reader.read_usize()?;
+		let range = bytes_reader.move_cursor(len)?;
+
+		let bytes = bytes_reader
+			.input
+			.get(range)
+			.ok_or_else(|| error("tried to parse bytes out of bounds"))?;
+
+		Ok(Self(bytes.to_vec()))
+	}
+
+	fn write(writer: &mut EvmDataWriter, value: Self) {
+		let len = value.0.len();
+		let padded_len = (len + WORD_SIZE - 1) / WORD_SIZE * WORD_SIZE;
+
+		let mut data = EvmDataWriter::new().write(U256::from(len)).build();
+		data.extend_from_slice(&value.0);
+		data.resize(WORD_SIZE + padded_len, 0);
+
+		writer.write_pointer(data);
+	}
+}
@@ -0,0 +1,154 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Utilities shared by the Thales precompiles: Solidity ABI encoding and decoding, function
+//! selectors generation, gas accounting and EVM logs.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use evm::ExitError;
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::Get,
+};
+use pallet_evm::GasWeightMapping;
+use sp_std::{borrow::Cow, marker::PhantomData};
+
+mod data;
+mod logs;
+
+pub use data::{Bytes, EvmData, EvmDataReader, EvmDataWriter};
+pub use logs::{
+	collect_logs, collect_logs_and_calls, deposit_log, log1, log2, log3, log_cost, NestedCall,
+	PrecompileLogsRunner,
+};
+pub use precompile_utils_macro::{generate_function_selector, keccak256};
+
+#[cfg(test)]
+mod tests;
+
+/// Alias for Result returning an EVM precompile error.
+pub type EvmResult<T = ()> = Result<T, ExitError>;
+
+/// Return an error with provided (static) text.
+pub fn error<T: Into<Cow<'static, str>>>(text: T) -> ExitError {
+	ExitError::Other(text.into())
+}
+
+/// Keeps track of the gas used by a precompile, making sure it stays below the target gas.
+#[derive(Clone, Copy, Debug)]
+pub struct Gasometer {
+	target_gas: Option<u64>,
+	used_gas: u64,
+}
+
+impl Gasometer {
+	pub fn new(target_gas: Option<u64>) -> Self {
+		Self {
+			target_gas,
+			used_gas: 0,
+		}
+	}
+
+	/// Gas used so far.
+	pub fn used_gas(&self) -> u64 {
+		self.used_gas
+	}
+
+	/// Record an amount of used gas, returning `OutOfGas` if it exceeds the target gas.
+	pub fn record_cost(&mut self, cost: u64) -> EvmResult {
+		self.used_gas = self
+			.used_gas
+			.checked_add(cost)
+			.ok_or(ExitError::OutOfGas)?;
+
+		match self.target_gas {
+			Some(gas_limit) if self.used_gas > gas_limit => Err(ExitError::OutOfGas),
+			_ => Ok(()),
+		}
+	}
+
+	/// Gas left before reaching the target gas, if any.
+	pub fn remaining_gas(&self) -> EvmResult<Option<u64>> {
+		self.target_gas
+			.map(|gas_limit| {
+				gas_limit
+					.checked_sub(self.used_gas)
+					.ok_or(ExitError::OutOfGas)
+			})
+			.transpose()
+	}
+}
+
+/// Helpers for precompiles interacting with a runtime.
+pub struct RuntimeHelper<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> RuntimeHelper<Runtime>
+where
+	Runtime: pallet_evm::Config,
+	Runtime::Call: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+{
+	/// Gas cost of a single storage read, according to the runtime `RuntimeDbWeight`.
+	pub fn db_read_gas_cost() -> u64 {
+		<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+			<Runtime as frame_system::Config>::DbWeight::get().read,
+		)
+	}
+
+	/// Gas cost of a single storage write, according to the runtime `RuntimeDbWeight`.
+	pub fn db_write_gas_cost() -> u64 {
+		<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+			<Runtime as frame_system::Config>::DbWeight::get().write,
+		)
+	}
+
+	/// Dispatch a runtime call, charging its weight to the gasometer. The call is only dispatched
+	/// if the remaining gas covers its declared weight, and its actual weight is then recorded.
+	/// If the call fails, the error message is the name of the `DispatchError`, which for pallet
+	/// errors is the variant name (e.g. `NomBondBelowMin`). The `PrecompileLogsRunner` reverts
+	/// with it as `Error(string)` data when the precompile is the target of the transaction.
+	pub fn try_dispatch<Call>(
+		origin: <Runtime::Call as Dispatchable>::Origin,
+		call: Call,
+		gasometer: &mut Gasometer,
+	) -> EvmResult
+	where
+		Runtime::Call: From<Call>,
+	{
+		let call = Runtime::Call::from(call);
+		let dispatch_info = call.get_dispatch_info();
+
+		// Make sure there is enough gas.
+		if let Some(remaining_gas) = gasometer.remaining_gas()? {
+			let required_gas = Runtime::GasWeightMapping::weight_to_gas(dispatch_info.weight);
+			if required_gas > remaining_gas {
+				return Err(ExitError::OutOfGas);
+			}
+		}
+
+		let post_info = call.dispatch(origin).map_err(|e| {
+			log::trace!(target: "precompile-utils", "Dispatched call failed: {:?}", e);
+			// Precompiles cannot revert with data, so the reason is carried by the error message:
+			// the name of the pallet error when the call failed with one. The runner turns it into
+			// revert data.
+			error(<&'static str>::from(e.error))
+		})?;
+
+		gasometer.record_cost(Runtime::GasWeightMapping::weight_to_gas(
+			post_info.actual_weight.unwrap_or(dispatch_info.weight),
+		))
+	}
+}
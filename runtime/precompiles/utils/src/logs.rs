@@ -0,0 +1,517 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+//! EVM logs emitted by precompiles.
+//!
+//! The precompile interface of the EVM only returns an output, so precompiles deposit their logs
+//! in an environment set up by the `PrecompileLogsRunner`. The runner wraps the EVM state to follow
+//! its substates: the logs of the contracts and of the precompiles are recorded in the frame of the
+//! call emitting them, and dropped along with it when it reverts or fails. Once the execution is
+//! over, the logs of the frames that went through are emitted in order.
+//!
+//! The executions nested in a precompile are recorded as well, so the tracer can show them as
+//! subcalls of the precompile call.
+
+use evm::{
+	backend::{Backend, Basic, Log},
+	executor::{StackExecutor, StackState, StackSubstateMetadata},
+	CreateScheme, ExitError, ExitReason, ExitRevert, Transfer,
+};
+use pallet_evm::{
+	runner::stack::{Runner as StackRunner, SubstrateStackState},
+	CallInfo, CreateInfo, Error, ExecutionInfo, PrecompileSet, Runner, Vicinity,
+};
+use sp_core::{H160, H256, U256};
+use sp_std::{marker::PhantomData, vec, vec::Vec};
+
+use crate::{Bytes, EvmDataWriter};
+
+/// Selector of the `Error(string)` revert data produced by Solidity `require` and `revert`.
+const ERROR_STRING_SELECTOR: u32 = 0x08c379a0;
+
+/// Call executed by the `PrecompileLogsRunner` during the execution of a precompile, such as a
+/// subcall of a batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NestedCall {
+	pub source: H160,
+	pub target: H160,
+	pub input: Vec<u8>,
+	pub value: U256,
+	pub gas_limit: u64,
+	pub used_gas: u64,
+	pub exit_reason: ExitReason,
+	/// Returned data, or revert data.
+	pub output: Vec<u8>,
+	/// Logs emitted by the call, including the ones of its nested calls. Empty if it failed.
+	pub logs: Vec<Log>,
+	/// Index of the first log of the call in the logs of the precompile call.
+	pub log_index: usize,
+	/// Calls executed by the precompiles the call went through.
+	pub calls: Vec<NestedCall>,
+}
+
+/// Logs emitted, accounts deleted and nested calls executed by a call frame.
+#[derive(Default)]
+struct Frame {
+	logs: Vec<Log>,
+	deleted: Vec<H160>,
+	calls: Vec<NestedCall>,
+}
+
+/// Frames of the calls being executed, the last one being the current call.
+struct Frames(Vec<Frame>);
+
+impl Frames {
+	fn new() -> Self {
+		Self(vec![Frame::default()])
+	}
+
+	fn current(&mut self) -> &mut Frame {
+		self.0.last_mut().expect("the root frame is never exited; qed")
+	}
+
+	fn enter(&mut self) {
+		self.0.push(Frame::default());
+	}
+
+	/// Exit the current frame, keeping its logs and deleted accounts if `commit`. Its nested
+	/// calls are always kept, as they were executed even if they are reverted.
+	fn exit(&mut self, commit: bool) {
+		if self.0.len() == 1 {
+			return;
+		}
+		let frame = self.0.pop().expect("checked above; qed");
+		let parent = self.current();
+		if commit {
+			parent.logs.extend(frame.logs);
+			parent.deleted.extend(frame.deleted);
+		}
+		parent.calls.extend(frame.calls);
+	}
+
+	fn into_root(mut self) -> Frame {
+		self.0.truncate(1);
+		self.0.pop().unwrap_or_default()
+	}
+}
+
+environmental::environmental!(frames: Frames);
+
+/// Deposit a log emitted by a precompile in the frame of its call. The log is dropped when the
+/// execution doesn't go through the `PrecompileLogsRunner`, or `collect_logs` like the tracer.
+pub fn deposit_log(log: Log) {
+	frames::with(|frames| frames.current().logs.push(log));
+}
+
+/// Enter the frame of a call.
+pub(crate) fn enter_frame() {
+	frames::with(|frames| frames.enter());
+}
+
+/// Exit the frame of a call, keeping what it recorded if `commit`.
+pub(crate) fn exit_frame(commit: bool) {
+	frames::with(|frames| frames.exit(commit));
+}
+
+/// Run `f`, returning its result along with the logs deposited by the precompiles it executed.
+pub fn collect_logs<R>(f: impl FnOnce() -> R) -> (R, Vec<Log>) {
+	let (result, logs, _) = collect_logs_and_calls(f);
+	(result, logs)
+}
+
+/// Run `f`, returning its result along with the logs deposited and the calls executed by the
+/// precompiles it executed.
+pub fn collect_logs_and_calls<R>(f: impl FnOnce() -> R) -> (R, Vec<Log>, Vec<NestedCall>) {
+	let (result, root) = with_frames(f);
+	(result, root.logs, root.calls)
+}
+
+/// Run `f` in a new set of frames, returning what its root frame recorded.
+fn with_frames<R>(f: impl FnOnce() -> R) -> (R, Frame) {
+	let mut frames = Frames::new();
+	let result = frames::using(&mut frames, f);
+	(result, frames.into_root())
+}
+
+/// Build a log with a single topic.
+pub fn log1(address: H160, topic0: impl Into<H256>, data: Vec<u8>) -> Log {
+	Log {
+		address,
+		topics: vec![topic0.into()],
+		data,
+	}
+}
+
+/// Build a log with two topics.
+pub fn log2(
+	address: H160,
+	topic0: impl Into<H256>,
+	topic1: impl Into<H256>,
+	data: Vec<u8>,
+) -> Log {
+	Log {
+		address,
+		topics: vec![topic0.into(), topic1.into()],
+		data,
+	}
+}
+
+/// Build a log with three topics.
+pub fn log3(
+	address: H160,
+	topic0: impl Into<H256>,
+	topic1: impl Into<H256>,
+	topic2: impl Into<H256>,
+	data: Vec<u8>,
+) -> Log {
+	Log {
+		address,
+		topics: vec![topic0.into(), topic1.into(), topic2.into()],
+		data,
+	}
+}
+
+/// Gas cost of a log, following the cost of the `LOG` opcodes.
+pub fn log_cost(log: &Log) -> u64 {
+	const G_LOG: u64 = 375;
+	const G_LOGTOPIC: u64 = 375;
+	const G_LOGDATA: u64 = 8;
+
+	G_LOG
+		.saturating_add(G_LOGTOPIC.saturating_mul(log.topics.len() as u64))
+		.saturating_add(G_LOGDATA.saturating_mul(log.data.len() as u64))
+}
+
+/// EVM state wrapping the one of `pallet_evm`, recording the logs and deleted accounts in the
+/// frames of the calls. The wrapped state only sees the frames being entered and exited.
+struct FramesStackState<S>(S);
+
+impl<S: Backend> Backend for FramesStackState<S> {
+	fn gas_price(&self) -> U256 {
+		self.0.gas_price()
+	}
+	fn origin(&self) -> H160 {
+		self.0.origin()
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		self.0.block_hash(number)
+	}
+	fn block_number(&self) -> U256 {
+		self.0.block_number()
+	}
+	fn block_coinbase(&self) -> H160 {
+		self.0.block_coinbase()
+	}
+	fn block_timestamp(&self) -> U256 {
+		self.0.block_timestamp()
+	}
+	fn block_difficulty(&self) -> U256 {
+		self.0.block_difficulty()
+	}
+	fn block_gas_limit(&self) -> U256 {
+		self.0.block_gas_limit()
+	}
+	fn chain_id(&self) -> U256 {
+		self.0.chain_id()
+	}
+	fn exists(&self, address: H160) -> bool {
+		self.0.exists(address)
+	}
+	fn basic(&self, address: H160) -> Basic {
+		self.0.basic(address)
+	}
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.0.code(address)
+	}
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.0.storage(address, index)
+	}
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.0.original_storage(address, index)
+	}
+}
+
+impl<'config, S: StackState<'config>> StackState<'config> for FramesStackState<S> {
+	fn metadata(&self) -> &StackSubstateMetadata<'config> {
+		self.0.metadata()
+	}
+	fn metadata_mut(&mut self) -> &mut StackSubstateMetadata<'config> {
+		self.0.metadata_mut()
+	}
+
+	fn enter(&mut self, gas_limit: u64, is_static: bool) {
+		enter_frame();
+		self.0.enter(gas_limit, is_static)
+	}
+	fn exit_commit(&mut self) -> Result<(), ExitError> {
+		exit_frame(true);
+		self.0.exit_commit()
+	}
+	fn exit_revert(&mut self) -> Result<(), ExitError> {
+		exit_frame(false);
+		self.0.exit_revert()
+	}
+	fn exit_discard(&mut self) -> Result<(), ExitError> {
+		exit_frame(false);
+		self.0.exit_discard()
+	}
+
+	fn is_empty(&self, address: H160) -> bool {
+		self.0.is_empty(address)
+	}
+	fn deleted(&self, address: H160) -> bool {
+		self.0.deleted(address)
+	}
+	fn inc_nonce(&mut self, address: H160) {
+		self.0.inc_nonce(address)
+	}
+	fn set_storage(&mut self, address: H160, key: H256, value: H256) {
+		self.0.set_storage(address, key, value)
+	}
+	fn reset_storage(&mut self, address: H160) {
+		self.0.reset_storage(address)
+	}
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) {
+		frames::with(|frames| {
+			frames.current().logs.push(Log {
+				address,
+				topics,
+				data,
+			})
+		});
+	}
+	fn set_deleted(&mut self, address: H160) {
+		frames::with(|frames| frames.current().deleted.push(address));
+		self.0.set_deleted(address)
+	}
+	fn set_code(&mut self, address: H160, code: Vec<u8>) {
+		self.0.set_code(address, code)
+	}
+	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+		self.0.transfer(transfer)
+	}
+	fn reset_balance(&mut self, address: H160) {
+		self.0.reset_balance(address)
+	}
+	fn touch(&mut self, address: H160) {
+		self.0.touch(address)
+	}
+}
+
+/// EVM runner including the logs deposited by the precompiles in the logs of the executions,
+/// in the order they are emitted and only when the frame of the precompile call went through.
+///
+/// The execution itself, charging its fees, is done by the `pallet_evm` stack runner. Executions
+/// nested in a precompile, such as the subcalls of a batch, record their logs in the frame of
+/// that precompile call, and the calls are recorded as `NestedCall`s.
+///
+/// Precompiles can't revert with data, they fail with an `ExitError::Other` message. When the
+/// precompile is the target of the call, the runner turns its failure into a revert with the
+/// `Error(string)` data of the message, which Ethereum clients decode as the revert reason. A
+/// contract calling the precompile still sees a failure without revert data.
+pub struct PrecompileLogsRunner<T>(PhantomData<T>);
+
+impl<T: pallet_evm::Config> PrecompileLogsRunner<T> {
+	/// Execute `f`, returning what the root frame of a nested execution recorded.
+	fn execute<'config, R>(
+		source: H160,
+		value: U256,
+		gas_limit: u64,
+		gas_price: Option<U256>,
+		nonce: Option<U256>,
+		config: &'config evm::Config,
+		f: impl FnOnce(
+			&mut StackExecutor<'config, FramesStackState<SubstrateStackState<'_, 'config, T>>>,
+		) -> (ExitReason, R),
+	) -> Result<(ExecutionInfo<R>, Frame), Error<T>> {
+		let nested = frames::with(|_| ()).is_some();
+		let mut recorded = Frame::default();
+
+		let info = StackRunner::<T>::execute(
+			source,
+			value,
+			gas_limit,
+			gas_price,
+			nonce,
+			config,
+			|executor| {
+				// Execute in a state following the frames, then report the gas it used and what
+				// its root frame recorded to the state of the stack runner.
+				let vicinity = Vicinity {
+					gas_price: executor.state().gas_price(),
+					origin: source,
+				};
+				let metadata = StackSubstateMetadata::new(gas_limit, config);
+				let state = FramesStackState(SubstrateStackState::new(&vicinity, metadata));
+				let mut inner =
+					StackExecutor::new_with_precompile(state, config, T::Precompiles::execute);
+
+				let ((reason, value), root) = with_frames(|| f(&mut inner));
+
+				// The stack runner pays for the inner execution. When it can't, the execution
+				// fails and what its root frame recorded is dropped.
+				let state = executor.state_mut();
+				if state
+					.metadata_mut()
+					.gasometer_mut()
+					.record_cost(inner.used_gas())
+					.is_err()
+				{
+					return (ExitReason::Error(ExitError::OutOfGas), value);
+				}
+				for address in root.deleted {
+					state.set_deleted(address);
+				}
+				if nested {
+					frames::with(|frames| frames.current().logs.extend(root.logs.iter().cloned()));
+					recorded.logs = root.logs;
+					recorded.calls = root.calls;
+				} else {
+					for Log {
+						address,
+						topics,
+						data,
+					} in root.logs
+					{
+						state.log(address, topics, data);
+					}
+				}
+
+				(reason, value)
+			},
+		)?;
+
+		Ok((info, recorded))
+	}
+}
+
+impl<T: pallet_evm::Config> Runner<T> for PrecompileLogsRunner<T> {
+	type Error = Error<T>;
+
+	fn call(
+		source: H160,
+		target: H160,
+		input: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		gas_price: Option<U256>,
+		nonce: Option<U256>,
+		config: &evm::Config,
+	) -> Result<CallInfo, Self::Error> {
+		// The input of a nested call is kept to record it.
+		let nested_input = frames::with(|_| input.clone());
+
+		let (mut info, recorded) = Self::execute(
+			source,
+			value,
+			gas_limit,
+			gas_price,
+			nonce,
+			config,
+			|executor| executor.transact_call(source, target, value, input, gas_limit),
+		)?;
+
+		// Only precompiles fail with `ExitError::Other`, and the failure of a subcall is not the
+		// result of the execution, so this is the failure of the target precompile.
+		if let ExitReason::Error(ExitError::Other(message)) = &info.exit_reason {
+			info.value = EvmDataWriter::new_with_selector(ERROR_STRING_SELECTOR)
+				.write(Bytes::from(message.as_bytes()))
+				.build();
+			info.exit_reason = ExitReason::Revert(ExitRevert::Reverted);
+		}
+
+		if let Some(input) = nested_input {
+			frames::with(|frames| {
+				let frame = frames.current();
+				frame.calls.push(NestedCall {
+					source,
+					target,
+					input,
+					value,
+					gas_limit,
+					used_gas: info.used_gas.low_u64(),
+					exit_reason: info.exit_reason.clone(),
+					output: info.value.clone(),
+					log_index: frame.logs.len() - recorded.logs.len(),
+					logs: recorded.logs,
+					calls: recorded.calls,
+				})
+			});
+		}
+
+		Ok(info)
+	}
+
+	fn create(
+		source: H160,
+		init: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		gas_price: Option<U256>,
+		nonce: Option<U256>,
+		config: &evm::Config,
+	) -> Result<CreateInfo, Self::Error> {
+		Self::execute(
+			source,
+			value,
+			gas_limit,
+			gas_price,
+			nonce,
+			config,
+			|executor| {
+				let address = executor.create_address(CreateScheme::Legacy { caller: source });
+				(
+					executor.transact_create(source, value, init, gas_limit),
+					address,
+				)
+			},
+		)
+		.map(|(info, _)| info)
+	}
+
+	fn create2(
+		source: H160,
+		init: Vec<u8>,
+		salt: H256,
+		value: U256,
+		gas_limit: u64,
+		gas_price: Option<U256>,
+		nonce: Option<U256>,
+		config: &evm::Config,
+	) -> Result<CreateInfo, Self::Error> {
+		let code_hash = H256::from(sp_io::hashing::keccak_256(&init));
+		Self::execute(
+			source,
+			value,
+			gas_limit,
+			gas_price,
+			nonce,
+			config,
+			|executor| {
+				let address = executor.create_address(CreateScheme::Create2 {
+					caller: source,
+					code_hash,
+					salt,
+				});
+				(
+					executor.transact_create2(source, value, init, salt, gas_limit),
+					address,
+				)
+			},
+		)
+		.map(|(info, _)| info)
+	}
+}
@@ -0,0 +1,326 @@
+// Copyright 2019-2021 Thales Inc.
+// This file is part of Thales.
+
+// Thales is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Thales is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Thales.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::*;
+use evm::ExitError;
+use hex_literal::hex;
+use sp_core::{H160, H256, U256};
+use sp_std::convert::TryFrom;
+
+#[generate_function_selector]
+#[derive(Debug, PartialEq)]
+enum Action {
+	Sam = "sam(bytes,bool,uint256[])",
+	Baz = "baz(uint32,bool)",
+}
+
+/// Deterministic pseudo-random bytes, to throw garbage at the reader.
+struct XorShift(u64);
+
+impl XorShift {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn bytes(&mut self, len: usize) -> Vec<u8> {
+		(0..len).map(|_| self.next() as u8).collect()
+	}
+}
+
+/// Example taken from the Solidity ABI specification.
+fn sam_input() -> Vec<u8> {
+	hex!(
+		"a5643bf2"
+		"0000000000000000000000000000000000000000000000000000000000000060"
+		"0000000000000000000000000000000000000000000000000000000000000001"
+		"00000000000000000000000000000000000000000000000000000000000000a0"
+		"0000000000000000000000000000000000000000000000000000000000000004"
+		"6461766500000000000000000000000000000000000000000000000000000000"
+		"0000000000000000000000000000000000000000000000000000000000000003"
+		"0000000000000000000000000000000000000000000000000000000000000001"
+		"0000000000000000000000000000000000000000000000000000000000000002"
+		"0000000000000000000000000000000000000000000000000000000000000003"
+	)
+	.to_vec()
+}
+
+fn read_sam(input: &[u8]) -> EvmResult<(Bytes, bool, Vec<U256>)> {
+	let mut reader = EvmDataReader::new(input);
+	match reader.read_selector()? {
+		Action::Sam => (),
+		_ => return Err(error("unexpected selector")),
+	}
+	reader.expect_arguments(3)?;
+	Ok((reader.read()?, reader.read()?, reader.read()?))
+}
+
+#[test]
+fn selectors_are_generated_from_signatures() {
+	assert_eq!(Action::Sam as u32, 0xa5643bf2);
+	assert_eq!(Action::Baz as u32, 0xcdcd77c0);
+	assert_eq!(Action::try_from(0xcdcd77c0), Ok(Action::Baz));
+	assert_eq!(Action::try_from(0x12345678), Err(()));
+}
+
+#[test]
+fn reads_specification_example() {
+	assert_eq!(
+		read_sam(&sam_input()),
+		Ok((
+			Bytes(b"dave".to_vec()),
+			true,
+			vec![U256::from(1), U256::from(2), U256::from(3)]
+		))
+	);
+}
+
+#[test]
+fn writes_specification_example() {
+	let output = EvmDataWriter::new_with_selector(Action::Sam as u32)
+		.write(Bytes(b"dave".to_vec()))
+		.write(true)
+		.write(vec![U256::from(1), U256::from(2), U256::from(3)])
+		.build();
+
+	assert_eq!(output, sam_input());
+}
+
+#[test]
+fn nested_dynamic_data_roundtrips() {
+	let value = vec![
+		vec![Bytes(b"thales".to_vec()), Bytes(vec![0xff; 40])],
+		vec![],
+		vec![Bytes(vec![])],
+	];
+	let output = EvmDataWriter::new().write(value.clone()).build();
+
+	assert_eq!(EvmDataReader::new(&output).read::<Vec<Vec<Bytes>>>(), Ok(value));
+}
+
+#[test]
+fn static_data_roundtrips() {
+	let address = H160::repeat_byte(0x42);
+	let hash = H256::repeat_byte(0x24);
+	let output = EvmDataWriter::new()
+		.write(address)
+		.write(hash)
+		.write(u128::MAX)
+		.write(7u32)
+		.write(false)
+		.build();
+
+	let mut reader = EvmDataReader::new(&output);
+	assert_eq!(reader.read::<H160>(), Ok(address));
+	assert_eq!(reader.read::<H256>(), Ok(hash));
+	assert_eq!(reader.read::<u128>(), Ok(u128::MAX));
+	assert_eq!(reader.read::<u32>(), Ok(7));
+	assert_eq!(reader.read::<bool>(), Ok(false));
+	assert_eq!(
+		reader.read::<U256>(),
+		Err(error("tried to parse word out of bounds"))
+	);
+}
+
+#[test]
+fn rejects_out_of_range_values() {
+	let output = EvmDataWriter::new()
+		.write(U256::from(u32::MAX) + 1)
+		.build();
+	assert_eq!(
+		EvmDataReader::new(&output).read::<u32>(),
+		Err(error("value too large for u32"))
+	);
+
+	let output = EvmDataWriter::new().write(U256::from(2)).build();
+	assert_eq!(
+		EvmDataReader::new(&output).read::<bool>(),
+		Err(error("invalid bool value"))
+	);
+}
+
+#[test]
+fn rejects_unknown_or_truncated_selector() {
+	assert_eq!(
+		EvmDataReader::new(&hex!("a5643b")).read_selector::<Action>(),
+		Err(error("tried to parse selector out of bounds"))
+	);
+	assert_eq!(
+		EvmDataReader::new(&hex!("deadbeef")).read_selector::<Action>(),
+		Err(error("unknown selector"))
+	);
+}
+
+#[test]
+fn rejects_every_truncation_of_valid_input() {
+	let input = sam_input();
+	for len in 0..input.len() {
+		assert!(read_sam(&input[..len]).is_err(), "truncated at {}", len);
+	}
+}
+
+#[test]
+fn rejects_huge_lengths_and_offsets() {
+	let mut input = sam_input();
+	// Offset of the bytes argument.
+	input[4..36].copy_from_slice(&[0xff; 32]);
+	assert_eq!(read_sam(&input), Err(error("length or offset too large")));
+
+	let mut input = sam_input();
+	// Length of the array, which fits in 32 bits but not in the input.
+	input[164..196].copy_from_slice(&H256::from_low_u64_be(u32::MAX as u64).0);
+	assert_eq!(read_sam(&input), Err(error("tried to parse word out of bounds")));
+
+	let mut input = sam_input();
+	// Length of the bytes, which fits in 32 bits but not in the input.
+	input[100..132].copy_from_slice(&H256::from_low_u64_be(u32::MAX as u64).0);
+	assert_eq!(read_sam(&input), Err(error("tried to parse bytes out of bounds")));
+}
+
+#[test]
+fn random_input_never_panics() {
+	let mut rng = XorShift(0x5eed);
+	for _ in 0..2_000 {
+		let len = (rng.next() % 300) as usize;
+		let mut input = rng.bytes(len);
+		if rng.next() % 2 == 0 && input.len() >= 4 {
+			// Give a chance to go past the selector.
+			input[0..4].copy_from_slice(&hex!("a5643bf2"));
+		}
+
+		let _ = read_sam(&input);
+		let _ = EvmDataReader::new(&input).read::<Vec<Vec<Bytes>>>();
+		let _ = EvmDataReader::new(&input).read::<Vec<H160>>();
+		let _ = EvmDataReader::new(&input).read::<u8>();
+	}
+}
+
+#[test]
+fn random_mutations_of_valid_input_never_panic() {
+	let mut rng = XorShift(0xabcd);
+	let valid = sam_input();
+	for _ in 0..2_000 {
+		let mut input = valid.clone();
+		for _ in 0..(rng.next() % 4 + 1) {
+			let position = (rng.next() as usize) % input.len();
+			input[position] = rng.next() as u8;
+		}
+		let _ = read_sam(&input);
+	}
+}
+
+#[test]
+fn gasometer_enforces_target_gas() {
+	let mut gasometer = Gasometer::new(Some(100));
+	assert_eq!(gasometer.record_cost(60), Ok(()));
+	assert_eq!(gasometer.remaining_gas(), Ok(Some(40)));
+	assert_eq!(gasometer.record_cost(41), Err(ExitError::OutOfGas));
+
+	let mut gasometer = Gasometer::new(None);
+	assert_eq!(gasometer.record_cost(u64::MAX), Ok(()));
+	assert_eq!(gasometer.remaining_gas(), Ok(None));
+	assert_eq!(gasometer.record_cost(1), Err(ExitError::OutOfGas));
+	assert_eq!(gasometer.used_gas(), u64::MAX);
+}
+
+#[test]
+fn keccak256_hashes_event_signatures() {
+	assert_eq!(
+		keccak256!("Transfer(address,address,uint256)"),
+		hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+	);
+}
+
+#[test]
+fn logs_are_collected_only_in_an_environment() {
+	let address = H160::repeat_byte(0x08);
+	let topic = H256::repeat_byte(0x01);
+
+	// Without environment, logs are dropped.
+	deposit_log(log1(address, topic, vec![]));
+
+	let ((), logs) = collect_logs(|| {
+		deposit_log(log1(address, topic, vec![]));
+		deposit_log(log2(address, topic, H160::repeat_byte(0x42), vec![1, 2, 3]));
+	});
+
+	assert_eq!(logs.len(), 2);
+	assert_eq!(logs[0].topics, vec![topic]);
+	assert_eq!(logs[1].topics, vec![topic, H256::from(H160::repeat_byte(0x42))]);
+	assert_eq!(logs[1].data, vec![1, 2, 3]);
+
+	assert_eq!(collect_logs(|| ()).1, vec![]);
+}
+
+#[test]
+fn logs_of_exited_frames_are_kept_only_when_committed() {
+	let address = H160::repeat_byte(0x08);
+	let log = |byte: u8| log1(address, H256::repeat_byte(byte), vec![]);
+	let enter = logs::enter_frame;
+	let exit = logs::exit_frame;
+
+	let ((), logs) = collect_logs(|| {
+		deposit_log(log(0x01));
+
+		enter();
+		deposit_log(log(0x02));
+		enter();
+		deposit_log(log(0x03));
+		exit(false);
+		deposit_log(log(0x04));
+		exit(true);
+
+		enter();
+		deposit_log(log(0x05));
+		exit(false);
+
+		deposit_log(log(0x06));
+	});
+
+	assert_eq!(
+		logs.into_iter().map(|log| log.topics[0]).collect::<Vec<_>>(),
+		vec![
+			H256::repeat_byte(0x01),
+			H256::repeat_byte(0x02),
+			H256::repeat_byte(0x04),
+			H256::repeat_byte(0x06),
+		]
+	);
+}
+
+#[test]
+fn the_root_frame_is_never_exited() {
+	let address = H160::repeat_byte(0x08);
+
+	let ((), logs) = collect_logs(|| {
+		deposit_log(log1(address, H256::repeat_byte(0x01), vec![]));
+		logs::exit_frame(false);
+	});
+
+	assert_eq!(logs.len(), 1);
+}
+
+#[test]
+fn log_cost_matches_log_opcodes() {
+	let address = H160::repeat_byte(0x08);
+	assert_eq!(log_cost(&log1(address, H256::zero(), vec![])), 750);
+	assert_eq!(
+		log_cost(&log3(address, H256::zero(), H256::zero(), H256::zero(), vec![0; 32])),
+		375 + 3 * 375 + 8 * 32
+	);
+}
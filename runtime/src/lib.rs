@@ -33,8 +33,11 @@ use frame_support::{
 	construct_runtime,
 	pallet_prelude::PhantomData,
 	parameter_types,
-	traits::{Get, Randomness},
-	weights::{constants::WEIGHT_PER_SECOND, IdentityFee, Weight},
+	traits::{Currency, Filter, Get, Randomness},
+	weights::{
+		constants::{RocksDbWeight, WEIGHT_PER_SECOND},
+		IdentityFee, Weight,
+	},
 };
 use frame_system::{EnsureOneOf, EnsureRoot};
 use thales_extensions_evm::runner::stack::TraceRunner as TraceRunnerT;
@@ -46,6 +49,7 @@ use pallet_evm::{
 };
 use pallet_transaction_payment::CurrencyAdapter;
 pub use parachain_staking::{InflationInfo, Range};
+pub use precompiles::ThalesPrecompiles;
 use parity_scale_codec::{Decode, Encode};
 use sha3::{Digest, Keccak256};
 use sp_api::impl_runtime_apis;
@@ -60,6 +64,7 @@ use sp_std::{convert::TryFrom, prelude::*};
 #[cfg(feature = "std")]
 use sp_version::NativeVersion;
 use sp_version::RuntimeVersion;
+use thales_primitives_collator_stats::CollatorRoundStats;
 
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
@@ -113,7 +118,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("thales"),
 	impl_name: create_runtime_str!("thales"),
 	authoring_version: 3,
-	spec_version: 35,
+	spec_version: 37,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 2,
@@ -176,7 +181,8 @@ impl frame_system::Config for Runtime {
 	type AccountData = pallet_balances::AccountData<Balance>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
-	type DbWeight = ();
+	/// The weight of database operations, also used to charge the precompiles storage reads.
+	type DbWeight = RocksDbWeight;
 	type BaseCallFilter = ();
 	type SystemWeightInfo = ();
 	/// This is used as an identifier of the chain. 42 is the generic substrate prefix.
@@ -270,13 +276,42 @@ impl pallet_evm::Config for Runtime {
 	type AddressMapping = IdentityAddressMapping;
 	type Currency = Balances;
 	type Event = Event;
-	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	// Include the logs deposited by the precompiles in the result of the executions.
+	type Runner = precompile_utils::PrecompileLogsRunner<Self>;
 	type Precompiles = precompiles::ThalesPrecompiles<Self>;
 	type ChainId = EthereumChainId;
 	type OnChargeTransaction = ();
 	type BlockGasLimit = BlockGasLimit;
 }
 
+/// Metadata of GLMR, as exposed by the ERC-20 precompile.
+impl precompiles::Erc20Metadata for Runtime {
+	fn name() -> &'static str {
+		"Thales Token"
+	}
+
+	fn symbol() -> &'static str {
+		"GLMR"
+	}
+
+	fn decimals() -> u8 {
+		18
+	}
+}
+
+/// Calls that contracts can dispatch through the `Dispatch` precompile. Calls of pallets able to
+/// wrap other calls (such as `Utility` or `Sudo`) or to re-enter the EVM are not allowed.
+pub struct DispatchableByContracts;
+impl Filter<Call> for DispatchableByContracts {
+	fn filter(call: &Call) -> bool {
+		matches!(call, Call::Balances(_) | Call::ParachainStaking(_) | Call::Democracy(_))
+	}
+}
+
+impl precompiles::DispatchFilter for Runtime {
+	type CallFilter = DispatchableByContracts;
+}
+
 parameter_types! {
 	pub MaximumSchedulerWeight: Weight = Perbill::from_percent(80) * BlockWeights::get().max_block;
 }
@@ -443,7 +478,15 @@ impl cumulus_pallet_parachain_system::Config for Runtime {
 	type OnValidationData = ();
 	type SelfParaId = ParachainInfo;
 	type DownwardMessageHandlers = ();
-	type HrmpMessageHandlers = ();
+	type HrmpMessageHandlers = TokenDealer;
+}
+
+impl pallet_token_dealer::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type UpwardMessageSender = ParachainSystem;
+	type HrmpMessageSender = ParachainSystem;
+	type WeightInfo = pallet_token_dealer::weights::SubstrateWeight<Runtime>;
 }
 
 impl parachain_info::Config for Runtime {}
@@ -487,18 +530,31 @@ impl parachain_staking::Config for Runtime {
 	type MinNominatorStk = MinNominatorStk;
 	type WeightInfo = parachain_staking::weights::SubstrateWeight<Runtime>;
 }
+parameter_types! {
+	/// Keep the authors of the last 4 hours of blocks (2400 * 6 second block times)
+	pub const AuthorHistoryDepth: BlockNumber = 2400;
+}
 impl author_inherent::Config for Runtime {
-	type EventHandler = ParachainStaking;
+	type Event = Event;
+	// Staking rewards the author, the filter records block production statistics.
+	type EventHandler = (ParachainStaking, AuthorFilter);
 	// We cannot run the full filtered author checking logic in the preliminary check because it
 	// depends on entropy from the relay chain. Instead we just make sure that the author is staked
 	// in the preliminary check. The final check including the filtering happens during execution.
 	type PreliminaryCanAuthor = ParachainStaking;
 	type FinalCanAuthor = AuthorFilter;
+	type AuthorHistoryDepth = AuthorHistoryDepth;
+	type WeightInfo = author_inherent::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+	/// Keep block production statistics for the last 24 rounds (one day of default rounds)
+	pub const StatsHistoryDepth: u32 = 24;
+}
 impl pallet_author_filter::Config for Runtime {
 	type Event = Event;
 	type RandomnessSource = RandomnessCollectiveFlip;
+	type StatsHistoryDepth = StatsHistoryDepth;
 }
 
 construct_runtime! {
@@ -528,8 +584,9 @@ construct_runtime! {
 			pallet_collective::<Instance2>::{Pallet, Call, Event<T>, Origin<T>, Config<T>},
 		// The order matters here. Inherents will be included in the order specified here.
 		// Concretely we need the author inherent to come after the parachain_upgrade inherent.
-		AuthorInherent: author_inherent::{Pallet, Call, Storage, Inherent},
-		AuthorFilter: pallet_author_filter::{Pallet, Call, Storage, Event<T>,}
+		AuthorInherent: author_inherent::{Pallet, Call, Storage, Inherent, Event<T>},
+		AuthorFilter: pallet_author_filter::{Pallet, Call, Storage, Event<T>,},
+		TokenDealer: pallet_token_dealer::{Pallet, Call, Event<T>},
 	}
 }
 
@@ -696,7 +753,7 @@ impl_runtime_apis! {
 			// TraceExecutorResult.
 			match transaction.action {
 				TransactionAction::Call(to) => {
-					if let Ok(res) = <Runtime as pallet_evm::Config>::Runner::trace_call(
+					if let Ok(res) = pallet_evm::runner::stack::Runner::<Runtime>::trace_call(
 						from,
 						to,
 						transaction.input.clone(),
@@ -711,7 +768,7 @@ impl_runtime_apis! {
 					}
 				},
 				TransactionAction::Create => {
-					if let Ok(res) = <Runtime as pallet_evm::Config>::Runner::trace_create(
+					if let Ok(res) = pallet_evm::runner::stack::Runner::<Runtime>::trace_create(
 						from,
 						transaction.input.clone(),
 						transaction.value,
@@ -768,7 +825,7 @@ impl_runtime_apis! {
 						// return the TraceExecutorResult.
 						let tx_traces = match transaction.action {
 							TransactionAction::Call(to) => {
-								<Runtime as pallet_evm::Config>::Runner::trace_call(
+								pallet_evm::runner::stack::Runner::<Runtime>::trace_call(
 									from,
 									to,
 									transaction.input.clone(),
@@ -780,7 +837,7 @@ impl_runtime_apis! {
 
 							},
 							TransactionAction::Create => {
-								<Runtime as pallet_evm::Config>::Runner::trace_create(
+								pallet_evm::runner::stack::Runner::<Runtime>::trace_create(
 									from,
 									transaction.input.clone(),
 									transaction.value,
@@ -814,6 +871,7 @@ impl_runtime_apis! {
 									block_hash: H256::default(),
 									// Can't be known here, must be inserted upstream.
 									block_number: 0,
+									logs: trace.logs,
 									output: match res {
 										CallResult::Output(output) => {
 											block::TransactionTraceOutput::Result(
@@ -824,6 +882,8 @@ impl_runtime_apis! {
 										},
 										CallResult::Error(error) =>
 											block::TransactionTraceOutput::Error(error),
+										CallResult::Revert(revert) =>
+											block::TransactionTraceOutput::Revert(revert),
 									},
 									subtraces: trace.subtraces,
 									trace_address: trace.trace_address,
@@ -843,6 +903,7 @@ impl_runtime_apis! {
 									block_hash: H256::default(),
 									// Can't be known here, must be inserted upstream.
 									block_number: 0,
+									logs: trace.logs,
 									output: match res {
 										CreateResult::Success {
 											created_contract_address_hash,
@@ -859,6 +920,8 @@ impl_runtime_apis! {
 										CreateResult::Error {
 											error
 										} => block::TransactionTraceOutput::Error(error),
+										CreateResult::Revert { revert } =>
+											block::TransactionTraceOutput::Revert(revert),
 									},
 									subtraces: trace.subtraces,
 									trace_address: trace.trace_address,
@@ -880,6 +943,7 @@ impl_runtime_apis! {
 									block_hash: H256::default(),
 									// Can't be known here, must be inserted upstream.
 									block_number: 0,
+									logs: trace.logs,
 									output: block::TransactionTraceOutput::Result(
 												block::TransactionTraceResult::Suicide
 											),
@@ -903,6 +967,143 @@ impl_runtime_apis! {
 
 			Ok(traces)
 		}
+
+		fn trace_block_transactions(
+			extrinsics: Vec<<Block as BlockT>::Extrinsic>,
+			trace_type: thales_rpc_primitives_debug::single::TraceType,
+		) -> Result<
+			Vec<thales_rpc_primitives_debug::single::TransactionTrace>,
+			sp_runtime::DispatchError
+		> {
+			let mut config = <Runtime as pallet_evm::Config>::config().clone();
+			config.estimate = true;
+
+			let mut traces = vec![];
+
+			// Apply all extrinsics. Ethereum extrinsics are traced with the requested trace type.
+			for ext in extrinsics.into_iter() {
+				match &ext.function {
+					Call::Ethereum(transact(transaction)) => {
+						// Get the caller;
+						let mut sig = [0u8; 65];
+						let mut msg = [0u8; 32];
+						sig[0..32].copy_from_slice(&transaction.signature.r()[..]);
+						sig[32..64].copy_from_slice(&transaction.signature.s()[..]);
+						sig[64] = transaction.signature.standard_v();
+						msg.copy_from_slice(
+							&pallet_ethereum::TransactionMessage::from(transaction.clone())
+								.hash()[..]
+						);
+
+						let from = match sp_io::crypto::secp256k1_ecdsa_recover(&sig, &msg) {
+							Ok(pk) => H160::from(
+								H256::from_slice(Keccak256::digest(&pk).as_slice())
+							),
+							_ => H160::default()
+						};
+
+						let trace = match transaction.action {
+							TransactionAction::Call(to) => {
+								pallet_evm::runner::stack::Runner::<Runtime>::trace_call(
+									from,
+									to,
+									transaction.input.clone(),
+									transaction.value,
+									transaction.gas_limit.low_u64(),
+									&config,
+									trace_type,
+								).map_err(|_| sp_runtime::DispatchError::Other("Evm error"))?
+							},
+							TransactionAction::Create => {
+								pallet_evm::runner::stack::Runner::<Runtime>::trace_create(
+									from,
+									transaction.input.clone(),
+									transaction.value,
+									transaction.gas_limit.low_u64(),
+									&config,
+									trace_type,
+								).map_err(|_| sp_runtime::DispatchError::Other("Evm error"))?
+							}
+						};
+
+						traces.push(trace);
+					},
+					_ => {let _ = Executive::apply_extrinsic(ext); }
+				};
+			}
+
+			Ok(traces)
+		}
+
+		fn trace_call(
+			from: H160,
+			to: Option<H160>,
+			data: Vec<u8>,
+			value: U256,
+			gas_limit: U256,
+			state_overrides: Vec<(H160, thales_rpc_primitives_debug::single::StateOverride)>,
+			trace_type: thales_rpc_primitives_debug::single::TraceType,
+		) -> Result<
+			thales_rpc_primitives_debug::single::TransactionTrace,
+			sp_runtime::DispatchError
+		> {
+			// Overrides only live in the overlay of this runtime API call.
+			for (address, state_override) in state_overrides {
+				if let Some(balance) = state_override.balance {
+					let balance = balance.min(U256::from(Balance::max_value())).low_u128();
+					Balances::make_free_balance_be(&address, balance);
+				}
+				if let Some(nonce) = state_override.nonce {
+					let nonce = nonce.min(U256::from(Index::max_value())).low_u32();
+					frame_system::Account::<Runtime>::mutate(&address, |info| info.nonce = nonce);
+				}
+				if let Some(code) = state_override.code {
+					if code.is_empty() {
+						pallet_evm::AccountCodes::<Runtime>::remove(address);
+					} else {
+						pallet_evm::AccountCodes::<Runtime>::insert(address, code);
+					}
+				}
+				if let Some(state) = state_override.state {
+					pallet_evm::AccountStorages::<Runtime>::remove_prefix(address);
+					for (index, value) in state {
+						pallet_evm::AccountStorages::<Runtime>::insert(address, index, value);
+					}
+				}
+				for (index, value) in state_override.state_diff {
+					pallet_evm::AccountStorages::<Runtime>::insert(address, index, value);
+				}
+			}
+
+			if gas_limit > U256::from(u64::MAX) {
+				return Err(sp_runtime::DispatchError::Other("Gas limit exceeds u64"));
+			}
+			let gas_limit = gas_limit.low_u64();
+
+			let mut config = <Runtime as pallet_evm::Config>::config().clone();
+			config.estimate = true;
+
+			match to {
+				Some(to) => pallet_evm::runner::stack::Runner::<Runtime>::trace_call(
+					from,
+					to,
+					data,
+					value,
+					gas_limit,
+					&config,
+					trace_type,
+				),
+				None => pallet_evm::runner::stack::Runner::<Runtime>::trace_create(
+					from,
+					data,
+					value,
+					gas_limit,
+					&config,
+					trace_type,
+				),
+			}
+			.map_err(|_| sp_runtime::DispatchError::Other("Evm error"))
+		}
 	}
 
 	impl thales_rpc_primitives_txpool::TxPoolRuntimeApi<Block> for Runtime {
@@ -916,6 +1117,36 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl thales_primitives_author_history::AuthorHistoryApi<Block, AccountId, BlockNumber>
+		for Runtime
+	{
+		fn author_of(number: BlockNumber) -> Option<AccountId> {
+			AuthorInherent::author_history(number)
+		}
+
+		fn authors(from: BlockNumber, to: BlockNumber) -> Vec<(BlockNumber, AccountId)> {
+			AuthorInherent::authors(from, to)
+		}
+	}
+
+	impl thales_primitives_collator_stats::CollatorStatsApi<Block, AccountId> for Runtime {
+		fn current_round() -> u32 {
+			ParachainStaking::round().current
+		}
+
+		fn round_stats(round: u32) -> Vec<(AccountId, CollatorRoundStats)> {
+			AuthorFilter::round_stats(round)
+		}
+
+		fn collator_stats(
+			collator: AccountId,
+			from: u32,
+			to: u32,
+		) -> Vec<(u32, CollatorRoundStats)> {
+			AuthorFilter::collator_stats(&collator, from, to)
+		}
+	}
+
 	impl fp_rpc::EthereumRuntimeRPCApi<Block> for Runtime {
 		fn chain_id() -> u64 {
 			<Runtime as pallet_evm::Config>::ChainId::get()
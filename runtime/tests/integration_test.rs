@@ -19,21 +19,28 @@
 #![cfg(test)]
 
 use cumulus_primitives_parachain_inherent::ParachainInherentData;
-use evm::{Context, ExitSucceed};
+use evm::{Context, ExitError, ExitReason, ExitSucceed};
 use frame_support::{
 	assert_noop, assert_ok,
-	dispatch::Dispatchable,
-	traits::{GenesisBuild, OnFinalize, OnInitialize},
+	dispatch::{Dispatchable, GetDispatchInfo},
+	traits::{Get, GenesisBuild, OnFinalize, OnInitialize},
+	weights::constants::RocksDbWeight,
 };
 use thales_runtime::{
-	AccountId, AuthorInherent, Balance, Balances, Call, Event, InflationInfo, ParachainStaking,
-	Range, Runtime, System, GLMR,
+	AccountId, AuthorFilter, AuthorInherent, Balance, Balances, Call, Democracy, EnactmentPeriod,
+	Event, InflationInfo, LaunchPeriod, ParachainStaking, Range, Runtime, System, VotingPeriod,
+	GLMR,
 };
-use pallet_evm::PrecompileSet;
+use pallet_evm::{GasWeightMapping, PrecompileSet, Runner};
 use parachain_staking::Bond;
+use parity_scale_codec::Encode;
+use precompile_utils::{keccak256, EvmDataWriter};
 use precompiles::ThalesPrecompiles;
-use sp_core::{H160, U256};
-use sp_runtime::{DispatchError, Perbill};
+use sp_core::{H160, H256, U256};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	DispatchError, Perbill,
+};
 
 fn run_to_block(n: u32) {
 	while System::block_number() < n {
@@ -48,6 +55,20 @@ fn last_event() -> Event {
 	System::events().pop().expect("Event expected").event
 }
 
+/// Gas charged by the precompiles for each storage read.
+fn db_read_gas_cost() -> u64 {
+	<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(RocksDbWeight::get().read)
+}
+
+/// Event of an EVM log emitted by the staking precompile.
+fn staking_precompile_log(topics: Vec<H256>, data: Vec<u8>) -> Event {
+	Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Log(pallet_evm::Log {
+		address: H160::from_low_u64_be(2048),
+		topics,
+		data,
+	}))
+}
+
 struct ExtBuilder {
 	// endowed accounts with balances
 	balances: Vec<(AccountId, Balance)>,
@@ -135,12 +156,34 @@ impl ExtBuilder {
 		.assimilate_storage(&mut t)
 		.unwrap();
 
+		// Like the chain spec, put dummy code under the precompiles so contracts can call them.
+		pallet_evm::GenesisConfig {
+			accounts: ThalesPrecompiles::<Runtime>::used_addresses()
+				.map(|address| {
+					(
+						address,
+						pallet_evm::GenesisAccount {
+							nonce: Default::default(),
+							balance: Default::default(),
+							storage: Default::default(),
+							code: REVERT_BYTECODE.to_vec(),
+						},
+					)
+				})
+				.collect(),
+		}
+		.assimilate_storage::<Runtime>(&mut t)
+		.unwrap();
+
 		let mut ext = sp_io::TestExternalities::new(t);
 		ext.execute_with(|| System::set_block_number(1));
 		ext
 	}
 }
 
+/// The bytecode deployed by the chain spec under the precompiles (PUSH1 0x00 PUSH1 0x00 REVERT).
+const REVERT_BYTECODE: [u8; 5] = [0x60, 0x00, 0x60, 0x00, 0xFD];
+
 const ALICE: [u8; 20] = [4u8; 20];
 const BOB: [u8; 20] = [5u8; 20];
 const CHARLIE: [u8; 20] = [6u8; 20];
@@ -364,6 +407,36 @@ fn reward_block_authors() {
 		});
 }
 
+#[test]
+fn collator_stats_are_recorded() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.with_collators(vec![(AccountId::from(ALICE), 1_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			set_parachain_inherent_data();
+			for x in 2..5 {
+				set_author(AccountId::from(ALICE));
+				run_to_block(x);
+			}
+			// Alice is the only selected candidate so she is eligible at every height.
+			let round = ParachainStaking::round().current;
+			let stats = AuthorFilter::round_stats(round);
+			assert_eq!(stats.len(), 1);
+			assert_eq!(stats[0].0, AccountId::from(ALICE));
+			assert_eq!(stats[0].1.blocks_authored, 3);
+			assert_eq!(stats[0].1.times_eligible, 3);
+			assert_eq!(stats[0].1.authorship_ratio(), Perbill::one());
+			assert_eq!(
+				AuthorFilter::collator_stats(&AccountId::from(BOB), 0, round),
+				vec![]
+			);
+		});
+}
+
 #[test]
 fn join_candidates_via_precompile() {
 	ExtBuilder::default()
@@ -407,6 +480,13 @@ fn join_candidates_via_precompile() {
 					1000 * GLMR,
 					1000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("JoinedCandidates(address,uint256)").into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -457,6 +537,13 @@ fn leave_candidates_via_precompile() {
 					AccountId::from(ALICE),
 					3,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("LeftCandidates(address)").into(),
+						H160::from(ALICE).into(),
+					],
+					vec![],
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -508,6 +595,13 @@ fn go_online_offline_via_precompile() {
 					1,
 					AccountId::from(ALICE),
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("WentOffline(address)").into(),
+						H160::from(ALICE).into(),
+					],
+					vec![],
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -542,6 +636,13 @@ fn go_online_offline_via_precompile() {
 					1,
 					AccountId::from(ALICE),
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("WentOnline(address)").into(),
+						H160::from(ALICE).into(),
+					],
+					vec![],
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -601,6 +702,13 @@ fn candidate_bond_more_less_via_precompile() {
 					1_000 * GLMR,
 					2_000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("CandidateBondedMore(address,uint256)").into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(bond_more_amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -642,6 +750,13 @@ fn candidate_bond_more_less_via_precompile() {
 					2_000 * GLMR,
 					1_500 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("CandidateBondedLess(address,uint256)").into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(bond_less_amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -707,6 +822,14 @@ fn nominate_via_precompile() {
 					AccountId::from(ALICE),
 					2000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("Nominated(address,address,uint256)").into(),
+						H160::from(BOB).into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(nomination_amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -792,6 +915,13 @@ fn leave_nominators_via_precompile() {
 					AccountId::from(CHARLIE),
 					1_000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("LeftNominators(address)").into(),
+						H160::from(CHARLIE).into(),
+					],
+					vec![],
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -864,6 +994,14 @@ fn revoke_nomination_via_precompile() {
 					500 * GLMR,
 					1_000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("NominationRevoked(address,address)").into(),
+						H160::from(CHARLIE).into(),
+						H160::from(ALICE).into(),
+					],
+					vec![],
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -932,6 +1070,14 @@ fn nominator_bond_more_less_via_precompile() {
 					1_500 * GLMR,
 					2_000 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("NominationIncreased(address,address,uint256)").into(),
+						H160::from(BOB).into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(bond_more_amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -975,6 +1121,14 @@ fn nominator_bond_more_less_via_precompile() {
 					2_000 * GLMR,
 					1_500 * GLMR,
 				)),
+				staking_precompile_log(
+					vec![
+						keccak256!("NominationDecreased(address,address,uint256)").into(),
+						H160::from(BOB).into(),
+						H160::from(ALICE).into(),
+					],
+					EvmDataWriter::new().write(bond_less_amount).build(),
+				),
 				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
 					staking_precompile_address,
 				)),
@@ -1019,17 +1173,21 @@ fn is_nominator_via_precompile() {
 			// Expected result is an EVM boolean true which is 256 bits long.
 			let mut expected_bytes = Vec::from([0u8; 32]);
 			expected_bytes[31] = 1;
-			let expected_true_result = Some(Ok((ExitSucceed::Returned, expected_bytes, 0)));
+			let expected_true_result = Some(Ok((
+				ExitSucceed::Returned,
+				expected_bytes,
+				db_read_gas_cost(),
+			)));
 
 			// Assert precompile reports Bob is a nominator
 			assert_eq!(
 				ThalesPrecompiles::<Runtime>::execute(
 					staking_precompile_address,
 					&bob_input_data,
-					None, // target_gas is not necessary right now because consumed none now
+					None,
 					&Context {
 						// This context copied from Sacrifice tests, it's not great.
-						address: Default::default(),
+						address: staking_precompile_address,
 						caller: Default::default(),
 						apparent_value: From::from(0),
 					}
@@ -1044,7 +1202,11 @@ fn is_nominator_via_precompile() {
 
 			// Expected result is an EVM boolean false which is 256 bits long.
 			expected_bytes = Vec::from([0u8; 32]);
-			let expected_false_result = Some(Ok((ExitSucceed::Returned, expected_bytes, 0)));
+			let expected_false_result = Some(Ok((
+				ExitSucceed::Returned,
+				expected_bytes,
+				db_read_gas_cost(),
+			)));
 
 			// Assert precompile also reports Charlie as not a nominator
 			assert_eq!(
@@ -1054,7 +1216,7 @@ fn is_nominator_via_precompile() {
 					None,
 					&Context {
 						// This context copied from Sacrifice tests, it's not great.
-						address: Default::default(),
+						address: staking_precompile_address,
 						caller: Default::default(),
 						apparent_value: From::from(0),
 					}
@@ -1084,17 +1246,21 @@ fn is_candidate_via_precompile() {
 			// Expected result is an EVM boolean true which is 256 bits long.
 			let mut expected_bytes = Vec::from([0u8; 32]);
 			expected_bytes[31] = 1;
-			let expected_true_result = Some(Ok((ExitSucceed::Returned, expected_bytes, 0)));
+			let expected_true_result = Some(Ok((
+				ExitSucceed::Returned,
+				expected_bytes,
+				db_read_gas_cost(),
+			)));
 
 			// Assert precompile reports Alice is a collator candidate
 			assert_eq!(
 				ThalesPrecompiles::<Runtime>::execute(
 					staking_precompile_address,
 					&alice_input_data,
-					None, // target_gas is not necessary right now because consumed none now
+					None,
 					&Context {
 						// This context copied from Sacrifice tests, it's not great.
-						address: Default::default(),
+						address: staking_precompile_address,
 						caller: Default::default(),
 						apparent_value: From::from(0),
 					}
@@ -1109,7 +1275,11 @@ fn is_candidate_via_precompile() {
 
 			// Expected result is an EVM boolean false which is 256 bits long.
 			expected_bytes = Vec::from([0u8; 32]);
-			let expected_false_result = Some(Ok((ExitSucceed::Returned, expected_bytes, 0)));
+			let expected_false_result = Some(Ok((
+				ExitSucceed::Returned,
+				expected_bytes,
+				db_read_gas_cost(),
+			)));
 
 			// Assert precompile also reports Bob as not a collator candidate
 			assert_eq!(
@@ -1119,7 +1289,7 @@ fn is_candidate_via_precompile() {
 					None,
 					&Context {
 						// This context copied from Sacrifice tests, it's not great.
-						address: Default::default(),
+						address: staking_precompile_address,
 						caller: Default::default(),
 						apparent_value: From::from(0),
 					}
@@ -1150,7 +1320,7 @@ fn min_nomination_via_precompile() {
 				None,
 				&Context {
 					// This context copied from Sacrifice tests, it's not great.
-					address: Default::default(),
+					address: staking_precompile_address,
 					caller: Default::default(),
 					apparent_value: From::from(0),
 				}
@@ -1159,3 +1329,1515 @@ fn min_nomination_via_precompile() {
 		);
 	});
 }
+
+#[test]
+fn staking_views_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.with_collators(vec![(AccountId::from(ALICE), 1_000 * GLMR)])
+		.with_nominators(vec![(
+			AccountId::from(BOB),
+			AccountId::from(ALICE),
+			500 * GLMR,
+		)])
+		.build()
+		.execute_with(|| {
+			set_parachain_inherent_data();
+			set_author(AccountId::from(ALICE));
+
+			let staking_precompile_address = H160::from_low_u64_be(2048);
+			// Call a view with the given selector and 32 bytes arguments, check it was charged a
+			// single storage read and return its output.
+			let view = |selector: [u8; 4], args: &[[u8; 32]]| {
+				let mut input = selector.to_vec();
+				for arg in args {
+					input.extend_from_slice(arg);
+				}
+				match ThalesPrecompiles::<Runtime>::execute(
+					staking_precompile_address,
+					&input,
+					None,
+					&Context {
+						address: staking_precompile_address,
+						caller: Default::default(),
+						apparent_value: From::from(0),
+					},
+				) {
+					Some(Ok((ExitSucceed::Returned, output, gas))) => {
+						assert_eq!(gas, db_read_gas_cost());
+						output
+					}
+					other => panic!("Unexpected result {:?}", other),
+				}
+			};
+			let address = |account: [u8; 20]| H256::from(H160::from(account)).0;
+			let word = |value: u128| {
+				let mut buffer = [0u8; 32];
+				U256::from(value).to_big_endian(&mut buffer);
+				buffer.to_vec()
+			};
+
+			assert_eq!(
+				view(hex_literal::hex!("a29d46c6"), &[address(ALICE)]),
+				word(1_500 * GLMR)
+			);
+			assert_eq!(
+				view(hex_literal::hex!("1b4c6ef1"), &[address(ALICE)]),
+				word(1_000 * GLMR)
+			);
+			assert_eq!(
+				view(hex_literal::hex!("1b4c6ef1"), &[address(CHARLIE)]),
+				word(0)
+			);
+			assert_eq!(
+				view(hex_literal::hex!("0ad6a7be"), &[address(ALICE)]),
+				word(1)
+			);
+			assert_eq!(
+				view(
+					hex_literal::hex!("a19a26cb"),
+					&[address(BOB), address(ALICE)]
+				),
+				word(500 * GLMR)
+			);
+			assert_eq!(
+				view(
+					hex_literal::hex!("a19a26cb"),
+					&[address(BOB), address(CHARLIE)]
+				),
+				word(0)
+			);
+			assert_eq!(
+				view(hex_literal::hex!("b6c29bcd"), &[address(BOB)]),
+				word(500 * GLMR)
+			);
+
+			// Dynamic array of a single address.
+			let mut selected = word(32);
+			selected.extend(word(1));
+			selected.extend_from_slice(&address(ALICE));
+			assert_eq!(view(hex_literal::hex!("89f47a21"), &[]), selected);
+
+			assert_eq!(view(hex_literal::hex!("146ca531"), &[]), word(1));
+			assert_eq!(view(hex_literal::hex!("e0811720"), &[]), word(0));
+
+			let mut round_one = [0u8; 32];
+			round_one[31] = 1;
+			assert_eq!(view(hex_literal::hex!("9799b4e7"), &[round_one]), word(20));
+			assert_eq!(
+				view(
+					hex_literal::hex!("075a156e"),
+					&[round_one, address(ALICE)]
+				),
+				word(20)
+			);
+			assert_eq!(
+				view(hex_literal::hex!("075a156e"), &[round_one, address(BOB)]),
+				word(0)
+			);
+
+			// 20% in parts per billion.
+			assert_eq!(
+				view(hex_literal::hex!("b0c0081f"), &[]),
+				word(200_000_000)
+			);
+		})
+}
+
+#[test]
+fn staking_precompile_reports_pallet_error_name() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.with_collators(vec![(AccountId::from(ALICE), 1_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let staking_precompile_address = H160::from_low_u64_be(2048);
+			let context = Context {
+				address: staking_precompile_address,
+				caller: H160::from(BOB),
+				apparent_value: From::from(0),
+			};
+
+			// Bob nominates Alice below the minimum nomination
+			let mut call_data = Vec::<u8>::from([0u8; 68]);
+			call_data[0..4].copy_from_slice(&hex_literal::hex!("82f2c8df"));
+			call_data[16..36].copy_from_slice(&ALICE);
+			U256::from(GLMR).to_big_endian(&mut call_data[36..68]);
+
+			assert_eq!(
+				ThalesPrecompiles::<Runtime>::execute(
+					staking_precompile_address,
+					&call_data,
+					None,
+					&context,
+				),
+				Some(Err(ExitError::Other("NomBondBelowMin".into())))
+			);
+
+			// Bob is not a nominator, so he cannot leave the nominators
+			assert_eq!(
+				ThalesPrecompiles::<Runtime>::execute(
+					staking_precompile_address,
+					&hex_literal::hex!("e8d68a37"),
+					None,
+					&context,
+				),
+				Some(Err(ExitError::Other("NominatorDNE".into())))
+			);
+
+			// Sent to the precompile, the call reverts with the error name as revert reason
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(BOB),
+				staking_precompile_address,
+				call_data,
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert_eq!(
+				info.value,
+				EvmDataWriter::new_with_selector(0x08c379a0)
+					.write(precompile_utils::Bytes::from(&b"NomBondBelowMin"[..]))
+					.build()
+			);
+		})
+}
+
+#[test]
+fn staking_precompile_rejects_malformed_input() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.with_collators(vec![(AccountId::from(ALICE), 1_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let staking_precompile_address = H160::from_low_u64_be(2048);
+			let context = Context {
+				address: staking_precompile_address,
+				caller: H160::from(BOB),
+				apparent_value: From::from(0),
+			};
+			let execute = |input: &[u8]| {
+				ThalesPrecompiles::<Runtime>::execute(
+					staking_precompile_address,
+					input,
+					None,
+					&context,
+				)
+				.expect("staking precompile is installed at 2048")
+			};
+
+			// Selectors of functions taking arguments, with the amount of 32 bytes arguments.
+			let selectors_with_args = [
+				(hex_literal::hex!("8e5080e7"), 1),
+				(hex_literal::hex!("8545c833"), 1),
+				(hex_literal::hex!("ad76ed5a"), 1),
+				(hex_literal::hex!("289b6ba7"), 1),
+				(hex_literal::hex!("c57bd3a8"), 1),
+				(hex_literal::hex!("82f2c8df"), 2),
+				(hex_literal::hex!("4b65c34b"), 1),
+				(hex_literal::hex!("f6a52569"), 2),
+				(hex_literal::hex!("971d44c8"), 2),
+			];
+
+			// Any truncation of the arguments is rejected.
+			for (selector, args) in selectors_with_args.iter() {
+				for len in 0..(args * 32) {
+					let mut input = selector.to_vec();
+					input.extend(vec![0u8; len]);
+					assert!(execute(&input).is_err(), "{:?} with {} bytes", selector, len);
+				}
+			}
+
+			// Unknown or truncated selectors are rejected.
+			assert!(execute(&[]).is_err());
+			assert!(execute(&hex_literal::hex!("82f2c8")).is_err());
+			assert!(execute(&hex_literal::hex!("deadbeef")).is_err());
+
+			// Amounts which don't fit in the balance type are rejected.
+			let mut input = hex_literal::hex!("82f2c8df").to_vec();
+			input.extend(vec![0u8; 12]);
+			input.extend(&ALICE);
+			input.extend(vec![0xffu8; 32]);
+			assert!(execute(&input).is_err());
+
+			// Pseudo-random arguments never panic.
+			let mut seed = 0x5eedu64;
+			for _ in 0..500 {
+				for (selector, args) in selectors_with_args.iter() {
+					let mut input = selector.to_vec();
+					for _ in 0..(args * 32 + 8) {
+						seed ^= seed << 13;
+						seed ^= seed >> 7;
+						seed ^= seed << 17;
+						input.push(seed as u8);
+					}
+					let _ = execute(&input);
+				}
+			}
+		})
+}
+
+#[test]
+fn erc20_views_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+			let view = |input: Vec<u8>| {
+				ThalesPrecompiles::<Runtime>::execute(
+					erc20_precompile_address,
+					&input,
+					None,
+					&Context {
+						address: erc20_precompile_address,
+						caller: Default::default(),
+						apparent_value: From::from(0),
+					},
+				)
+			};
+
+			assert_eq!(
+				view(EvmDataWriter::new_with_selector(0x06fdde03).build()),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new()
+						.write(precompile_utils::Bytes::from(&b"Thales Token"[..]))
+						.build(),
+					0
+				)))
+			);
+			assert_eq!(
+				view(EvmDataWriter::new_with_selector(0x95d89b41).build()),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new()
+						.write(precompile_utils::Bytes::from(&b"GLMR"[..]))
+						.build(),
+					0
+				)))
+			);
+			assert_eq!(
+				view(EvmDataWriter::new_with_selector(0x313ce567).build()),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new().write(18u8).build(),
+					0
+				)))
+			);
+			assert_eq!(
+				view(EvmDataWriter::new_with_selector(0x18160ddd).build()),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new().write(3_000 * GLMR).build(),
+					db_read_gas_cost()
+				)))
+			);
+			assert_eq!(
+				view(
+					EvmDataWriter::new_with_selector(0x70a08231)
+						.write(H160::from(BOB))
+						.build()
+				),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new().write(1_000 * GLMR).build(),
+					db_read_gas_cost()
+				)))
+			);
+			assert_eq!(
+				view(
+					EvmDataWriter::new_with_selector(0x70a08231)
+						.write(H160::from(CHARLIE))
+						.build()
+				),
+				Some(Ok((
+					ExitSucceed::Returned,
+					EvmDataWriter::new().write(0u128).build(),
+					db_read_gas_cost()
+				)))
+			);
+		})
+}
+
+#[test]
+fn erc20_transfer_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+
+			// Alice transfers to Bob through the EVM
+			let call_data = EvmDataWriter::new_with_selector(0xa9059cbb)
+				.write(H160::from(BOB))
+				.write(400 * GLMR)
+				.build();
+
+			assert_ok!(Call::EVM(pallet_evm::Call::<Runtime>::call(
+				AccountId::from(ALICE),
+				erc20_precompile_address,
+				call_data,
+				U256::zero(), // No value sent in EVM
+				100000u64,
+				1000.into(),
+				None, // Use the next nonce
+			))
+			.dispatch(<Runtime as frame_system::Config>::Origin::root()));
+
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 1_600 * GLMR);
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_400 * GLMR);
+
+			// Check for the right events.
+			let expected_events = vec![
+				Event::pallet_balances(pallet_balances::Event::Transfer(
+					AccountId::from(ALICE),
+					AccountId::from(BOB),
+					400 * GLMR,
+				)),
+				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Log(pallet_evm::Log {
+					address: erc20_precompile_address,
+					topics: vec![
+						keccak256!("Transfer(address,address,uint256)").into(),
+						H160::from(ALICE).into(),
+						H160::from(BOB).into(),
+					],
+					data: EvmDataWriter::new().write(400 * GLMR).build(),
+				})),
+				Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Executed(
+					erc20_precompile_address,
+				)),
+			];
+
+			assert_eq!(
+				System::events()
+					.into_iter()
+					.map(|e| e.event)
+					.collect::<Vec<_>>(),
+				expected_events
+			);
+		})
+}
+
+/// Bytecode of a contract forwarding its input to `target` with DELEGATECALL, reverting if the
+/// DELEGATECALL fails.
+fn delegating_caller(target: H160) -> Vec<u8> {
+	let mut code = vec![
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x37, // CALLDATACOPY
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x73, // PUSH20 target
+	];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[
+		0x5a, // GAS
+		0xf4, // DELEGATECALL
+		0x60, 0x2b, // PUSH1 0x2b
+		0x57, // JUMPI
+		0x60, 0x00, // PUSH1 0x00
+		0x80, // DUP1
+		0xfd, // REVERT
+		0x5b, // JUMPDEST (0x2b)
+		0x00, // STOP
+	]);
+	code
+}
+
+#[test]
+fn erc20_precompile_rejects_delegatecall() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+
+			let delegating_address = H160::from_low_u64_be(0xde1e);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				delegating_address,
+				delegating_caller(erc20_precompile_address),
+			);
+
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				delegating_address,
+				EvmDataWriter::new_with_selector(0xa9059cbb)
+					.write(H160::from(BOB))
+					.write(400 * GLMR)
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert!(info.logs.is_empty());
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_000 * GLMR);
+		})
+}
+
+#[test]
+fn staking_precompile_rejects_delegatecall() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 3_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let staking_precompile_address = H160::from_low_u64_be(2048);
+
+			let delegating_address = H160::from_low_u64_be(0xde1e);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				delegating_address,
+				delegating_caller(staking_precompile_address),
+			);
+
+			// Through DELEGATECALL, Alice would join the candidates and the log would be emitted
+			// by the delegating contract.
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				delegating_address,
+				EvmDataWriter::new_with_selector(0xad76ed5a)
+					.write(1000 * GLMR)
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert!(info.logs.is_empty());
+			assert!(!ParachainStaking::is_candidate(&AccountId::from(ALICE)));
+			assert_eq!(Balances::reserved_balance(AccountId::from(ALICE)), 0);
+		})
+}
+
+#[test]
+fn erc20_approve_and_transfer_from_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+			// Execute the precompile as `caller`, returning its output and logs.
+			let execute = |caller: [u8; 20], input: Vec<u8>| {
+				precompile_utils::collect_logs(|| {
+					ThalesPrecompiles::<Runtime>::execute(
+						erc20_precompile_address,
+						&input,
+						None,
+						&Context {
+							address: erc20_precompile_address,
+							caller: H160::from(caller),
+							apparent_value: From::from(0),
+						},
+					)
+					.expect("erc20 precompile is installed at 2049")
+					.map(|(_, output, _)| output)
+				})
+			};
+			let allowance = || {
+				execute(
+					CHARLIE,
+					EvmDataWriter::new_with_selector(0xdd62ed3e)
+						.write(H160::from(ALICE))
+						.write(H160::from(BOB))
+						.build(),
+				)
+				.0
+			};
+			let transfer_from = |amount: u128| {
+				execute(
+					BOB,
+					EvmDataWriter::new_with_selector(0x23b872dd)
+						.write(H160::from(ALICE))
+						.write(H160::from(CHARLIE))
+						.write(amount)
+						.build(),
+				)
+			};
+			let success = EvmDataWriter::new().write(true).build();
+
+			// Alice allows Bob to spend 500 GLMR
+			let (result, logs) = execute(
+				ALICE,
+				EvmDataWriter::new_with_selector(0x095ea7b3)
+					.write(H160::from(BOB))
+					.write(500 * GLMR)
+					.build(),
+			);
+			assert_eq!(result, Ok(success.clone()));
+			assert_eq!(
+				logs,
+				vec![pallet_evm::Log {
+					address: erc20_precompile_address,
+					topics: vec![
+						keccak256!("Approval(address,address,uint256)").into(),
+						H160::from(ALICE).into(),
+						H160::from(BOB).into(),
+					],
+					data: EvmDataWriter::new().write(500 * GLMR).build(),
+				}]
+			);
+			assert_eq!(
+				allowance(),
+				Ok(EvmDataWriter::new().write(500 * GLMR).build())
+			);
+
+			// Bob cannot spend more than allowed
+			assert_eq!(
+				transfer_from(600 * GLMR),
+				(
+					Err(ExitError::Other("trying to spend more than allowed".into())),
+					vec![]
+				)
+			);
+
+			// Bob transfers 300 GLMR from Alice to Charlie
+			let (result, logs) = transfer_from(300 * GLMR);
+			assert_eq!(result, Ok(success.clone()));
+			assert_eq!(
+				logs,
+				vec![pallet_evm::Log {
+					address: erc20_precompile_address,
+					topics: vec![
+						keccak256!("Transfer(address,address,uint256)").into(),
+						H160::from(ALICE).into(),
+						H160::from(CHARLIE).into(),
+					],
+					data: EvmDataWriter::new().write(300 * GLMR).build(),
+				}]
+			);
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 1_700 * GLMR);
+			assert_eq!(Balances::free_balance(AccountId::from(CHARLIE)), 300 * GLMR);
+			assert_eq!(
+				allowance(),
+				Ok(EvmDataWriter::new().write(200 * GLMR).build())
+			);
+
+			// A failed transfer leaves the allowance untouched
+			let approve = |amount: U256| {
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0x095ea7b3)
+						.write(H160::from(BOB))
+						.write(amount)
+						.build(),
+				)
+				.0
+			};
+			assert_eq!(approve((5_000 * GLMR).into()), Ok(success.clone()));
+			assert_eq!(
+				transfer_from(5_000 * GLMR),
+				(Err(ExitError::Other("InsufficientBalance".into())), vec![])
+			);
+			assert_eq!(
+				allowance(),
+				Ok(EvmDataWriter::new().write(5_000 * GLMR).build())
+			);
+
+			// The maximal allowance is not decreased
+			assert_eq!(approve(U256::max_value()), Ok(success.clone()));
+			assert_eq!(transfer_from(100 * GLMR).0, Ok(success));
+			assert_eq!(
+				allowance(),
+				Ok(EvmDataWriter::new().write(U256::max_value()).build())
+			);
+		})
+}
+
+#[test]
+fn democracy_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+			(AccountId::from(CHARLIE), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let democracy_precompile_address = H160::from_low_u64_be(2050);
+			let execute = |caller: [u8; 20], input: Vec<u8>| {
+				ThalesPrecompiles::<Runtime>::execute(
+					democracy_precompile_address,
+					&input,
+					None,
+					&Context {
+						address: democracy_precompile_address,
+						caller: H160::from(caller),
+						apparent_value: From::from(0),
+					},
+				)
+				.expect("democracy precompile is installed at 2050")
+				.map(|(_, output, _)| output)
+			};
+			let word = |value: u128| EvmDataWriter::new().write(value).build();
+
+			// Alice notes the preimage of a proposal and proposes it
+			let proposal = Call::System(frame_system::Call::remark(b"thales".to_vec())).encode();
+			let proposal_hash = BlakeTwo256::hash(&proposal);
+			assert_eq!(
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0x200881f5)
+						.write(precompile_utils::Bytes(proposal))
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0x7824e7d1)
+						.write(proposal_hash)
+						.write(10 * GLMR)
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(
+				execute(ALICE, EvmDataWriter::new_with_selector(0x56fdf547).build()),
+				Ok(word(1))
+			);
+			assert_eq!(
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0xa30305e9)
+						.write(0u32)
+						.build()
+				),
+				Ok(word(10 * GLMR))
+			);
+
+			// Bob seconds it
+			assert_eq!(
+				execute(
+					BOB,
+					EvmDataWriter::new_with_selector(0xc7a76601)
+						.write(0u32)
+						.write(10u32)
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(Balances::reserved_balance(AccountId::from(BOB)), 10 * GLMR);
+
+			// The proposal is tabled at the next launch period
+			let launch_block = LaunchPeriod::get();
+			System::set_block_number(launch_block);
+			Democracy::on_initialize(launch_block);
+			assert_eq!(
+				execute(ALICE, EvmDataWriter::new_with_selector(0x0388f282).build()),
+				Ok(word(0))
+			);
+
+			let ongoing_info = |ayes: u128, turnout: u128| {
+				EvmDataWriter::new()
+					.write(launch_block + VotingPeriod::get())
+					.write(proposal_hash)
+					.write(0u8)
+					.write(EnactmentPeriod::get())
+					.write(ayes)
+					.write(0u128)
+					.write(turnout)
+					.build()
+			};
+			let referendum_info = || {
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0x8b93d11a)
+						.write(0u32)
+						.build(),
+				)
+			};
+			assert_eq!(referendum_info(), Ok(ongoing_info(0, 0)));
+
+			// Charlie votes aye with a 1x conviction, then removes his vote
+			assert_eq!(
+				execute(
+					CHARLIE,
+					EvmDataWriter::new_with_selector(0x3f3c21cc)
+						.write(0u32)
+						.write(true)
+						.write(100 * GLMR)
+						.write(1u8)
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(referendum_info(), Ok(ongoing_info(100 * GLMR, 100 * GLMR)));
+			assert_eq!(
+				execute(
+					CHARLIE,
+					EvmDataWriter::new_with_selector(0x2042f50b)
+						.write(0u32)
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(referendum_info(), Ok(ongoing_info(0, 0)));
+
+			// The referendum is not finished yet
+			assert_eq!(
+				execute(
+					ALICE,
+					EvmDataWriter::new_with_selector(0xb1fd383f)
+						.write(0u32)
+						.build()
+				),
+				Err(ExitError::Other("Referendum is ongoing".into()))
+			);
+
+			// Bob delegates to Alice, then undelegates
+			assert_eq!(
+				execute(
+					BOB,
+					EvmDataWriter::new_with_selector(0x0185921e)
+						.write(H160::from(ALICE))
+						.write(2u8)
+						.write(50 * GLMR)
+						.build()
+				),
+				Ok(vec![])
+			);
+			assert_eq!(
+				execute(BOB, EvmDataWriter::new_with_selector(0xcb37b8ea).build()),
+				Ok(vec![])
+			);
+
+			// Pallet errors and invalid convictions are reported
+			assert_eq!(
+				execute(
+					CHARLIE,
+					EvmDataWriter::new_with_selector(0x7824e7d1)
+						.write(proposal_hash)
+						.write(GLMR)
+						.build()
+				),
+				Err(ExitError::Other("ValueLow".into()))
+			);
+			assert_eq!(
+				execute(
+					CHARLIE,
+					EvmDataWriter::new_with_selector(0x3f3c21cc)
+						.write(0u32)
+						.write(true)
+						.write(100 * GLMR)
+						.write(7u8)
+						.build()
+				),
+				Err(ExitError::Other("Must be an integer between 0 and 6".into()))
+			);
+		})
+}
+
+#[test]
+fn democracy_precompile_rejects_delegatecall() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let democracy_precompile_address = H160::from_low_u64_be(2050);
+
+			let delegating_address = H160::from_low_u64_be(0xde1e);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				delegating_address,
+				delegating_caller(democracy_precompile_address),
+			);
+
+			// Through DELEGATECALL, the proposal would be made with the deposit of Alice.
+			let proposal_hash = BlakeTwo256::hash(b"thales");
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				delegating_address,
+				EvmDataWriter::new_with_selector(0x7824e7d1)
+					.write(proposal_hash)
+					.write(10 * GLMR)
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert_eq!(Democracy::public_prop_count(), 0);
+			assert_eq!(Balances::reserved_balance(AccountId::from(ALICE)), 0);
+		})
+}
+
+#[test]
+fn token_dealer_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let token_dealer_precompile_address = H160::from_low_u64_be(2052);
+			let relay_account = H256::repeat_byte(3);
+
+			// Alice sends tokens to an account of the relay chain through the EVM
+			let call_data = EvmDataWriter::new_with_selector(0x536787a8)
+				.write(relay_account)
+				.write(400 * GLMR)
+				.build();
+
+			assert_ok!(Call::EVM(pallet_evm::Call::<Runtime>::call(
+				AccountId::from(ALICE),
+				token_dealer_precompile_address,
+				call_data,
+				U256::zero(), // No value sent in EVM
+				100000u64,
+				1000.into(),
+				None, // Use the next nonce
+			))
+			.dispatch(<Runtime as frame_system::Config>::Origin::root()));
+
+			// The tokens left this parachain
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 1_600 * GLMR);
+			assert_eq!(Balances::total_issuance(), 1_600 * GLMR);
+
+			let events = System::events()
+				.into_iter()
+				.map(|e| e.event)
+				.collect::<Vec<_>>();
+			assert!(events.contains(&Event::pallet_token_dealer(
+				pallet_token_dealer::Event::TransferredTokensToRelayChain(
+					AccountId::from(ALICE),
+					pallet_token_dealer::RelayAccountId::new(relay_account.0),
+					400 * GLMR,
+				)
+			)));
+			assert!(
+				events.contains(&Event::pallet_evm(pallet_evm::RawEvent::<AccountId>::Log(
+					pallet_evm::Log {
+						address: token_dealer_precompile_address,
+						topics: vec![
+							keccak256!("TransferredToRelayChain(address,bytes32,uint256)").into(),
+							H160::from(ALICE).into(),
+							relay_account,
+						],
+						data: EvmDataWriter::new().write(400 * GLMR).build(),
+					}
+				)))
+			);
+		})
+}
+
+#[test]
+fn token_dealer_precompile_requires_an_open_channel() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let token_dealer_precompile_address = H160::from_low_u64_be(2052);
+
+			// No HRMP channel is open to parachain 2000, the tokens stay with Alice
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				token_dealer_precompile_address,
+				EvmDataWriter::new_with_selector(0x0b8bd79f)
+					.write(2000u32)
+					.write(H160::from(BOB))
+					.write(400 * GLMR)
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert_eq!(
+				info.value,
+				EvmDataWriter::new_with_selector(0x08c379a0)
+					.write(precompile_utils::Bytes::from(&b"MessageNotSent"[..]))
+					.build()
+			);
+			assert!(info.logs.is_empty());
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 2_000 * GLMR);
+		})
+}
+
+#[test]
+fn token_dealer_precompile_rejects_delegatecall() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let token_dealer_precompile_address = H160::from_low_u64_be(2052);
+
+			let delegating_address = H160::from_low_u64_be(0xde1e);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				delegating_address,
+				delegating_caller(token_dealer_precompile_address),
+			);
+
+			// Through DELEGATECALL, the tokens of Alice would be sent to the relay chain.
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				delegating_address,
+				EvmDataWriter::new_with_selector(0x536787a8)
+					.write(H256::repeat_byte(3))
+					.write(400 * GLMR)
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert!(info.logs.is_empty());
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 2_000 * GLMR);
+		})
+}
+
+#[test]
+fn dispatch_via_precompile_is_filtered() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let dispatch_precompile_address = H160::from_low_u64_be(1024);
+			let execute = |input: Vec<u8>, target_gas: Option<u64>| {
+				ThalesPrecompiles::<Runtime>::execute(
+					dispatch_precompile_address,
+					&input,
+					target_gas,
+					&Context {
+						address: dispatch_precompile_address,
+						caller: H160::from(ALICE),
+						apparent_value: From::from(0),
+					},
+				)
+				.expect("dispatch precompile is installed at 1024")
+			};
+			let transfer = Call::Balances(pallet_balances::Call::<Runtime>::transfer(
+				AccountId::from(BOB),
+				400 * GLMR,
+			));
+
+			// The weight of the call is charged as gas
+			assert_eq!(execute(transfer.encode(), Some(1)), Err(ExitError::OutOfGas));
+			assert_eq!(
+				execute(transfer.encode(), None).map(|(_, output, gas)| (output, gas)),
+				Ok((
+					vec![],
+					<Runtime as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+						transfer.get_dispatch_info().weight,
+					),
+				))
+			);
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 1_600 * GLMR);
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_400 * GLMR);
+
+			// Calls re-entering the EVM or wrapping other calls are rejected
+			let not_allowed = Err(ExitError::Other("Call is not allowed".into()));
+			let evm_call = Call::EVM(pallet_evm::Call::<Runtime>::call(
+				AccountId::from(ALICE),
+				dispatch_precompile_address,
+				transfer.encode(),
+				U256::zero(),
+				100000u64,
+				1000.into(),
+				None,
+			));
+			assert_eq!(execute(evm_call.encode(), None), not_allowed);
+			let sudo_call =
+				Call::Sudo(pallet_sudo::Call::<Runtime>::sudo(Box::new(transfer.clone())));
+			assert_eq!(execute(sudo_call.encode(), None), not_allowed);
+			let batch_call =
+				Call::Utility(pallet_utility::Call::<Runtime>::batch(vec![transfer.clone()]));
+			assert_eq!(execute(batch_call.encode(), None), not_allowed);
+
+			// Nothing was dispatched
+			assert_eq!(Balances::free_balance(AccountId::from(ALICE)), 1_600 * GLMR);
+
+			assert_eq!(
+				execute(vec![0xff, 0xff, 0xff], None),
+				Err(ExitError::Other("Failed to decode call".into()))
+			);
+		})
+}
+
+/// Bytecode of a contract calling `target` the way Solidity high-level calls do: it reverts if
+/// `target` has no code, then forwards its input to `target` and returns or reverts with the
+/// returned data.
+fn high_level_caller(target: H160) -> Vec<u8> {
+	let mut code = vec![0x73]; // PUSH20 target
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[
+		0x3b, // EXTCODESIZE
+		0x60, 0x1d, // PUSH1 0x1d
+		0x57, // JUMPI
+		0x60, 0x00, // PUSH1 0x00
+		0x80, // DUP1
+		0xfd, // REVERT
+		0x5b, // JUMPDEST (0x1d)
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x37, // CALLDATACOPY
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x73, // PUSH20 target
+	]);
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[
+		0x5a, // GAS
+		0xf1, // CALL
+		0x3d, // RETURNDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x3e, // RETURNDATACOPY
+		0x60, 0x51, // PUSH1 0x51
+		0x57, // JUMPI
+		0x3d, // RETURNDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0xfd, // REVERT
+		0x5b, // JUMPDEST (0x51)
+		0x3d, // RETURNDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0xf3, // RETURN
+	]);
+	code
+}
+
+#[test]
+fn precompiles_can_be_called_from_contracts() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let caller_address = H160::from_low_u64_be(0xca11);
+			let call_through_contract = |target: H160, input: Vec<u8>| {
+				pallet_evm::AccountCodes::<Runtime>::insert(
+					caller_address,
+					high_level_caller(target),
+				);
+				<Runtime as pallet_evm::Config>::Runner::call(
+					H160::from(ALICE),
+					caller_address,
+					input,
+					U256::zero(),
+					1_000_000u64,
+					None,
+					None,
+					<Runtime as pallet_evm::Config>::config(),
+				)
+				.expect("call is executed")
+			};
+
+			// Inputs accepted by each precompile.
+			let transfer_nothing = Call::Balances(pallet_balances::Call::<Runtime>::transfer(
+				AccountId::from(ALICE),
+				0,
+			));
+			let input = |address: H160| match address.to_low_u64_be() {
+				5 => vec![0u8; 96],
+				1024 => transfer_nothing.encode(),
+				2048 => EvmDataWriter::new_with_selector(0xc9f593b2).build(),
+				2049 => EvmDataWriter::new_with_selector(0x18160ddd).build(),
+				2050 => EvmDataWriter::new_with_selector(0x56fdf547).build(),
+				2051 => EvmDataWriter::new_with_selector(0x79df4b9c)
+					.write(Vec::<H160>::new())
+					.write(Vec::<U256>::new())
+					.write(Vec::<precompile_utils::Bytes>::new())
+					.write(Vec::<u64>::new())
+					.build(),
+				2052 => EvmDataWriter::new_with_selector(0x536787a8)
+					.write(H256::zero())
+					.write(U256::zero())
+					.build(),
+				_ => vec![],
+			};
+
+			for address in ThalesPrecompiles::<Runtime>::used_addresses() {
+				let info = call_through_contract(address, input(address));
+				assert!(
+					matches!(info.exit_reason, ExitReason::Succeed(_)),
+					"call to precompile {:?} failed: {:?}",
+					address,
+					info.exit_reason
+				);
+			}
+
+			// The output of the precompile is returned
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+			assert_eq!(
+				call_through_contract(erc20_precompile_address, input(erc20_precompile_address))
+					.value,
+				EvmDataWriter::new().write(Balances::total_issuance()).build()
+			);
+
+			// Addresses without code can't be called
+			assert!(matches!(
+				call_through_contract(H160::from_low_u64_be(9), vec![]).exit_reason,
+				ExitReason::Revert(_)
+			));
+		})
+}
+
+/// Bytecode of a contract forwarding its input to `target`, then reverting whatever happened.
+fn reverting_caller(target: H160) -> Vec<u8> {
+	let mut code = vec![
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x37, // CALLDATACOPY
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x36, // CALLDATASIZE
+		0x60, 0x00, // PUSH1 0x00
+		0x60, 0x00, // PUSH1 0x00
+		0x73, // PUSH20 target
+	];
+	code.extend_from_slice(target.as_bytes());
+	code.extend_from_slice(&[
+		0x5a, // GAS
+		0xf1, // CALL
+		0x50, // POP
+		0x60, 0x00, // PUSH1 0x00
+		0x80, // DUP1
+		0xfd, // REVERT
+	]);
+	code
+}
+
+#[test]
+fn precompile_logs_follow_the_evm_substate() {
+	ExtBuilder::default()
+		.with_balances(vec![(AccountId::from(ALICE), 2_000 * GLMR)])
+		.build()
+		.execute_with(|| {
+			let erc20_precompile_address = H160::from_low_u64_be(2049);
+			let reverting_address = H160::from_low_u64_be(0xdead);
+			let caller_address = H160::from_low_u64_be(0xca11);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				reverting_address,
+				reverting_caller(erc20_precompile_address),
+			);
+
+			// The caller emits an empty log, forwards its input to the precompile through the
+			// reverting contract, then directly, and emits another empty log.
+			let forward_input_to = |target: H160| {
+				let mut code = vec![
+					0x36, // CALLDATASIZE
+					0x60, 0x00, // PUSH1 0x00
+					0x60, 0x00, // PUSH1 0x00
+					0x37, // CALLDATACOPY
+					0x60, 0x00, // PUSH1 0x00
+					0x60, 0x00, // PUSH1 0x00
+					0x36, // CALLDATASIZE
+					0x60, 0x00, // PUSH1 0x00
+					0x60, 0x00, // PUSH1 0x00
+					0x73, // PUSH20 target
+				];
+				code.extend_from_slice(target.as_bytes());
+				code.extend_from_slice(&[
+					0x5a, // GAS
+					0xf1, // CALL
+					0x50, // POP
+				]);
+				code
+			};
+			let empty_log = [
+				0x60, 0x00, // PUSH1 0x00
+				0x80, // DUP1
+				0xa0, // LOG0
+			];
+			let mut caller_code = empty_log.to_vec();
+			caller_code.extend(forward_input_to(reverting_address));
+			caller_code.extend(forward_input_to(erc20_precompile_address));
+			caller_code.extend_from_slice(&empty_log);
+			caller_code.push(0x00); // STOP
+			pallet_evm::AccountCodes::<Runtime>::insert(caller_address, caller_code);
+
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				caller_address,
+				EvmDataWriter::new_with_selector(0x095ea7b3)
+					.write(H160::from(BOB))
+					.write(U256::from(100))
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+			assert!(matches!(info.exit_reason, ExitReason::Succeed(_)));
+
+			// The approval of the reverted call is dropped, the other one keeps its place.
+			let approval: H256 = keccak256!("Approval(address,address,uint256)").into();
+			assert_eq!(
+				info.logs
+					.iter()
+					.map(|log| (log.address, log.topics.first().cloned()))
+					.collect::<Vec<_>>(),
+				vec![
+					(caller_address, None),
+					(erc20_precompile_address, Some(approval)),
+					(caller_address, None),
+				]
+			);
+
+			// Only those logs are deposited as events.
+			let logs: Vec<_> = System::events()
+				.into_iter()
+				.filter_map(|record| match record.event {
+					Event::pallet_evm(pallet_evm::RawEvent::Log(log)) => Some(log),
+					_ => None,
+				})
+				.collect();
+			assert_eq!(logs, info.logs);
+		})
+}
+
+#[test]
+fn batch_via_precompile() {
+	ExtBuilder::default()
+		.with_balances(vec![
+			(AccountId::from(ALICE), 2_000 * GLMR),
+			(AccountId::from(BOB), 1_000 * GLMR),
+		])
+		.build()
+		.execute_with(|| {
+			let batch_precompile_address = H160::from_low_u64_be(2051);
+
+			// A contract reverting with `Error("nope")`, copying the revert data from its code.
+			let reverting_address = H160::from_low_u64_be(0xdead);
+			let mut reverting_code = vec![
+				0x60, 0x64, // PUSH1 0x64 (revert data length)
+				0x60, 0x0c, // PUSH1 0x0c (revert data offset in the code)
+				0x60, 0x00, // PUSH1 0x00
+				0x39, // CODECOPY
+				0x60, 0x64, // PUSH1 0x64
+				0x60, 0x00, // PUSH1 0x00
+				0xfd, // REVERT
+			];
+			let mut revert_data = vec![0x08, 0xc3, 0x79, 0xa0];
+			revert_data.extend(
+				EvmDataWriter::new()
+					.write(precompile_utils::Bytes::from(&b"nope"[..]))
+					.build(),
+			);
+			reverting_code.extend_from_slice(&revert_data);
+			pallet_evm::AccountCodes::<Runtime>::insert(reverting_address, reverting_code);
+
+			// A contract emitting a log of 32 zero bytes.
+			let logging_address = H160::from_low_u64_be(0x10c);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				logging_address,
+				vec![
+					0x60, 0x20, // PUSH1 0x20
+					0x60, 0x00, // PUSH1 0x00
+					0xa0, // LOG0
+					0x00, // STOP
+				],
+			);
+
+			// Alice sends 100 GLMR to Bob, then calls the reverting and the logging contracts.
+			let execute = |selector: u32| {
+				precompile_utils::collect_logs(|| {
+					ThalesPrecompiles::<Runtime>::execute(
+						batch_precompile_address,
+						&EvmDataWriter::new_with_selector(selector)
+							.write(vec![H160::from(BOB), reverting_address, logging_address])
+							.write(vec![100 * GLMR])
+							.write(Vec::<precompile_utils::Bytes>::new())
+							.write(vec![0u64, 100_000u64, 100_000u64])
+							.build(),
+						None,
+						&Context {
+							address: batch_precompile_address,
+							caller: H160::from(ALICE),
+							apparent_value: From::from(0),
+						},
+					)
+					.expect("batch precompile is installed at 2051")
+					.map(|(_, output, _)| output)
+				})
+			};
+			let subcall_log = |succeeded: bool, index: u32| pallet_evm::Log {
+				address: batch_precompile_address,
+				topics: vec![if succeeded {
+					keccak256!("SubcallSucceeded(uint256)").into()
+				} else {
+					keccak256!("SubcallFailed(uint256)").into()
+				}],
+				data: EvmDataWriter::new().write(index).build(),
+			};
+			let contract_log = pallet_evm::Log {
+				address: logging_address,
+				topics: vec![],
+				data: vec![0u8; 32],
+			};
+
+			// batchSome skips the failed subcall
+			assert_eq!(
+				execute(0x79df4b9c),
+				(
+					Ok(vec![]),
+					vec![
+						subcall_log(true, 0),
+						subcall_log(false, 1),
+						contract_log.clone(),
+						subcall_log(true, 2),
+					]
+				)
+			);
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_100 * GLMR);
+
+			// batchSomeUntilFailure stops at the failed subcall
+			assert_eq!(
+				execute(0xcf0491c7),
+				(Ok(vec![]), vec![subcall_log(true, 0), subcall_log(false, 1)])
+			);
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_200 * GLMR);
+
+			// batchAll fails with the revert reason and data of the failed subcall, and the first
+			// subcall is rolled back. Executed in a transaction, the failure drops the logs.
+			let failure = format!(
+				"Subcall 1 failed: reverted with \"nope\" (0x{})",
+				sp_core::hexdisplay::HexDisplay::from(&revert_data)
+			);
+			assert_eq!(
+				execute(0x96e292b8),
+				(Err(ExitError::Other(failure.into())), vec![subcall_log(true, 0)])
+			);
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_200 * GLMR);
+
+			// The subcalls are sent by Alice, without increasing her nonce
+			assert_eq!(System::account_nonce(AccountId::from(ALICE)), 0);
+
+			// Sent through the runner, the failed batchAll has no effect and emits no log.
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				batch_precompile_address,
+				EvmDataWriter::new_with_selector(0x96e292b8)
+					.write(vec![H160::from(BOB), reverting_address])
+					.write(vec![100 * GLMR])
+					.write(Vec::<precompile_utils::Bytes>::new())
+					.write(Vec::<u64>::new())
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert!(info.logs.is_empty());
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), 1_200 * GLMR);
+
+			// The subcalls are recorded for the tracer, after the logs of the previous ones.
+			let (_, logs, calls) = precompile_utils::collect_logs_and_calls(|| {
+				ThalesPrecompiles::<Runtime>::execute(
+					batch_precompile_address,
+					&EvmDataWriter::new_with_selector(0x79df4b9c)
+						.write(vec![reverting_address, logging_address])
+						.write(Vec::<U256>::new())
+						.write(Vec::<precompile_utils::Bytes>::new())
+						.write(Vec::<u64>::new())
+						.build(),
+					None,
+					&Context {
+						address: batch_precompile_address,
+						caller: H160::from(ALICE),
+						apparent_value: From::from(0),
+					},
+				)
+			});
+			assert_eq!(
+				logs,
+				vec![subcall_log(false, 0), contract_log.clone(), subcall_log(true, 1)]
+			);
+			assert_eq!(
+				calls
+					.iter()
+					.map(|call| (call.source, call.target, call.log_index, call.logs.clone()))
+					.collect::<Vec<_>>(),
+				vec![
+					(H160::from(ALICE), reverting_address, 0, vec![]),
+					(H160::from(ALICE), logging_address, 1, vec![contract_log]),
+				]
+			);
+			assert_eq!(calls[0].output, revert_data);
+			assert!(matches!(calls[0].exit_reason, ExitReason::Revert(_)));
+
+			// Through DELEGATECALL, the subcalls would be sent by Alice instead of the contract.
+			let delegating_address = H160::from_low_u64_be(0xde1e);
+			pallet_evm::AccountCodes::<Runtime>::insert(
+				delegating_address,
+				delegating_caller(batch_precompile_address),
+			);
+			let bob_balance = Balances::free_balance(AccountId::from(BOB));
+			let info = <Runtime as pallet_evm::Config>::Runner::call(
+				H160::from(ALICE),
+				delegating_address,
+				EvmDataWriter::new_with_selector(0x79df4b9c)
+					.write(vec![H160::from(BOB)])
+					.write(vec![100 * GLMR])
+					.write(Vec::<precompile_utils::Bytes>::new())
+					.write(Vec::<u64>::new())
+					.build(),
+				U256::zero(),
+				1_000_000u64,
+				None,
+				None,
+				<Runtime as pallet_evm::Config>::config(),
+			)
+			.expect("call is executed");
+
+			assert!(matches!(info.exit_reason, ExitReason::Revert(_)));
+			assert_eq!(Balances::free_balance(AccountId::from(BOB)), bob_balance);
+		})
+}
@@ -0,0 +1,70 @@
+import { expect } from "chai";
+
+import { createAndFinalizeBlock } from "../util/block";
+import { customWeb3Request } from "../util/providers";
+import { describeDevThales } from "../util/setup-dev-tests";
+
+describeDevThales("Dev relay mock - relay parent number", (context) => {
+  it("should increase at every block", async function () {
+    await createAndFinalizeBlock(context.polkadotApi);
+    const first = (await context.polkadotApi.query.parachainSystem.validationData()).toJSON();
+    await createAndFinalizeBlock(context.polkadotApi);
+    const second = (await context.polkadotApi.query.parachainSystem.validationData()).toJSON();
+    expect(second["relayParentNumber"]).to.equal(first["relayParentNumber"] + 1);
+  });
+});
+
+describeDevThales("Dev relay mock - message injection", (context) => {
+  it("should accept a downward message", async function () {
+    const result = await customWeb3Request(context.web3, "dev_injectDownwardMessage", [
+      "0x010203",
+    ]);
+    expect(result.error).to.be.undefined;
+    // The block including the message must still pass the runtime's checks.
+    await createAndFinalizeBlock(context.polkadotApi);
+    expect(Number(await context.web3.eth.getBlockNumber())).to.equal(1);
+  });
+
+  it("should accept a horizontal message", async function () {
+    const result = await customWeb3Request(context.web3, "dev_injectHrmpMessage", [
+      2000,
+      "0x0405",
+    ]);
+    expect(result.error).to.be.undefined;
+    await createAndFinalizeBlock(context.polkadotApi);
+    expect(Number(await context.web3.eth.getBlockNumber())).to.equal(2);
+  });
+});
+
+describeDevThales("Dev relay mock - relay state entries", (context) => {
+  const storageRoot = async () =>
+    (await context.polkadotApi.query.parachainSystem.validationData()).toJSON()[
+      "relayParentStorageRoot"
+    ];
+
+  it("should include a set entry in the relay state proof", async function () {
+    await createAndFinalizeBlock(context.polkadotApi);
+    const before = await storageRoot();
+
+    const result = await customWeb3Request(context.web3, "dev_setRelayStateEntry", [
+      "0x0102",
+      "0x0304",
+    ]);
+    expect(result.error).to.be.undefined;
+    await createAndFinalizeBlock(context.polkadotApi);
+    expect(await storageRoot()).to.not.equal(before);
+  });
+
+  it("should remove an entry without a value", async function () {
+    await createAndFinalizeBlock(context.polkadotApi);
+    const withEntry = await storageRoot();
+
+    const result = await customWeb3Request(context.web3, "dev_setRelayStateEntry", [
+      "0x0102",
+      null,
+    ]);
+    expect(result.error).to.be.undefined;
+    await createAndFinalizeBlock(context.polkadotApi);
+    expect(await storageRoot()).to.not.equal(withEntry);
+  });
+});
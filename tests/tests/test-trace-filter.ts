@@ -1,8 +1,8 @@
 import { expect } from "chai";
 import { customWeb3Request } from "../util/providers";
 import { describeDevThales } from "../util/setup-dev-tests";
-import { createContract, createContractExecution } from "../util/transactions";
-import { GENESIS_ACCOUNT } from "../util/constants";
+import { createContract, createContractExecution, createTransaction } from "../util/transactions";
+import { GENESIS_ACCOUNT, TEST_ACCOUNT } from "../util/constants";
 
 const GENESIS_CONTRACT_ADDRESSES = [
   "0xc2bf5f29a4384b1ab0c063e1c666f02121b6084a",
@@ -85,6 +85,8 @@ describeDevThales("Trace filter - Contract creation ", (context) => {
     expect(response.result[0].blockNumber).to.equal(2);
     expect(response.result[0].result).to.equal(undefined);
     expect(response.result[0].error).to.equal("Reverted");
+    expect(response.result[0].output).to.equal("0x");
+    expect(response.result[0].revertReason).to.equal(undefined);
     expect(response.result[0].subtraces).to.equal(0);
     expect(response.result[0].traceAddress.length).to.equal(0);
     expect(response.result[0].transactionHash).to.equal(
@@ -188,7 +190,62 @@ describeDevThales("Trace filter - Contract creation ", (context) => {
       },
     ]);
 
-    expect(response.result.length).to.equal(4);
+    // The creation of the contract in block 3 and the 4 calls to it in block 4.
+    expect(response.result.length).to.equal(5);
+    expect(response.result[0].type).to.equal("create");
+  });
+
+  it("should match traces from or to the filtered addresses", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "0x03",
+        toBlock: "0x04",
+        fromAddress: [GENESIS_ACCOUNT],
+        toAddress: [GENESIS_CONTRACT_ADDRESSES[2]],
+      },
+    ]);
+
+    // The 3 traces of the genesis account, one being the creation of the contract, and the 4
+    // calls to the contract.
+    expect(response.result.length).to.equal(7);
+  });
+
+  it("should support block hashes and tags as range bounds", async function () {
+    const block = await context.web3.eth.getBlock(3);
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: block.hash,
+        toBlock: "pending",
+      },
+    ]);
+
+    expect(response.result.length).to.equal(9);
+    expect(response.result[0].blockNumber).to.equal(3);
+    expect(response.result[8].blockNumber).to.equal(4);
+  });
+
+  it("should ignore blocks after the best one", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "0x04",
+        toBlock: "0x100",
+      },
+    ]);
+
+    expect(response.result.length).to.equal(7);
+  });
+
+  it("should fail for an inverted block range", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "0x04",
+        toBlock: "0x03",
+      },
+    ]);
+    expect(response.error).to.deep.eq({
+      code: -32603,
+      message: "fromBlock (4) can't be greater than toBlock (3)",
+    });
   });
 
   it("should handle pagination", async function () {
@@ -232,4 +289,173 @@ describeDevThales("Trace filter - Contract creation ", (context) => {
       message: "count (501) can't be greater than maximum (500)",
     });
   });
+
+  it("should trace a block", async function () {
+    let response = await customWeb3Request(context.web3, "trace_block", ["0x03"]);
+
+    expect(response.result.length).to.equal(2);
+    expect(response.result[0].blockNumber).to.equal(3);
+    expect(response.result[0].transactionPosition).to.equal(0);
+    expect(response.result[1].blockNumber).to.equal(3);
+    expect(response.result[1].transactionPosition).to.equal(1);
+  });
+
+  it("should trace a transaction", async function () {
+    const block = await context.web3.eth.getBlock(4);
+    let response = await customWeb3Request(context.web3, "trace_transaction", [
+      block.transactions[0],
+    ]);
+
+    expect(response.result.length).to.equal(7);
+    expect(response.result[0].transactionHash).to.equal(block.transactions[0]);
+    expect(response.result[0].traceAddress).to.deep.equal([]);
+  });
+
+  it("should get a trace of a transaction by its address", async function () {
+    const block = await context.web3.eth.getBlock(4);
+    let response = await customWeb3Request(context.web3, "trace_get", [
+      block.transactions[0],
+      ["0x1", "0x0"],
+    ]);
+
+    expect(response.result.traceAddress).to.deep.equal([1, 0]);
+    expect(response.result.subtraces).to.equal(0);
+
+    let missing = await customWeb3Request(context.web3, "trace_get", [
+      block.transactions[0],
+      ["0x2"],
+    ]);
+    expect(missing.result).to.be.null;
+  });
+
+  it("should replay a transaction with its state diff", async function () {
+    const block = await context.web3.eth.getBlock(4);
+    let response = await customWeb3Request(context.web3, "trace_replayTransaction", [
+      block.transactions[0],
+      ["trace", "stateDiff"],
+    ]);
+
+    expect(response.result.trace.length).to.equal(7);
+    expect(response.result.vmTrace).to.be.null;
+    const sender = response.result.stateDiff[GENESIS_ACCOUNT.toLowerCase()];
+    expect(sender.balance).to.have.property("*");
+    expect(sender.code).to.equal("=");
+
+    let traceOnly = await customWeb3Request(context.web3, "trace_replayTransaction", [
+      block.transactions[0],
+      ["trace"],
+    ]);
+    expect(traceOnly.result.stateDiff).to.be.null;
+  });
+
+  it("should report the accounts created by a transaction", async function () {
+    const block = await context.web3.eth.getBlock(1);
+    let response = await customWeb3Request(context.web3, "trace_replayTransaction", [
+      block.transactions[0],
+      ["stateDiff"],
+    ]);
+
+    const created = response.result.stateDiff[GENESIS_CONTRACT_ADDRESSES[0]];
+    expect(created.balance).to.deep.equal({ "+": "0x0" });
+    expect(created.code).to.have.property("+");
+    const sender = response.result.stateDiff[GENESIS_ACCOUNT.toLowerCase()];
+    expect(sender.nonce).to.have.property("*");
+  });
+
+  it("should fail when VM traces are requested", async function () {
+    const block = await context.web3.eth.getBlock(4);
+    let response = await customWeb3Request(context.web3, "trace_replayTransaction", [
+      block.transactions[0],
+      ["trace", "vmTrace"],
+    ]);
+
+    expect(response.error).to.deep.eq({
+      code: -32603,
+      message: "vmTrace is not supported",
+    });
+  });
+});
+
+describeDevThales("Trace filter - Pending block", (context) => {
+  let txHash: string;
+
+  before("Setup: Send a transaction without including it in a block", async function () {
+    const { rawTx } = await createContract(context.web3, "TraceFilter", {}, [false]);
+    txHash = (await customWeb3Request(context.web3, "eth_sendRawTransaction", [rawTx])).result;
+  });
+
+  it("should trace the pending block", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "pending",
+        toBlock: "pending",
+      },
+    ]);
+
+    expect(response.result.length).to.equal(1);
+    expect(response.result[0]).to.include({
+      blockNumber: 1,
+      transactionHash: txHash,
+      type: "create",
+    });
+
+    let blockResponse = await customWeb3Request(context.web3, "trace_block", ["pending"]);
+    expect(blockResponse.result).to.deep.equal(response.result);
+  });
+
+  it("should trace the pending block after the blocks of the chain", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "latest",
+        toBlock: "pending",
+      },
+    ]);
+
+    expect(response.result.length).to.equal(1);
+    expect(response.result[0].transactionHash).to.equal(txHash);
+  });
+});
+
+describeDevThales("Trace filter - Batch precompile", (context) => {
+  const BATCH_PRECOMPILE_ADDRESS = "0x0000000000000000000000000000000000000803";
+
+  before("Setup: Send a batch of two transfers", async function () {
+    // batchSome(address[],uint256[],bytes[],uint64[])
+    const data =
+      "0x79df4b9c" +
+      context.web3.eth.abi
+        .encodeParameters(
+          ["address[]", "uint256[]", "bytes[]", "uint64[]"],
+          [[TEST_ACCOUNT, TEST_ACCOUNT], ["1000", "2000"], [], []]
+        )
+        .slice(2);
+    await context.createBlock({
+      transactions: [
+        await createTransaction(context.web3, { to: BATCH_PRECOMPILE_ADDRESS, data }),
+      ],
+    });
+  });
+
+  it("should trace the subcalls of the batch", async function () {
+    let response = await customWeb3Request(context.web3, "trace_filter", [
+      {
+        fromBlock: "0x01",
+        toBlock: "0x01",
+      },
+    ]);
+
+    expect(response.result.length).to.equal(3);
+    expect(response.result[0].subtraces).to.equal(2);
+    expect(response.result[0].traceAddress).to.deep.equal([]);
+    expect(response.result[0].action.to).to.equal(BATCH_PRECOMPILE_ADDRESS);
+    ["0x3e8", "0x7d0"].forEach((value, index) => {
+      expect(response.result[index + 1].subtraces).to.equal(0);
+      expect(response.result[index + 1].traceAddress).to.deep.equal([index]);
+      expect(response.result[index + 1].action).to.include({
+        from: GENESIS_ACCOUNT,
+        to: TEST_ACCOUNT,
+        value,
+      });
+    });
+  });
 });
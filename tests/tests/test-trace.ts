@@ -5,6 +5,7 @@ import { GENESIS_ACCOUNT, GENESIS_ACCOUNT_PRIVATE_KEY } from "../util/constants"
 import { createContract } from "../util/transactions";
 
 const BS_TRACER = require("../util/tracer/blockscout_tracer.min.json");
+const NESTED_STORAGE = require("../util/tracer/expected/nested_storage.json");
 
 async function nested(context) {
   let nonce = await context.web3.eth.getTransactionCount(GENESIS_ACCOUNT);
@@ -153,4 +154,263 @@ describeDevThales("Trace", (context) => {
     expect(resCallee.traceAddress.length).to.be.eq(1);
     expect(resCallee.traceAddress[0]).to.be.eq(0);
   });
+
+  it("should format as nested call frames (callTracer)", async function () {
+    const send = await nested(context);
+    await context.createBlock();
+    let traceTx = await customWeb3Request(context.web3, "debug_traceTransaction", [
+      send.result,
+      { tracer: "callTracer" },
+    ]);
+    let frame = traceTx.result;
+    expect(frame.type).to.be.equal("CALL");
+    expect(frame.from).to.be.equal(GENESIS_ACCOUNT.toLowerCase());
+    expect(frame.calls).to.be.lengthOf(1);
+    expect(frame.calls[0].type).to.be.equal("CALL");
+    expect(frame.calls[0].from).to.be.equal(frame.to);
+    expect(frame.calls[0]).to.not.have.property("calls");
+  });
+
+  it("should report the touched state (prestateTracer)", async function () {
+    const send = await nested(context);
+    await context.createBlock();
+    let traceTx = await customWeb3Request(context.web3, "debug_traceTransaction", [
+      send.result,
+      { tracer: "prestateTracer" },
+    ]);
+    let prestate = traceTx.result;
+    expect(prestate).to.have.property(GENESIS_ACCOUNT.toLowerCase());
+    expect(Object.keys(prestate)).to.have.lengthOf(3);
+
+    let diffTx = await customWeb3Request(context.web3, "debug_traceTransaction", [
+      send.result,
+      { tracer: "prestateTracer", tracerConfig: { diffMode: true } },
+    ]);
+    let { pre, post } = diffTx.result;
+    expect(Object.keys(pre)).to.have.members(Object.keys(post));
+  });
+
+  it("should trace all the transactions of a block", async function () {
+    const send = await nested(context);
+    await context.createBlock();
+    const block = await context.web3.eth.getBlock("latest");
+
+    let byNumber = await customWeb3Request(context.web3, "debug_traceBlockByNumber", [
+      context.web3.utils.numberToHex(block.number),
+      { tracer: "callTracer" },
+    ]);
+    expect(byNumber.result).to.be.lengthOf(1);
+    expect(byNumber.result[0].txHash).to.be.equal(send.result);
+    expect(byNumber.result[0].result.calls).to.be.lengthOf(1);
+
+    let byHash = await customWeb3Request(context.web3, "debug_traceBlockByHash", [
+      block.hash,
+      { tracer: BS_TRACER.body },
+    ]);
+    expect(byHash.result).to.be.lengthOf(1);
+    expect(byHash.result[0].txHash).to.be.equal(send.result);
+    expect(byHash.result[0].result).to.be.lengthOf(2);
+  });
+
+  it("should trace a call with overridden state", async function () {
+    // Returns the storage slot 0.
+    const code = "0x60005460005260206000f3";
+    const address = "0x1111111111111111111111111111111111111111";
+    const slot = "0x" + "00".repeat(32);
+    const value = "0x" + "00".repeat(31) + "2a";
+
+    let traceCall = await customWeb3Request(context.web3, "debug_traceCall", [
+      { from: GENESIS_ACCOUNT, to: address, gas: "0x100000" },
+      "latest",
+      {
+        tracer: "callTracer",
+        stateOverrides: { [address]: { code, stateDiff: { [slot]: value } } },
+      },
+    ]);
+    expect(traceCall.result.type).to.be.equal("CALL");
+    expect(traceCall.result.to).to.be.equal(address);
+    expect(traceCall.result.output).to.be.equal(value);
+  });
+
+  it("should reject a call with a gas limit above 64 bits", async function () {
+    let traceCall = await customWeb3Request(context.web3, "debug_traceCall", [
+      { from: GENESIS_ACCOUNT, to: GENESIS_ACCOUNT, gas: "0x10000000000000000" },
+      "latest",
+      { tracer: "callTracer" },
+    ]);
+    expect(traceCall.error.message).to.contain("DispatchError");
+  });
+
+  it("should include the logs of the call frames (callTracer withLog)", async function () {
+    // Emits a log with the topic 1 and the data 42.
+    const code = "0x602a600052600160206000a100";
+    const address = "0x1111111111111111111111111111111111111111";
+    const topic = "0x" + "00".repeat(31) + "01";
+    const data = "0x" + "00".repeat(31) + "2a";
+    const request = [{ from: GENESIS_ACCOUNT, to: address, gas: "0x100000" }, "latest"];
+
+    let withLog = await customWeb3Request(context.web3, "debug_traceCall", [
+      ...request,
+      {
+        tracer: "callTracer",
+        tracerConfig: { withLog: true },
+        stateOverrides: { [address]: { code } },
+      },
+    ]);
+    expect(withLog.result.logs).to.deep.equal([
+      { address, topics: [topic], data, position: "0x0" },
+    ]);
+
+    let withoutLog = await customWeb3Request(context.web3, "debug_traceCall", [
+      ...request,
+      { tracer: "callTracer", stateOverrides: { [address]: { code } } },
+    ]);
+    expect(withoutLog.result).to.not.have.property("logs");
+  });
+
+  it("should trace the precompiles with their logs (callTracer withLog)", async function () {
+    // approve(address,uint256) of the ERC-20 precompile, emitting an Approval log.
+    const erc20 = "0x0000000000000000000000000000000000000801";
+    const spender = "0x" + "11".repeat(20);
+    const amount = "0x" + "00".repeat(31) + "2a";
+    const data = "0x095ea7b3" + "00".repeat(12) + spender.slice(2) + amount.slice(2);
+
+    let trace = await customWeb3Request(context.web3, "debug_traceCall", [
+      { from: GENESIS_ACCOUNT, to: erc20, gas: "0x100000", data },
+      "latest",
+      { tracer: "callTracer", tracerConfig: { withLog: true } },
+    ]);
+    expect(trace.result.type).to.be.equal("CALL");
+    expect(trace.result).to.not.have.property("error");
+    expect(trace.result.output).to.be.equal("0x" + "00".repeat(31) + "01");
+    expect(trace.result.logs).to.deep.equal([
+      {
+        address: erc20,
+        topics: [
+          context.web3.utils.sha3("Approval(address,address,uint256)"),
+          "0x" + "00".repeat(12) + GENESIS_ACCOUNT.slice(2).toLowerCase(),
+          "0x" + "00".repeat(12) + spender.slice(2),
+        ],
+        data: amount,
+        position: "0x0",
+      },
+    ]);
+  });
+
+  it("should track the storage of nested calls like the geth structLogger", async function () {
+    const owner = "0x1111111111111111111111111111111111111111";
+    const library = "0x2222222222222222222222222222222222222222";
+    const other = "0x3333333333333333333333333333333333333333";
+    // Stores 1 in slot 0, delegate calls the library, calls the other contract, then stores 3
+    // in slot 2.
+    const ownerCode =
+      "0x6001600055" +
+      "6000600060006000" +
+      "73" +
+      library.slice(2) +
+      "5af450" +
+      "60006000600060006000" +
+      "73" +
+      other.slice(2) +
+      "5af150" +
+      "6003600255" +
+      "00";
+    // Loads slot 0, then stores 2 in slot 1.
+    const libraryCode = "0x60005450600260015500";
+    // Stores 5 in slot 0.
+    const otherCode = "0x600560005500";
+
+    let traceCall = await customWeb3Request(context.web3, "debug_traceCall", [
+      { from: GENESIS_ACCOUNT, to: owner, gas: "0x100000" },
+      "latest",
+      {
+        stateOverrides: {
+          [owner]: { code: ownerCode },
+          [library]: { code: libraryCode },
+          [other]: { code: otherCode },
+        },
+      },
+    ]);
+
+    // Like geth, compares the storage of the steps accessing it, with unprefixed keys and values.
+    const unprefixed = (hex: string) => hex.replace(/^0x/, "");
+    const storageLogs = traceCall.result.stepLogs
+      .filter((log) => log.op == "SLOAD" || log.op == "SSTORE")
+      .map((log) => ({
+        depth: log.depth,
+        op: log.op,
+        storage: Object.fromEntries(
+          Object.entries(log.storage).map(([key, value]) => [
+            unprefixed(key),
+            unprefixed(value as string),
+          ])
+        ),
+      }));
+    expect(storageLogs).to.deep.equal(NESTED_STORAGE.structLogs);
+  });
+
+  it("should report the errors and the gas refund of the steps", async function () {
+    const address = "0x1111111111111111111111111111111111111111";
+    const slot = "0x" + "00".repeat(32);
+    const value = "0x" + "00".repeat(31) + "01";
+    const request = [{ from: GENESIS_ACCOUNT, to: address, gas: "0x100000" }, "latest"];
+
+    // Clears slot 0, which is refunded.
+    let clear = await customWeb3Request(context.web3, "debug_traceCall", [
+      ...request,
+      { stateOverrides: { [address]: { code: "0x600060005500", stateDiff: { [slot]: value } } } },
+    ]);
+    const [sstore, stop] = clear.result.stepLogs.slice(-2);
+    expect(sstore.op).to.be.equal("SSTORE");
+    expect(sstore).to.not.have.property("refund");
+    expect(stop.op).to.be.equal("STOP");
+    expect(stop.refund).to.be.above(0);
+
+    // Jumps to 0, which is not a JUMPDEST.
+    let invalidJump = await customWeb3Request(context.web3, "debug_traceCall", [
+      ...request,
+      { stateOverrides: { [address]: { code: "0x600056" } } },
+    ]);
+    const jump = invalidJump.result.stepLogs[1];
+    expect(jump.op).to.be.equal("JUMP");
+    expect(jump.error).to.be.equal("invalid jump");
+    expect(invalidJump.result.stepLogs[0]).to.not.have.property("error");
+  });
+
+  it("should decode the panic reason of reverted calls (callTracer)", async function () {
+    const address = "0x1111111111111111111111111111111111111111";
+    // Reverts with Panic(1), the code of a failed assertion.
+    const code = "0x634e487b7160e01b600052600160045260246000fd";
+
+    let traceCall = await customWeb3Request(context.web3, "debug_traceCall", [
+      { from: GENESIS_ACCOUNT, to: address, gas: "0x100000" },
+      "latest",
+      { tracer: "callTracer", stateOverrides: { [address]: { code } } },
+    ]);
+    expect(traceCall.result.error).to.be.equal("execution reverted");
+    expect(traceCall.result.output).to.be.equal("0x4e487b71" + "00".repeat(31) + "01");
+    expect(traceCall.result.revertReason).to.be.equal("assert(false)");
+  });
+
+  it("should honour the timeout parameter", async function () {
+    const send = await nested(context);
+    await context.createBlock();
+    let traceTx = await customWeb3Request(context.web3, "debug_traceTransaction", [
+      send.result,
+      { timeout: "1ns" },
+    ]);
+    expect(traceTx.error).to.deep.eq({
+      code: -32603,
+      message: "Tracing timed out",
+    });
+
+    let invalid = await customWeb3Request(context.web3, "debug_traceTransaction", [
+      send.result,
+      { timeout: "soon" },
+    ]);
+    expect(invalid.error).to.deep.eq({
+      code: -32603,
+      message: "invalid timeout: soon",
+    });
+  });
 });